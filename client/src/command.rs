@@ -1,4 +1,9 @@
-use crate::keystore::wallet::CreateCmd;
+use crate::common::logger::LogFormat;
+use crate::common::metadata::CheckCmd;
+use crate::common::multisig::{DeriveCmd, VerifyCmd as VerifyMultisigCmd};
+use crate::keystore::wallet::{
+    ChangePasswordCmd, CreateCmd, ExportCmd, ImportCmd, InspectCmd, MigrateCmd, VerifyCmd,
+};
 use crate::kusama::client::StartRelayCmd;
 use crate::parallel::client::StartParaCmd;
 use structopt::StructOpt;
@@ -14,4 +19,54 @@ pub enum StakeClient {
 
     /// Run relay chain multi-sig account
     StartRelay(StartRelayCmd),
+
+    /// Check the runtime metadata of para chain and relay chain is compatible
+    Check(CheckCmd),
+
+    /// Manage keystore files
+    Keystore(KeystoreCmd),
+
+    /// Derive and verify multi-signature accounts
+    Multisig(MultisigCmd),
+}
+
+impl StakeClient {
+    /// The log format of the clients, the other commands print their output as text.
+    pub fn log_format(&self) -> LogFormat {
+        match self {
+            StakeClient::StartPara(cmd) => cmd.log_format,
+            StakeClient::StartRelay(cmd) => cmd.log_format,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum KeystoreCmd {
+    /// Migrate keystore file to the current version
+    Migrate(MigrateCmd),
+
+    /// Import polkadot-js JSON keystore with the multisig metadata
+    Import(ImportCmd),
+
+    /// Export keystore to polkadot-js JSON keystore
+    Export(ExportCmd),
+
+    /// Show the addresses of keystore and check the multi address
+    Inspect(InspectCmd),
+
+    /// Decrypt the key of keystore to check the password
+    Verify(VerifyCmd),
+
+    /// Change the password of keystore
+    ChangePassword(ChangePasswordCmd),
+}
+
+#[derive(Debug, StructOpt)]
+pub enum MultisigCmd {
+    /// Derive the multi-signature account of signatories under the prefix of each chain
+    Derive(DeriveCmd),
+
+    /// Check the multi address of keystore and the pool addresses match the signatories
+    Verify(VerifyMultisigCmd),
 }
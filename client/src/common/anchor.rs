@@ -0,0 +1,111 @@
+use crate::common::error::Error;
+
+use sp_runtime::traits::Header;
+use std::collections::VecDeque;
+use substrate_subxt::events::Raw;
+use substrate_subxt::{
+    system::System, Client, Event, EventStorageSubscription, EventsDecoder, RawEvent, Runtime,
+};
+
+/// The interval of the anchor blocks the listeners decide at.
+pub const LISTEN_ANCHOR_INTERVAL: u32 = 10;
+
+/// The hash of the anchor block, `back` anchors before the one of block `at`. The anchors are
+/// every `interval` blocks, so the signatories deciding around the same time read the same block.
+pub async fn anchor_block<T>(
+    subxt_client: &Client<T>,
+    at: T::Hash,
+    interval: u32,
+    back: u32,
+) -> Result<T::Hash, Error>
+where
+    T: Runtime + System<BlockNumber = u32>,
+{
+    let number = subxt_client
+        .header(Some(at))
+        .await?
+        .map(|header| *header.number())
+        .ok_or_else(|| Error::Other(format!("header of {:?} not found", at)))?;
+    let anchor = anchor_number(number, interval, back);
+    subxt_client
+        .block_hash(Some(anchor.into()))
+        .await?
+        .ok_or_else(|| Error::Other(format!("hash of block #{} not found", anchor)))
+}
+
+/// The hash of the latest finalized anchor block.
+pub async fn finalized_anchor<T>(subxt_client: &Client<T>, interval: u32) -> Result<T::Hash, Error>
+where
+    T: Runtime + System<BlockNumber = u32>,
+{
+    let head = subxt_client.finalized_head().await?;
+    anchor_block(subxt_client, head, interval, 0).await
+}
+
+/// The number of the anchor block, `back` anchors before the one of block `number`.
+pub fn anchor_number(number: u32, interval: u32, back: u32) -> u32 {
+    (number - number % interval).saturating_sub(back * interval)
+}
+
+/// The finalized events of one kind with the hash of the block containing them, which every
+/// signatory sees the same, unlike the head at the time the event is received.
+pub struct BlockEvents<'a, T: Runtime> {
+    subscription: EventStorageSubscription<T>,
+    decoder: &'a EventsDecoder<T>,
+    event: (&'static str, &'static str),
+    events: VecDeque<(T::Hash, RawEvent)>,
+}
+
+impl<'a, T: Runtime> BlockEvents<'a, T> {
+    /// Filter the events `E` of `subscription`.
+    pub fn new<E: Event<T>>(
+        subscription: EventStorageSubscription<T>,
+        decoder: &'a EventsDecoder<T>,
+    ) -> Self {
+        Self {
+            subscription,
+            decoder,
+            event: (E::MODULE, E::EVENT),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// The next event and the hash of its block, `None` if the subscription is closed.
+    pub async fn next(&mut self) -> Option<Result<(T::Hash, RawEvent), Error>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(Ok(event));
+            }
+            let change_set = self.subscription.next().await?;
+            for (_key, data) in change_set.changes {
+                let data = match data {
+                    Some(data) => data,
+                    None => continue,
+                };
+                let raw_events = match self.decoder.decode_events(&mut &data.0[..]) {
+                    Ok(raw_events) => raw_events,
+                    Err(e) => return Some(Err(Error::SubxtError(e))),
+                };
+                for (_phase, raw) in raw_events {
+                    match raw {
+                        Raw::Event(event)
+                            if (event.module.as_str(), event.variant.as_str()) == self.event =>
+                        {
+                            self.events.push_back((change_set.block, event))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_anchor_number() {
+    assert_eq!(anchor_number(1234, 100, 0), 1200);
+    assert_eq!(anchor_number(1299, 100, 0), 1200);
+    assert_eq!(anchor_number(1300, 100, 1), 1200);
+    assert_eq!(anchor_number(150, 100, 3), 0);
+    assert_eq!(anchor_number(1234, 10, 0), 1230);
+}
@@ -0,0 +1,96 @@
+use crate::common::constants::RelayConstants;
+use crate::common::error::Error;
+
+use codec::Encode;
+use runtime::rpc;
+use sp_core::{sr25519, Pair};
+use sp_runtime::traits::{IdentifyAccount, SignedExtension, Verify};
+use substrate_subxt::{Call, Client, PairSigner, Runtime, SignedExtra, Signer};
+
+/// The balance kept by an account when the client moves its funds, so the multisig operation
+/// moving them can still be paid and the account stays alive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BalanceReserve {
+    /// The minimum balance the account must keep alive.
+    pub existential_deposit: u128,
+    /// `DepositBase + DepositFactor * threshold` reserved when opening the multisig operation.
+    pub multisig_deposit: u128,
+    /// Kept on top of the rest, for the fees changing before the operation is executed.
+    pub buffer: u128,
+    /// The least amount the account bonds at once, zero if it doesn't bond.
+    pub min_bond: u128,
+}
+
+impl BalanceReserve {
+    /// The reserve of an account on relay chain, opening multisig operations of `threshold`.
+    pub fn relay(constants: &RelayConstants, threshold: u16, buffer: u128) -> Self {
+        Self {
+            existential_deposit: constants.existential_deposit,
+            multisig_deposit: constants.multisig_deposit(threshold),
+            buffer,
+            min_bond: constants.min_bond,
+        }
+    }
+
+    /// The reserve of the staking currency on para chain, the fees and deposits are paid in the
+    /// native currency so only the buffer is kept.
+    pub fn para(buffer: u128) -> Self {
+        Self {
+            buffer,
+            ..Default::default()
+        }
+    }
+
+    /// The balance of an account the client can move, the free balance less the frozen one,
+    /// the estimated fee of moving it and the reserve.
+    pub fn spendable(&self, free: u128, frozen: u128, fee: u128) -> u128 {
+        free.saturating_sub(frozen)
+            .saturating_sub(fee)
+            .saturating_sub(self.multisig_deposit)
+            .saturating_sub(self.existential_deposit)
+            .saturating_sub(self.buffer)
+    }
+}
+
+/// The seed of the key signing the extrinsics whose fee is estimated, never submitted.
+const FEE_ESTIMATION_SEED: [u8; 32] = [0u8; 32];
+
+/// Estimate the fee of the call with `payment_queryInfo` at block `at`, the latest block if
+/// `None`. The fee only depends on the call and the length of extrinsic, so it is signed by a
+/// fixed throwaway key with the nonce pinned to zero. No key is generated and no nonce queried,
+/// and the extrinsic has the same length for every signatory, so they estimate the same fee.
+pub async fn estimate_fee<T, C>(
+    subxt_client: &Client<T>,
+    call: C,
+    at: Option<T::Hash>,
+) -> Result<u128, Error>
+where
+    T: Runtime,
+    C: Call<T> + Send + Sync,
+    T::Signature: From<sr25519::Signature>,
+    <T::Signature as Verify>::Signer:
+        From<sr25519::Public> + IdentifyAccount<AccountId = T::AccountId>,
+    PairSigner<T, sr25519::Pair>: Signer<T> + Send + Sync,
+    <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+{
+    let pair = sr25519::Pair::from_seed(&FEE_ESTIMATION_SEED);
+    let mut signer = PairSigner::<T, sr25519::Pair>::new(pair);
+    signer.set_nonce(Default::default());
+    let extrinsic = subxt_client.create_signed(call, &signer).await?;
+    let info = rpc::query_info(subxt_client, extrinsic.encode(), at).await?;
+    Ok(info.partial_fee)
+}
+
+#[test]
+fn test_spendable() {
+    let reserve = BalanceReserve {
+        existential_deposit: 10,
+        multisig_deposit: 20,
+        buffer: 30,
+        min_bond: 0,
+    };
+    // free 1000, frozen 100, fee 40
+    assert_eq!(reserve.spendable(1000, 100, 40), 800);
+    assert_eq!(reserve.spendable(100, 50, 40), 0);
+    assert_eq!(BalanceReserve::para(30).spendable(1000, 100, 0), 870);
+}
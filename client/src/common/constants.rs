@@ -0,0 +1,104 @@
+use crate::common::error::Error;
+
+use codec::Decode;
+use core::marker::PhantomData;
+use runtime::pallets::staking::{MinNominatorBondStore, Staking};
+use substrate_subxt::{Client, Runtime};
+use tracing::info;
+
+/// The instructions executed on para chain for a reserve transfer from relay chain, the
+/// destination weight is bought for each of them.
+const RESERVE_TRANSFER_INSTRUCTIONS: u64 = 4;
+
+/// The pallet constants of relay chain which the client depends on, read from the runtime
+/// metadata when the client starts.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RelayConstants {
+    /// `Staking::BondingDuration`, number of eras that unbonded funds stay locked.
+    pub bonding_duration: u32,
+    /// `Staking::SessionsPerEra`, number of sessions in an era.
+    pub sessions_per_era: u32,
+    /// `Balances::ExistentialDeposit`, the minimum balance an account must keep alive.
+    pub existential_deposit: u128,
+    /// `Multisig::DepositBase`, the base deposit reserved when opening a multisig operation.
+    pub deposit_base: u128,
+    /// `Multisig::DepositFactor`, the deposit reserved per signatory of a multisig operation.
+    pub deposit_factor: u128,
+    /// The least amount bonded at once, `Staking::MinNominatorBond` but no less than the
+    /// existential deposit.
+    pub min_bond: u128,
+}
+
+impl RelayConstants {
+    /// Read all constants from the metadata of relay chain, and the minimum bond from storage.
+    pub async fn fetch<T: Runtime + Staking>(subxt_client: &Client<T>) -> Result<Self, Error> {
+        let existential_deposit = constant(subxt_client, "Balances", "ExistentialDeposit")?;
+        let min_nominator_bond = subxt_client
+            .fetch_or_default(
+                &MinNominatorBondStore::<T> {
+                    _runtime: PhantomData,
+                },
+                None,
+            )
+            .await?;
+        let constants = Self {
+            bonding_duration: constant(subxt_client, "Staking", "BondingDuration")?,
+            sessions_per_era: constant(subxt_client, "Staking", "SessionsPerEra")?,
+            existential_deposit,
+            deposit_base: constant(subxt_client, "Multisig", "DepositBase")?,
+            deposit_factor: constant(subxt_client, "Multisig", "DepositFactor")?,
+            min_bond: min_nominator_bond.max(existential_deposit),
+        };
+        info!("relay chain constants: {:?}", constants);
+        Ok(constants)
+    }
+
+    /// The number of sessions the unbonded funds stay locked.
+    pub fn unbonding_sessions(&self) -> u32 {
+        self.bonding_duration.saturating_mul(self.sessions_per_era)
+    }
+
+    /// The deposit reserved from the depositor when opening a multisig operation.
+    pub fn multisig_deposit(&self, threshold: u16) -> u128 {
+        self.deposit_base
+            .saturating_add(self.deposit_factor.saturating_mul(threshold as u128))
+    }
+}
+
+/// The pallet constants of para chain which the client depends on, read from the runtime
+/// metadata when the client starts.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParaConstants {
+    /// `XTokens::BaseXcmWeight`, the weight of an XCM instruction on para chain.
+    pub base_xcm_weight: u64,
+}
+
+impl ParaConstants {
+    /// Read all constants from the metadata of para chain.
+    pub fn fetch<T: Runtime>(subxt_client: &Client<T>) -> Result<Self, Error> {
+        let constants = Self {
+            base_xcm_weight: constant(subxt_client, "XTokens", "BaseXcmWeight")?,
+        };
+        info!("para chain constants: {:?}", constants);
+        Ok(constants)
+    }
+
+    /// The weight bought on para chain for a transfer from relay chain.
+    pub fn xcm_dest_weight(&self) -> u64 {
+        self.base_xcm_weight
+            .saturating_mul(RESERVE_TRANSFER_INSTRUCTIONS)
+    }
+}
+
+fn constant<T: Runtime, V: Decode>(
+    subxt_client: &Client<T>,
+    module: &str,
+    name: &'static str,
+) -> Result<V, Error> {
+    let value = subxt_client
+        .metadata()
+        .module(module)?
+        .constant(name)?
+        .value::<V>()?;
+    Ok(value)
+}
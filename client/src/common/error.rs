@@ -1,10 +1,11 @@
 use thiserror::Error as ThisError;
 use toml::de::Error as TomlError;
 
+use crate::keystore::error::KeystoreError;
 use core::fmt::Error as SerializeError;
 use runtime::error::Error as ClientRuntimeError;
 use std::io::Error as IoError;
-use substrate_subxt::Error as SubxtError;
+use substrate_subxt::{Error as SubxtError, MetadataError};
 
 #[derive(ThisError, Debug)]
 pub enum Error {
@@ -16,8 +17,14 @@ pub enum Error {
     SerializeError(#[from] SerializeError),
     #[error("Substrate Subxt Error: `{0:?}`")]
     SubxtError(#[from] SubxtError),
+    #[error("Metadata Error: `{0:?}`")]
+    MetadataError(#[from] MetadataError),
+    #[error("Keystore Error: {0}")]
+    KeystoreError(#[from] KeystoreError),
     #[error("Client runtime Error: `{0:?}`")]
     ClientRuntimeError(#[from] ClientRuntimeError),
+    #[error("Incompatible runtime, missing or changed: {0}")]
+    IncompatibleRuntime(String),
     #[error("Other error: {0}")]
     Other(String),
 }
@@ -0,0 +1,158 @@
+use crate::common::profile::ChainKind;
+
+use codec::Encode;
+use core::convert::TryFrom;
+use core::fmt;
+use sp_core::{hashing::blake2_64, H256};
+use std::str::FromStr;
+use tokio::sync::oneshot;
+use tracing::{field, info, info_span, Span};
+use tracing_subscriber::EnvFilter;
+
+/// The task sent by a listener to the dispatcher, with the span it is executed in and the sender
+/// of its result.
+pub type TaskMessage<T> = (T, Span, oneshot::Sender<u64>);
+
+/// The format of log lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable lines, the fields of spans follow the span names.
+    Text,
+    /// One JSON object per line, the fields of the current span under `span`.
+    Json,
+}
+
+impl LogFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format: {}", s)),
+        }
+    }
+}
+
+/// Install the logger of the process, filtered by `RUST_LOG` and `info` by default. The `log`
+/// records of the dependencies are forwarded to it.
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .try_init(),
+    };
+    if let Err(e) = result {
+        eprintln!("failed to initialize logger: {}", e);
+    }
+}
+
+/// The span a task is executed in, from the listener deciding it to the result of its multisig
+/// call. It carries a correlation id, the destination chain of the task, the amount and the block
+/// it was decided at if known. The call hash, and the amount of the tasks deciding it, are
+/// recorded during the execution.
+pub fn task_span(
+    chain: ChainKind,
+    task: &'static str,
+    amount: Option<u128>,
+    at: Option<H256>,
+) -> Span {
+    let id = task_id(task, amount, at);
+    let chain = match chain {
+        ChainKind::Para => "para",
+        ChainKind::Relay => "relay",
+    };
+    let span = info_span!(
+        "task",
+        id,
+        chain,
+        task,
+        amount = field::Empty,
+        block = field::Empty,
+        call_hash = field::Empty
+    );
+    if let Some(amount) = amount {
+        record_amount_in(&span, amount);
+    }
+    if let Some(at) = at {
+        span.record("block", &field::debug(at));
+    }
+    span.in_scope(|| info!("task created"));
+    span
+}
+
+/// Record the hash of the multisig call submitted by the current task.
+pub fn record_call_hash(call_hash: &[u8; 32]) {
+    Span::current().record(
+        "call_hash",
+        &format!("0x{}", hex::encode(call_hash)).as_str(),
+    );
+}
+
+/// Record the amount decided by the current task.
+pub fn record_amount(amount: u128) {
+    record_amount_in(&Span::current(), amount);
+}
+
+/// The correlation id of a task, derived from what decided it so the signatories deciding the
+/// same task log the same id, and it stays the same across restarts.
+fn task_id(task: &str, amount: Option<u128>, at: Option<H256>) -> u64 {
+    u64::from_le_bytes(blake2_64(&(task, amount, at).encode()))
+}
+
+/// Record the amount as a number, tracing has no 128-bit values so the amounts beyond `u64` are
+/// recorded as strings.
+fn record_amount_in(span: &Span, amount: u128) {
+    match u64::try_from(amount) {
+        Ok(amount) => span.record("amount", &amount),
+        Err(_) => span.record("amount", &amount.to_string().as_str()),
+    };
+}
+
+#[test]
+fn test_log_format() {
+    assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+    assert_eq!(LogFormat::Text.to_string(), "text");
+    assert!("yaml".parse::<LogFormat>().is_err());
+}
+
+#[test]
+fn test_task_id() {
+    let at = Some(H256::repeat_byte(1));
+    assert_eq!(
+        task_id("ParaStake", Some(1), at),
+        task_id("ParaStake", Some(1), at)
+    );
+    assert_ne!(
+        task_id("ParaStake", Some(1), at),
+        task_id("ParaStake", Some(2), at)
+    );
+    assert_ne!(
+        task_id("ParaStake", Some(1), at),
+        task_id("RelayBond", Some(1), at)
+    );
+    assert_ne!(
+        task_id("ParaStake", Some(1), at),
+        task_id("ParaStake", Some(1), None)
+    );
+}
@@ -0,0 +1,357 @@
+use crate::common::error::Error;
+use crate::common::origin::DispatchOrigin;
+use crate::common::profile::{build_client, Network};
+
+use core::fmt;
+use runtime::heiko::runtime::HeikoRuntime;
+use runtime::kusama::{api::XcmVersion, runtime::KusamaRuntime};
+use structopt::StructOpt;
+use substrate_subxt::{Client, Metadata, Runtime};
+use tracing::{error, info, warn};
+
+/// An item of runtime metadata the client depends on.
+#[derive(Clone, Copy, Debug)]
+pub enum Requirement {
+    /// A module, `module`.
+    Module(&'static str),
+    /// A call of module, `(module, call)`.
+    Call(&'static str, &'static str),
+    /// A storage item of module, `(module, storage)`.
+    Storage(&'static str, &'static str),
+    /// A constant of module, `(module, constant)`.
+    Constant(&'static str, &'static str),
+    /// An event of module with the types of its arguments, `(module, event, arguments)`.
+    Event(&'static str, &'static str, &'static [&'static str]),
+}
+
+/// The modules, calls, storage items, constants and events of relay chain the client depends on.
+pub const RELAY_REQUIREMENTS: &[Requirement] = &[
+    Requirement::Call("Staking", "bond"),
+    Requirement::Call("Staking", "bond_extra"),
+    Requirement::Call("Staking", "nominate"),
+    Requirement::Call("Staking", "unbond"),
+    Requirement::Call("Staking", "withdraw_unbonded"),
+    Requirement::Call("Multisig", "approve_as_multi"),
+    Requirement::Call("Multisig", "as_multi"),
+    Requirement::Storage("System", "Account"),
+    Requirement::Storage("Staking", "Bonded"),
+    Requirement::Storage("Staking", "CurrentEra"),
+    Requirement::Storage("Staking", "SlashingSpans"),
+    Requirement::Storage("Staking", "MinNominatorBond"),
+    Requirement::Storage("Multisig", "Multisigs"),
+    Requirement::Constant("Staking", "BondingDuration"),
+    Requirement::Constant("Staking", "SessionsPerEra"),
+    Requirement::Constant("Balances", "ExistentialDeposit"),
+    Requirement::Constant("Multisig", "DepositBase"),
+    Requirement::Constant("Multisig", "DepositFactor"),
+    Requirement::Event("Staking", "Reward", &["AccountId", "Balance"]),
+    Requirement::Event("Staking", "Slash", &["AccountId", "Balance"]),
+    Requirement::Event("Staking", "Unbonded", &["AccountId", "Balance"]),
+    Requirement::Event("Staking", "Withdrawn", &["AccountId", "Balance"]),
+];
+
+/// The calls and storage items of relay chain the client depends on in proxy mode.
+pub const PROXY_REQUIREMENTS: &[Requirement] = &[
+    Requirement::Call("Proxy", "proxy"),
+    Requirement::Storage("Proxy", "Proxies"),
+];
+
+/// The calls of relay chain the client depends on to bond through the multisig, the bond is
+/// batched with a remark of the anchor block.
+pub const ANCHORED_BOND_REQUIREMENTS: &[Requirement] = &[
+    Requirement::Call("System", "remark"),
+    Requirement::Call("Utility", "batch_all"),
+];
+
+/// The calls of relay chain the client depends on to batch the multisig calls.
+pub const BATCH_REQUIREMENTS: &[Requirement] = &[Requirement::Call("Utility", "batch_all")];
+
+/// The calls of relay chain the client depends on to transfer to para chain with XCM v0 or v1.
+pub const RESERVE_TRANSFER_REQUIREMENTS: &[Requirement] =
+    &[Requirement::Call("XcmPallet", "reserve_transfer_assets")];
+
+/// The calls of relay chain the client depends on to transfer to para chain with XCM v2.
+pub const LIMITED_RESERVE_TRANSFER_REQUIREMENTS: &[Requirement] = &[Requirement::Call(
+    "XcmPallet",
+    "limited_reserve_transfer_assets",
+)];
+
+/// The calls of relay chain the client depends on to transfer to para chain with the XCM version.
+pub fn xcm_requirements(version: XcmVersion) -> &'static [Requirement] {
+    match version {
+        XcmVersion::V0 | XcmVersion::V1 => RESERVE_TRANSFER_REQUIREMENTS,
+        XcmVersion::V2 => LIMITED_RESERVE_TRANSFER_REQUIREMENTS,
+    }
+}
+
+/// The calls of para chain the client depends on to transfer to relay chain.
+pub const XTOKENS_REQUIREMENTS: &[Requirement] = &[Requirement::Call("XTokens", "transfer")];
+
+/// The events of relay chain the client depends on to track the transfer from para chain.
+pub const RELAY_DEPOSIT_REQUIREMENTS: &[Requirement] = &[Requirement::Event(
+    "Balances",
+    "Deposit",
+    &["AccountId", "Balance"],
+)];
+
+/// The events and constants of para chain the client depends on to transfer from relay chain and
+/// track the transfer.
+pub const PARA_DEPOSIT_REQUIREMENTS: &[Requirement] = &[
+    Requirement::Event(
+        "Currencies",
+        "Deposited",
+        &["CurrencyId", "AccountId", "Balance"],
+    ),
+    Requirement::Constant("XTokens", "BaseXcmWeight"),
+];
+
+/// The calls of para chain the client depends on to wrap the calls in sudo.
+pub const SUDO_REQUIREMENTS: &[Requirement] = &[Requirement::Call("Sudo", "sudo")];
+
+/// The calls of para chain the client depends on to dispatch the calls by the council.
+pub const GOVERNANCE_REQUIREMENTS: &[Requirement] =
+    &[Requirement::Call("GeneralCouncil", "propose")];
+
+/// The modules, calls, storage items and events of para chain the client depends on.
+pub const PARA_REQUIREMENTS: &[Requirement] = &[
+    Requirement::Module("NomineeElection"),
+    Requirement::Call("LiquidStaking", "withdraw"),
+    Requirement::Call("LiquidStaking", "record_rewards"),
+    Requirement::Call("LiquidStaking", "record_slash"),
+    Requirement::Call("LiquidStaking", "process_pending_unstake"),
+    Requirement::Call("LiquidStaking", "finish_processed_unstake"),
+    Requirement::Call("Multisig", "approve_as_multi"),
+    Requirement::Call("Multisig", "as_multi"),
+    Requirement::Storage("Tokens", "Accounts"),
+    Requirement::Storage("Multisig", "Multisigs"),
+    Requirement::Storage("ParachainInfo", "ParachainId"),
+    Requirement::Event(
+        "LiquidStaking",
+        "Unstaked",
+        &["AccountId", "Balance", "Balance"],
+    ),
+];
+
+/// The requirements of para chain both clients depend on, with the calls of the origin.
+pub fn para_requirements(origin: DispatchOrigin) -> Vec<Requirement> {
+    [
+        PARA_REQUIREMENTS,
+        origin.requirements(),
+        PARA_DEPOSIT_REQUIREMENTS,
+    ]
+    .concat()
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Requirement::Module(module) => write!(f, "module {}", module),
+            Requirement::Call(module, call) => write!(f, "call {}.{}", module, call),
+            Requirement::Storage(module, storage) => write!(f, "storage {}.{}", module, storage),
+            Requirement::Constant(module, constant) => {
+                write!(f, "constant {}.{}", module, constant)
+            }
+            Requirement::Event(module, event, _) => write!(f, "event {}.{}", module, event),
+        }
+    }
+}
+
+impl Requirement {
+    /// Check the requirement against the metadata, returns the reason if it is not satisfied.
+    pub fn check(&self, metadata: &Metadata) -> Result<(), String> {
+        match *self {
+            Requirement::Module(module) => metadata
+                .module(module)
+                .map(|_| ())
+                .map_err(|e| format!("{:?}", e)),
+            Requirement::Call(module, call) => metadata
+                .module_with_calls(module)
+                .and_then(|m| m.call(call, ()))
+                .map(|_| ())
+                .map_err(|e| format!("{:?}", e)),
+            Requirement::Storage(module, storage) => metadata
+                .module(module)
+                .and_then(|m| m.storage(storage))
+                .map(|_| ())
+                .map_err(|e| format!("{:?}", e)),
+            Requirement::Constant(module, constant) => metadata
+                .module(module)
+                .and_then(|m| m.constant(constant))
+                .map(|_| ())
+                .map_err(|e| format!("{:?}", e)),
+            Requirement::Event(module, event, arguments) => {
+                let m = metadata
+                    .modules_with_events()
+                    .find(|m| m.name() == module)
+                    .ok_or(format!("module {} has no events", module))?;
+                let e = m
+                    .events()
+                    .find(|e| e.name == event)
+                    .ok_or("event not found".to_string())?;
+                let found: Vec<String> = e
+                    .arguments()
+                    .iter()
+                    .map(|arg| arg.primitives().join(", "))
+                    .collect();
+                let matched = found.len() == arguments.len()
+                    && found
+                        .iter()
+                        .zip(arguments.iter())
+                        .all(|(found, expected)| argument_type(found) == *expected);
+                if !matched {
+                    return Err(format!(
+                        "expected arguments {:?}, found {:?}",
+                        arguments, found
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The type of an event argument as named by the metadata, without the `T::` prefix and the
+/// `Of<T>` suffix the pallets may leave, e.g. `BalanceOf<T>` is `Balance`.
+fn argument_type(name: &str) -> &str {
+    let name = name.trim();
+    let name = name.strip_prefix("T::").unwrap_or(name);
+    name.strip_suffix("Of<T>").unwrap_or(name)
+}
+
+/// Check every requirement against the metadata of chain.
+pub fn check_requirements<T: Runtime>(
+    subxt_client: &Client<T>,
+    requirements: &[Requirement],
+) -> Vec<(Requirement, Result<(), String>)> {
+    requirements
+        .iter()
+        .map(|requirement| (*requirement, requirement.check(subxt_client.metadata())))
+        .collect()
+}
+
+/// The types used by events of chain whose size is not registered in the client builder.
+pub fn missing_type_sizes<T: Runtime>(subxt_client: &Client<T>) -> Vec<String> {
+    match subxt_client.events_decoder().check_missing_type_sizes() {
+        Ok(_) => vec![],
+        Err(missing) => {
+            let mut missing: Vec<String> = missing.into_iter().collect();
+            missing.sort();
+            missing
+        }
+    }
+}
+
+/// Make sure every requirement is satisfied by the metadata of chain.
+pub fn ensure_compatible<T: Runtime>(
+    subxt_client: &Client<T>,
+    chain: &str,
+    requirements: &[Requirement],
+) -> Result<(), Error> {
+    let mut incompatible = vec![];
+    for (requirement, result) in check_requirements(subxt_client, requirements) {
+        if let Err(e) = result {
+            error!("{} metadata incompatible, {}: {}", chain, requirement, e);
+            incompatible.push(requirement.to_string());
+        }
+    }
+    let missing = missing_type_sizes(subxt_client);
+    if !missing.is_empty() {
+        warn!("{} missing type sizes: {}", chain, missing.join(", "));
+    }
+    if !incompatible.is_empty() {
+        return Err(Error::IncompatibleRuntime(format!(
+            "{}: {}",
+            chain,
+            incompatible.join(", ")
+        )));
+    }
+    info!("{} metadata compatible", chain);
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CheckCmd {
+    /// the network to stake on: kusama, polkadot or dev
+    #[structopt(long, default_value = "dev")]
+    pub network: Network,
+
+    /// websocket server endpoint of para chain, defaults to the endpoint of para chain profile
+    #[structopt(long)]
+    pub para_ws: Option<String>,
+
+    /// websocket server endpoint of relay chain, defaults to the endpoint of relay chain profile
+    #[structopt(long)]
+    pub relay_ws: Option<String>,
+
+    /// how the multisig account dispatches the liquid staking calls: none, sudo or
+    /// governance[:<council threshold>]
+    #[structopt(long, default_value = "sudo")]
+    pub origin: DispatchOrigin,
+}
+
+impl CheckCmd {
+    /// Run the command
+    pub async fn run(&self) {
+        let mut passed = true;
+        let (para_chain, relay_chain) = (self.network.para_chain(), self.network.relay_chain());
+        let para_ws = para_chain.endpoint(&self.para_ws);
+        match build_client::<HeikoRuntime>(para_chain, &para_ws).await {
+            Ok(client) => {
+                let requirements = para_requirements(self.origin);
+                passed &= print_report(&client, "para chain", &requirements);
+            }
+            Err(e) => {
+                println!("[FAIL] para chain: {:?}", e);
+                passed = false;
+            }
+        }
+        let relay_ws = relay_chain.endpoint(&self.relay_ws);
+        match build_client::<KusamaRuntime>(relay_chain, &relay_ws).await {
+            Ok(client) => {
+                let xcm = xcm_requirements(relay_chain.xcm_version());
+                // the default mode bonds through the multisig
+                let requirements = [RELAY_REQUIREMENTS, ANCHORED_BOND_REQUIREMENTS, xcm].concat();
+                passed &= print_report(&client, "relay chain", &requirements);
+            }
+            Err(e) => {
+                println!("[FAIL] relay chain: {:?}", e);
+                passed = false;
+            }
+        }
+        if !passed {
+            println!("compatibility check failed");
+            std::process::exit(1);
+        }
+        println!("compatibility check passed");
+    }
+}
+
+fn print_report<T: Runtime>(
+    subxt_client: &Client<T>,
+    chain: &str,
+    requirements: &[Requirement],
+) -> bool {
+    println!("---------- {} ----------", chain);
+    let mut passed = true;
+    for (requirement, result) in check_requirements(subxt_client, requirements) {
+        match result {
+            Ok(_) => println!("[PASS] {}", requirement),
+            Err(e) => {
+                println!("[FAIL] {}: {}", requirement, e);
+                passed = false;
+            }
+        }
+    }
+    let missing = missing_type_sizes(subxt_client);
+    if !missing.is_empty() {
+        println!("[WARN] missing type sizes: {}", missing.join(", "));
+    }
+    passed
+}
+
+#[test]
+fn test_argument_type() {
+    assert_eq!(argument_type("T::AccountId"), "AccountId");
+    assert_eq!(argument_type("BalanceOf<T>"), "Balance");
+    assert_eq!(argument_type("Balance"), "Balance");
+}
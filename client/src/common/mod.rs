@@ -1,2 +1,12 @@
+pub(crate) mod anchor;
+pub(crate) mod balance;
+pub(crate) mod constants;
 pub(crate) mod error;
+pub(crate) mod logger;
+pub(crate) mod metadata;
+pub(crate) mod multisig;
+pub(crate) mod origin;
 pub(crate) mod primitives;
+pub(crate) mod profile;
+pub(crate) mod upgrade;
+pub(crate) mod xcm_tracker;
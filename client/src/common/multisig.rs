@@ -0,0 +1,254 @@
+use crate::common::primitives::AccountId;
+use crate::common::profile::{ChainProfile, Network};
+use crate::keystore::wallet::{check_multi_address, get_keystore};
+
+use codec::{Decode, Encode};
+use sp_core::{blake2_256, crypto::Ss58Codec};
+use structopt::StructOpt;
+
+/// Insert `who` into the sorted other signatories, the same check as `pallet_multisig`.
+pub fn ensure_sorted_and_insert(
+    other_signatories: Vec<AccountId>,
+    who: AccountId,
+) -> Result<Vec<AccountId>, String> {
+    let mut signatories = other_signatories;
+    let mut maybe_last = None;
+    let mut index = 0;
+    for item in signatories.iter() {
+        if let Some(last) = maybe_last {
+            if last >= item {
+                return Err("SignatoriesOutOfOrder".into());
+            }
+        }
+        if item <= &who {
+            if item == &who {
+                return Err("SenderInSignatories".into());
+            }
+            index += 1;
+        }
+        maybe_last = Some(item);
+    }
+    signatories.insert(index, who);
+    Ok(signatories)
+}
+
+/// The multisig account of the sorted signatories, the same as `pallet_multisig::multi_account_id`.
+pub fn multi_account_id(who: &[AccountId], threshold: u16) -> AccountId {
+    let entropy = (b"modlpy/utilisuba", who, threshold).using_encoded(blake2_256);
+    AccountId::decode(&mut &entropy[..]).unwrap_or_default()
+}
+
+/// Sort the signatories in any order and derive the multisig account of them.
+pub fn derive_multi_account_id(
+    signatories: &[AccountId],
+    threshold: u16,
+) -> Result<AccountId, String> {
+    let mut sorted = signatories.to_vec();
+    sorted.sort();
+    if sorted.windows(2).any(|w| w[0] == w[1]) {
+        return Err("DuplicateSignatories".into());
+    }
+    if sorted.len() < 2 {
+        return Err("TooFewSignatories".into());
+    }
+    if threshold < 2 || threshold as usize > sorted.len() {
+        return Err(format!(
+            "threshold {} is out of 2..={}",
+            threshold,
+            sorted.len()
+        ));
+    }
+    Ok(multi_account_id(&sorted, threshold))
+}
+
+/// The pallet id of `pallet_liquid_staking`, whose account is the pool of para chain.
+pub const LIQUID_STAKING_PALLET_ID: [u8; 8] = *b"par/lqsk";
+
+/// The account of the pallet, the same as `AccountIdConversion::into_account` of `PalletId`.
+pub fn pallet_account_id(pallet_id: [u8; 8]) -> AccountId {
+    let mut entropy = [0u8; 32];
+    entropy[..4].copy_from_slice(b"modl");
+    entropy[4..12].copy_from_slice(&pallet_id);
+    AccountId::decode(&mut &entropy[..]).unwrap_or_default()
+}
+
+/// Check the pool address of para chain is the account of liquid staking pallet, returns the
+/// reason if not.
+pub fn check_para_pool_address(pool_addr: &str) -> Result<(), String> {
+    let pool_account_id = AccountId::from_string(pool_addr)
+        .map_err(|_| format!("invalid pool address: {}", pool_addr))?;
+    if pool_account_id != pallet_account_id(LIQUID_STAKING_PALLET_ID) {
+        return Err(format!(
+            "pool address {} is not the account of liquid staking pallet {}",
+            pool_addr,
+            pallet_account_id(LIQUID_STAKING_PALLET_ID)
+        ));
+    }
+    Ok(())
+}
+
+/// Check the pool address is the multisig account, returns the reason if not.
+pub fn check_pool_address(pool_addr: &str, multi_account_id: &AccountId) -> Result<(), String> {
+    let pool_account_id = AccountId::from_string(pool_addr)
+        .map_err(|_| format!("invalid pool address: {}", pool_addr))?;
+    if &pool_account_id != multi_account_id {
+        return Err(format!(
+            "pool address {} is not the multisig account {}",
+            pool_addr, multi_account_id
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DeriveCmd {
+    /// the threshold of multi-signature account
+    #[structopt(short, long)]
+    pub threshold: u16,
+
+    /// the signatories of multi-signature account in any order, including the current one
+    #[structopt(short, long = "signatory")]
+    pub signatories: Vec<String>,
+}
+
+impl DeriveCmd {
+    /// Run the command
+    pub fn run(&self) {
+        let mut signatories = vec![];
+        for a in self.signatories.iter() {
+            match AccountId::from_string(a) {
+                Ok(account_id) => signatories.push(account_id),
+                Err(e) => return println!("invalid signatory {}: {:?}", a, e),
+            }
+        }
+        let multi_account_id = match derive_multi_account_id(&signatories, self.threshold) {
+            Ok(multi_account_id) => multi_account_id,
+            Err(e) => {
+                println!("failed to derive multisig account: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        println!("multisig account: 0x{}", hex::encode(&multi_account_id));
+        for profile in ChainProfile::ALL.iter() {
+            println!(
+                "    {:<10} {}",
+                profile.name(),
+                multi_account_id.to_ss58check_with_version(profile.ss58_format())
+            );
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct VerifyCmd {
+    /// the keystore whose signatories and threshold to verify against
+    #[structopt(short, long, default_value = "keystore.json")]
+    pub key_store: String,
+
+    /// the network of pool addresses: kusama, polkadot or dev
+    #[structopt(long, default_value = "dev")]
+    pub network: Network,
+
+    /// pool address of relay chain
+    #[structopt(long)]
+    pub relay_pool_addr: Option<String>,
+
+    /// pool address of para chain
+    #[structopt(long)]
+    pub para_pool_addr: Option<String>,
+}
+
+impl VerifyCmd {
+    /// Run the command
+    pub fn run(&self) {
+        let keystore = match get_keystore(self.key_store.to_string()) {
+            Ok(keystore) => keystore,
+            Err(e) => return println!("failed to get keystore: {}", e),
+        };
+        let mut passed = true;
+        match check_multi_address(&keystore) {
+            Ok(true) => println!("[ OK ] multi address {}", keystore.multi_address),
+            Ok(false) => {
+                println!(
+                    "[FAIL] multi address {} does not match the signatories and threshold",
+                    keystore.multi_address
+                );
+                passed = false;
+            }
+            Err(e) => {
+                println!("[FAIL] failed to recompute multi address: {}", e);
+                passed = false;
+            }
+        }
+
+        let multi_account_id = match keystore.multi_account_id() {
+            Ok(multi_account_id) => multi_account_id,
+            Err(e) => {
+                println!("[FAIL] {}", e);
+                std::process::exit(1);
+            }
+        };
+        // the relay pool is the multisig account, the para pool is the liquid staking pallet
+        let pools = [
+            (
+                self.network.relay_chain(),
+                self.relay_pool_addr
+                    .as_ref()
+                    .map(|addr| (addr, check_pool_address(addr, &multi_account_id))),
+            ),
+            (
+                self.network.para_chain(),
+                self.para_pool_addr
+                    .as_ref()
+                    .map(|addr| (addr, check_para_pool_address(addr))),
+            ),
+        ];
+        for (profile, checked) in pools.iter() {
+            match checked {
+                Some((pool_addr, Ok(()))) => {
+                    println!("[ OK ] {} pool address {}", profile, pool_addr)
+                }
+                Some((_, Err(e))) => {
+                    println!("[FAIL] {}: {}", profile, e);
+                    passed = false;
+                }
+                None => {}
+            }
+        }
+
+        if !passed {
+            println!("multisig verification failed");
+            std::process::exit(1);
+        }
+        println!("multisig verification passed");
+    }
+}
+
+#[test]
+fn test_derive_multi_account_id() {
+    use sp_core::{sr25519, Pair};
+
+    let account = |seed: &str| -> AccountId {
+        sr25519::Pair::from_string(seed, None)
+            .unwrap()
+            .public()
+            .into()
+    };
+    let (alice, bob, charlie) = (account("//Alice"), account("//Bob"), account("//Charlie"));
+    let mut others = vec![bob.clone(), charlie.clone()];
+    others.sort();
+    let expected = multi_account_id(&ensure_sorted_and_insert(others, alice.clone()).unwrap(), 2);
+
+    // the same account in whatever order the signatories are given
+    assert_eq!(
+        derive_multi_account_id(&[charlie, alice.clone(), bob], 2),
+        Ok(expected.clone())
+    );
+    assert!(derive_multi_account_id(&[alice.clone(), alice], 2).is_err());
+    assert!(check_pool_address(&expected.to_ss58check(), &expected).is_ok());
+
+    // the default para pool of the client
+    assert!(check_para_pool_address("5EYCAe5iie3Jms55YSqwGAx8H5Yj4Xv84tWYmdbm1sB1EwtZ").is_ok());
+    assert!(check_para_pool_address(&expected.to_ss58check()).is_err());
+}
@@ -0,0 +1,131 @@
+use crate::common::metadata::{Requirement, GOVERNANCE_REQUIREMENTS, SUDO_REQUIREMENTS};
+
+use core::fmt;
+use core::marker::PhantomData;
+use runtime::error::Error;
+use runtime::heiko::{self, runtime::HeikoRuntime};
+use std::str::FromStr;
+use substrate_subxt::{sudo, Call, Client, Encoded};
+
+/// The council threshold of `governance` without one, the proposal is executed at once.
+pub const COUNCIL_THRESHOLD: u32 = 1;
+
+/// How the liquid staking calls of para chain are dispatched by the multisig account. The calls
+/// of the liquid staking pallet require the root origin or its update origin, the multisig account
+/// itself is only accepted if the runtime makes it the update origin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DispatchOrigin {
+    /// The multisig account dispatches the call directly, it must be the update origin.
+    None,
+    /// The call is wrapped in `Sudo.sudo`, only works if the multisig account is the sudo key.
+    Sudo,
+    /// The call is proposed by the multisig account, a member of the general council, with the
+    /// threshold. Below 2 it is executed at once with the origin of one member, which satisfies
+    /// the update origin only if the runtime accepts a single member. Otherwise it is executed
+    /// once the threshold of members approved it, e.g. half of them for `EnsureRootOrHalfCouncil`.
+    Governance(u32),
+}
+
+impl Default for DispatchOrigin {
+    fn default() -> Self {
+        DispatchOrigin::Sudo
+    }
+}
+
+impl fmt::Display for DispatchOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DispatchOrigin::Governance(threshold) => write!(f, "{}:{}", self.name(), threshold),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl FromStr for DispatchOrigin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some("none"), None) => Ok(DispatchOrigin::None),
+            (Some("sudo"), None) => Ok(DispatchOrigin::Sudo),
+            (Some("governance"), None) | (Some("council"), None) => {
+                Ok(DispatchOrigin::Governance(COUNCIL_THRESHOLD))
+            }
+            (Some("governance"), Some(threshold)) | (Some("council"), Some(threshold)) => threshold
+                .parse::<u32>()
+                .ok()
+                .filter(|threshold| *threshold > 0)
+                .map(DispatchOrigin::Governance)
+                .ok_or_else(|| format!("invalid council threshold: {}", threshold)),
+            _ => Err(format!("unknown dispatch origin: {}", s)),
+        }
+    }
+}
+
+impl DispatchOrigin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DispatchOrigin::None => "none",
+            DispatchOrigin::Sudo => "sudo",
+            DispatchOrigin::Governance(_) => "governance",
+        }
+    }
+
+    /// The extra requirements of para chain to dispatch the calls with this origin.
+    pub fn requirements(&self) -> &'static [Requirement] {
+        match self {
+            DispatchOrigin::None => &[],
+            DispatchOrigin::Sudo => SUDO_REQUIREMENTS,
+            DispatchOrigin::Governance(_) => GOVERNANCE_REQUIREMENTS,
+        }
+    }
+
+    /// Encode the inner call wrapped for this origin, the result is what the multisig dispatches.
+    pub fn wrap<C: Call<HeikoRuntime> + Send + Sync>(
+        &self,
+        subxt_client: &Client<HeikoRuntime>,
+        inner_call: C,
+    ) -> Result<Encoded, Error> {
+        let inner_call_encoded = subxt_client
+            .encode(inner_call)
+            .map_err(|e| Error::SubxtError(e))?;
+        let call = match self {
+            DispatchOrigin::None => return Ok(inner_call_encoded),
+            DispatchOrigin::Sudo => subxt_client.encode(sudo::SudoCall::<HeikoRuntime> {
+                _runtime: PhantomData,
+                call: &inner_call_encoded,
+            }),
+            DispatchOrigin::Governance(threshold) => {
+                subxt_client.encode(heiko::api::general_council_propose_call::<HeikoRuntime>(
+                    *threshold,
+                    inner_call_encoded,
+                ))
+            }
+        };
+        call.map_err(|e| Error::SubxtError(e))
+    }
+}
+
+#[test]
+fn test_dispatch_origin() {
+    for origin in [
+        DispatchOrigin::None,
+        DispatchOrigin::Sudo,
+        DispatchOrigin::Governance(1),
+        DispatchOrigin::Governance(3),
+    ]
+    .iter()
+    {
+        assert_eq!(origin.to_string().parse::<DispatchOrigin>(), Ok(*origin));
+    }
+    assert_eq!(
+        "council".parse::<DispatchOrigin>(),
+        Ok(DispatchOrigin::Governance(COUNCIL_THRESHOLD))
+    );
+    assert!("governance:0".parse::<DispatchOrigin>().is_err());
+    assert!("sudo:2".parse::<DispatchOrigin>().is_err());
+    assert!("root".parse::<DispatchOrigin>().is_err());
+    assert_eq!(DispatchOrigin::default(), DispatchOrigin::Sudo);
+    assert!(DispatchOrigin::None.requirements().is_empty());
+}
@@ -1,7 +1,13 @@
+use crate::common::logger::{task_span, TaskMessage};
+use crate::common::profile::ChainKind;
+
+use sp_core::H256;
 use sp_runtime::{
     traits::{IdentifyAccount, Verify},
     MultiSignature,
 };
+use tokio::sync::oneshot;
+use tracing::Span;
 
 /// Alias to type for a signature for a transaction on the relay chain. This allows one of several
 /// kinds of underlying crypto to be used, so isn't a fixed size when encoded.
@@ -21,22 +27,50 @@ pub const MIN_WITHDRAW_BALANCE: u128 = 1_000_000_000_000;
 /// The maximum balance of pool to withdraw.
 pub const MAX_WITHDRAW_BALANCE: u128 = 1000_000_000_000_000;
 
-/// XcmDestWeight
-pub const XCM_DEST_WEIGHT: u128 = 100_000_000;
+/// XcmDestWeight of relay chain, which charges the XCM instructions much more than para chain
+pub const XCM_RELAY_DEST_WEIGHT: u128 = 4_000_000_000;
 
 // todo remove this mock in the future.
 /// Seeds for mock
-pub const FOR_MOCK_SEED: &str = "//Eve";
 pub const FROM_RELAY_CHAIN_SEED: &str = "//Alice";
 
-// Relay chain Bonding Duration
-pub const RELAY_CHAIN_ERA_LOCKED: u32 = 3;
-
-/// The tasks type
+/// The tasks type. The tasks decided from storage carry the finalized block hash the listener
+/// read it at, of para chain for `ParaStake` and of relay chain for `RelayUnbonded`.
 pub enum TasksType {
-    ParaStake(Amount),
+    ParaStake(Amount, H256),
     ParaUnstake(AccountId, Amount),
-    RelayUnbonded(AccountId, Amount),
+    RelayUnbonded(AccountId, Amount, H256),
     RelayWithdrawUnbonded(AccountId, Amount),
 }
+
+impl TasksType {
+    /// The span the task is executed in, on the chain the task ends on: the stake is withdrawn
+    /// for the relay chain, the others are executed on para chain.
+    pub fn span(&self) -> Span {
+        match self {
+            TasksType::ParaStake(amount, at) => {
+                task_span(ChainKind::Relay, "ParaStake", Some(*amount), Some(*at))
+            }
+            TasksType::ParaUnstake(_, amount) => {
+                task_span(ChainKind::Para, "ParaUnstake", Some(*amount), None)
+            }
+            TasksType::RelayUnbonded(_, amount, at) => {
+                task_span(ChainKind::Para, "RelayUnbonded", Some(*amount), Some(*at))
+            }
+            TasksType::RelayWithdrawUnbonded(_, amount) => task_span(
+                ChainKind::Para,
+                "RelayWithdrawUnbonded",
+                Some(*amount),
+                None,
+            ),
+        }
+    }
+
+    /// The task with its span and the sender of its result, to be sent to the dispatcher.
+    pub fn message(self, response: oneshot::Sender<u64>) -> TaskMessage<Self> {
+        let span = self.span();
+        (self, span, response)
+    }
+}
+
 pub type Amount = u128;
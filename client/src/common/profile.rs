@@ -0,0 +1,316 @@
+use crate::common::error::Error;
+
+use core::fmt;
+use frame_support::PalletId;
+use parallel_primitives::{Balance, CurrencyId, PriceWithDecimal};
+use runtime::heiko::api::{ParachainIdStore, ValidatorSet};
+use runtime::kusama::api::XcmVersion;
+use runtime::{ParaRuntime, RelayRuntime};
+use sp_core::crypto::{set_default_ss58_version, Ss58AddressFormat};
+use std::str::FromStr;
+use substrate_subxt::{staking::Staking, system::System, Client, ClientBuilder, Runtime};
+use tracing::error;
+use xcm::v0::{MultiLocation, Outcome};
+
+/// Whether a chain is a para chain or a relay chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainKind {
+    Para,
+    Relay,
+}
+
+/// The networks supported by the client. The profiles of a kind share the runtime of the kind,
+/// see [`ChainProfile::kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainProfile {
+    /// Heiko, the para chain on Kusama.
+    Heiko,
+    /// Parallel, the para chain on Polkadot, runs on the Heiko types of `ParaRuntime`.
+    Parallel,
+    /// Kusama relay chain.
+    Kusama,
+    /// Polkadot relay chain, runs on the Kusama types of `RelayRuntime`.
+    Polkadot,
+    /// Rococo or a local development relay chain, runs on `RelayRuntime`.
+    Rococo,
+    /// A local development para chain, runs on `ParaRuntime`.
+    Dev,
+}
+
+impl ChainProfile {
+    /// All supported profiles.
+    pub const ALL: [ChainProfile; 6] = [
+        ChainProfile::Heiko,
+        ChainProfile::Parallel,
+        ChainProfile::Kusama,
+        ChainProfile::Polkadot,
+        ChainProfile::Rococo,
+        ChainProfile::Dev,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChainProfile::Heiko => "heiko",
+            ChainProfile::Parallel => "parallel",
+            ChainProfile::Kusama => "kusama",
+            ChainProfile::Polkadot => "polkadot",
+            ChainProfile::Rococo => "rococo",
+            ChainProfile::Dev => "dev",
+        }
+    }
+
+    /// The kind of chain, `ParaRuntime` serves the para chains and `RelayRuntime` the relay chains.
+    pub fn kind(&self) -> ChainKind {
+        match self {
+            ChainProfile::Heiko | ChainProfile::Parallel | ChainProfile::Dev => ChainKind::Para,
+            ChainProfile::Kusama | ChainProfile::Polkadot | ChainProfile::Rococo => {
+                ChainKind::Relay
+            }
+        }
+    }
+
+    /// The websocket endpoint used when none is given.
+    pub fn default_endpoint(&self) -> &'static str {
+        match self {
+            ChainProfile::Heiko => "wss://heiko-rpc.parallel.fi",
+            ChainProfile::Parallel => "wss://rpc.parallel.fi",
+            ChainProfile::Kusama => "wss://kusama-rpc.polkadot.io",
+            ChainProfile::Polkadot => "wss://rpc.polkadot.io",
+            ChainProfile::Rococo => "ws://127.0.0.1:9955",
+            ChainProfile::Dev => "ws://127.0.0.1:9944",
+        }
+    }
+
+    /// The given endpoint, or the default endpoint of profile.
+    pub fn endpoint(&self, endpoint: &Option<String>) -> String {
+        endpoint
+            .clone()
+            .unwrap_or_else(|| self.default_endpoint().to_string())
+    }
+
+    pub fn ss58_prefix(&self) -> u16 {
+        match self {
+            ChainProfile::Heiko => 110,
+            ChainProfile::Parallel => 172,
+            ChainProfile::Kusama => 2,
+            ChainProfile::Polkadot => 0,
+            ChainProfile::Rococo | ChainProfile::Dev => 42,
+        }
+    }
+
+    pub fn ss58_format(&self) -> Ss58AddressFormat {
+        Ss58AddressFormat::Custom(self.ss58_prefix())
+    }
+
+    /// The XCM version of chain to transfer to another chain, the local chains are the oldest.
+    pub fn xcm_version(&self) -> XcmVersion {
+        match self {
+            ChainProfile::Rococo | ChainProfile::Dev => XcmVersion::V0,
+            _ => XcmVersion::V2,
+        }
+    }
+
+    /// Encode the addresses of this process with the SS58 prefix of profile.
+    pub fn use_ss58_format(&self) {
+        set_default_ss58_version(self.ss58_format());
+    }
+}
+
+impl fmt::Display for ChainProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ChainProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "heiko" => Ok(ChainProfile::Heiko),
+            "parallel" => Ok(ChainProfile::Parallel),
+            "kusama" | "ksm" => Ok(ChainProfile::Kusama),
+            "polkadot" | "dot" => Ok(ChainProfile::Polkadot),
+            "rococo" => Ok(ChainProfile::Rococo),
+            "dev" => Ok(ChainProfile::Dev),
+            _ => Err(format!("unknown chain profile: {}", s)),
+        }
+    }
+}
+
+/// The pair of para chain and relay chain the client stakes on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Network {
+    /// Heiko on Kusama, staking KSM.
+    Kusama,
+    /// Parallel on Polkadot, staking DOT.
+    Polkadot,
+    /// A local development para chain on Rococo.
+    Dev,
+}
+
+impl Network {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Kusama => "kusama",
+            Network::Polkadot => "polkadot",
+            Network::Dev => "dev",
+        }
+    }
+
+    pub fn para_chain(&self) -> ChainProfile {
+        match self {
+            Network::Kusama => ChainProfile::Heiko,
+            Network::Polkadot => ChainProfile::Parallel,
+            Network::Dev => ChainProfile::Dev,
+        }
+    }
+
+    pub fn relay_chain(&self) -> ChainProfile {
+        match self {
+            Network::Kusama => ChainProfile::Kusama,
+            Network::Polkadot => ChainProfile::Polkadot,
+            Network::Dev => ChainProfile::Rococo,
+        }
+    }
+
+    /// The currency staked on relay chain.
+    pub fn staking_currency(&self) -> CurrencyId {
+        match self {
+            Network::Kusama | Network::Dev => CurrencyId::KSM,
+            Network::Polkadot => CurrencyId::DOT,
+        }
+    }
+
+    /// The parachain id of para chain on relay chain.
+    pub fn para_id(&self) -> u32 {
+        match self {
+            Network::Kusama => 2085,
+            Network::Polkadot => 2012,
+            Network::Dev => 2000,
+        }
+    }
+}
+
+/// How the relay chain transfers to para chain through XCM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XcmConfig {
+    /// The XCM version of relay chain.
+    pub version: XcmVersion,
+    /// The parachain id of para chain on relay chain.
+    pub para_id: u32,
+    /// The index of the transferred asset which pays the fees on para chain.
+    pub fee_asset_item: u32,
+    /// The weight bought on para chain, derived from its constants when the client starts.
+    pub dest_weight: u64,
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kusama" | "ksm" => Ok(Network::Kusama),
+            "polkadot" | "dot" => Ok(Network::Polkadot),
+            "dev" | "rococo" => Ok(Network::Dev),
+            _ => Err(format!("unknown network: {}", s)),
+        }
+    }
+}
+
+/// The runtime of a kind of chain, owns the type registrations of its client.
+pub trait ProfileRuntime: Runtime {
+    const KIND: ChainKind;
+
+    /// Register the types of the chains of `KIND`, the profiles of a kind share a runtime and so
+    /// the registrations.
+    fn register_types(builder: ClientBuilder<Self>) -> ClientBuilder<Self>;
+}
+
+impl ProfileRuntime for ParaRuntime {
+    const KIND: ChainKind = ChainKind::Para;
+
+    fn register_types(builder: ClientBuilder<Self>) -> ClientBuilder<Self> {
+        // todo register all unknown type
+        builder
+            .register_type_size::<CurrencyId>("CurrencyIdOf<T>")
+            .register_type_size::<CurrencyId>("Currency<T>")
+            .register_type_size::<CurrencyId>("Currency")
+            .register_type_size::<Balance>("BalanceOf<T>")
+            .register_type_size::<<ParaRuntime as System>::AccountId>("T::AccountId")
+            .register_type_size::<ValidatorSet<ParaRuntime>>("ValidatorSet<T>")
+            .register_type_size::<CurrencyId>("T::CurrencyId")
+            .register_type_size::<Balance>("T::Balance")
+            .register_type_size::<CurrencyId>("T::OracleKey")
+            .register_type_size::<PriceWithDecimal>("T::OracleValue")
+            .register_type_size::<CurrencyId>("CurrencyId")
+            .register_type_size::<PalletId>("PalletId")
+            .register_type_size::<u32>("ParaId")
+            .register_type_size::<MultiLocation>("MultiLocation")
+            .register_type_size::<Outcome>("xcm::v0::Outcome")
+            .register_type_size::<Outcome>("Outcome")
+            .register_type_size::<[u8; 32]>("MessageId")
+    }
+}
+
+impl ProfileRuntime for RelayRuntime {
+    const KIND: ChainKind = ChainKind::Relay;
+
+    fn register_types(builder: ClientBuilder<Self>) -> ClientBuilder<Self> {
+        // todo register all unknown type
+        builder
+            .register_type_size::<<RelayRuntime as System>::AccountId>("T::AccountId")
+            .register_type_size::<<RelayRuntime as Staking>::CandidateReceipt>(
+                "CandidateReceipt<Hash>",
+            )
+            .register_type_size::<u32>("CoreIndex")
+            .register_type_size::<u32>("GroupIndex")
+            .register_type_size::<u32>("ParaId")
+            .register_type_size::<MultiLocation>("MultiLocation")
+            .register_type_size::<Outcome>("xcm::v0::Outcome")
+            .register_type_size::<Outcome>("Outcome")
+            .register_type_size::<[u8; 32]>("MessageId")
+    }
+}
+
+/// Build the client of chain with the type registrations of its runtime.
+pub async fn build_client<T: ProfileRuntime>(
+    profile: ChainProfile,
+    url: &str,
+) -> Result<Client<T>, Error> {
+    if profile.kind() != T::KIND {
+        return Err(Error::Other(format!(
+            "chain profile {} is not a {:?} chain",
+            profile,
+            T::KIND
+        )));
+    }
+    T::register_types(ClientBuilder::<T>::new().set_url(url))
+        .skip_type_sizes_check()
+        .build()
+        .await
+        .map_err(|e| {
+            error!("{} subxt_client error: {:?}", profile, e);
+            Error::SubxtError(e)
+        })
+}
+
+/// Check the para chain is the parachain `para_id` the client transfers to and from.
+pub async fn ensure_para_id(subxt_client: &Client<ParaRuntime>, para_id: u32) -> Result<(), Error> {
+    let actual = subxt_client
+        .fetch_or_default(&ParachainIdStore::default(), None)
+        .await?;
+    if actual != para_id {
+        return Err(Error::Other(format!(
+            "para chain is parachain {}, not {}, check --network and --para-id",
+            actual, para_id
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use crate::common::error::Error;
+
+use futures::{future::select, pin_mut};
+use runtime::rpc::{runtime_version, RuntimeVersion};
+use serde_json::Value;
+use substrate_subxt::{Client, Runtime};
+use tracing::{info, warn};
+
+/// Wait until the runtime of relay chain or para chain is upgraded.
+pub async fn wait_runtime_upgrade<R: Runtime, P: Runtime>(
+    relay_subxt_client: &Client<R>,
+    para_subxt_client: &Client<P>,
+) -> Result<(), Error> {
+    let relay = watch_runtime_version(relay_subxt_client, "relay chain");
+    let para = watch_runtime_version(para_subxt_client, "para chain");
+    pin_mut!(relay, para);
+    select(relay, para).await.factor_first().0
+}
+
+/// Watch the runtime version of chain, returns when the spec version changes or the subscription
+/// is closed.
+async fn watch_runtime_version<T: Runtime>(
+    subxt_client: &Client<T>,
+    chain: &str,
+) -> Result<(), Error> {
+    let current: RuntimeVersion = runtime_version(subxt_client, None).await?;
+    info!("{} runtime version: {:?}", chain, current);
+    // notified with the current version first, then on every upgrade
+    let params: &[Value] = &[];
+    let mut sub = subxt_client
+        .rpc_client()
+        .subscribe::<RuntimeVersion>(
+            "state_subscribeRuntimeVersion",
+            params.into(),
+            "state_unsubscribeRuntimeVersion",
+        )
+        .await?;
+    while let Some(version) = sub.next().await {
+        if version.spec_version != current.spec_version {
+            warn!(
+                "{} runtime upgraded from {} to {}",
+                chain, current.spec_version, version.spec_version
+            );
+            return Ok(());
+        }
+    }
+    // the connection is lost, rebuild the clients as well
+    warn!("{} runtime version subscription closed", chain);
+    Ok(())
+}
@@ -0,0 +1,311 @@
+use crate::common::primitives::{AccountId, Amount};
+use crate::common::profile::ChainKind;
+
+use async_std::{
+    sync::{Arc, Mutex},
+    task,
+};
+use parallel_primitives::CurrencyId;
+use runtime::heiko::{api::DepositedEvent, runtime::HeikoRuntime};
+use runtime::kusama::{api::DepositEvent, runtime::KusamaRuntime};
+use sp_core::{Decode, H256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use substrate_subxt::{Client, EventSubscription, RawEvent};
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
+
+/// How long a transfer may take before it is reported as delayed.
+pub const XCM_DELAY: Duration = Duration::from_secs(120);
+
+/// How long a transfer may take before it is reported as failed.
+pub const XCM_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The most XCM fees paid from a transfer, a deposit less than the amount by more is not its.
+pub const XCM_MAX_FEE: Amount = 1_000_000_000;
+
+const CHECK_INTERVAL: u64 = 12; // 12 sec
+
+/// The state of an outbound XCM transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferStatus {
+    /// Sent, the deposit on destination chain is not seen yet.
+    Pending,
+    /// Deposited to the beneficiary on destination chain.
+    Delivered,
+    /// Not deposited after `XCM_DELAY`.
+    Delayed,
+    /// Not deposited after `XCM_TIMEOUT`, e.g. the message failed on destination chain.
+    Failed,
+}
+
+/// An XCM transfer sent by the client, or expected from the other client.
+#[derive(Clone, Debug)]
+pub struct XcmTransfer {
+    pub id: u64,
+    /// The chain the amount is deposited on.
+    pub dest: ChainKind,
+    pub beneficiary: AccountId,
+    pub amount: Amount,
+    /// The block of source chain the transfer was sent in, if known.
+    pub source_block: Option<H256>,
+    /// The deposited amount, less than `amount` by the XCM fees.
+    pub deposited: Option<Amount>,
+    pub status: TransferStatus,
+    pub sent_at: Instant,
+}
+
+/// Reconcile the XCM transfers with the deposits of destination chain.
+#[derive(Debug)]
+pub struct XcmTracker {
+    transfers: Vec<XcmTransfer>,
+    /// Notified of the final status of a transfer, delivered or failed.
+    waiters: HashMap<u64, oneshot::Sender<TransferStatus>>,
+    next_id: u64,
+    delay: Duration,
+    timeout: Duration,
+    max_fee: Amount,
+}
+
+impl Default for XcmTracker {
+    fn default() -> Self {
+        Self::new(XCM_DELAY, XCM_TIMEOUT, XCM_MAX_FEE)
+    }
+}
+
+impl XcmTracker {
+    pub fn new(delay: Duration, timeout: Duration, max_fee: Amount) -> Self {
+        Self {
+            transfers: vec![],
+            waiters: HashMap::new(),
+            next_id: 0,
+            delay,
+            timeout,
+            max_fee,
+        }
+    }
+
+    /// Record a transfer of amount to the beneficiary of destination chain, returns its id.
+    pub fn record(
+        &mut self,
+        dest: ChainKind,
+        beneficiary: AccountId,
+        amount: Amount,
+        source_block: Option<H256>,
+    ) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        info!(
+            "track XCM transfer #{} of {} to {} on {:?} chain, source block {:?}",
+            id, amount, beneficiary, dest, source_block
+        );
+        self.transfers.push(XcmTransfer {
+            id,
+            dest,
+            beneficiary,
+            amount,
+            source_block,
+            deposited: None,
+            status: TransferStatus::Pending,
+            sent_at: Instant::now(),
+        });
+        id
+    }
+
+    /// Wait for the final status of the transfer, delivered or failed. The receiver is dropped
+    /// if the transfer is unknown or already final.
+    pub fn wait(&mut self, id: u64) -> oneshot::Receiver<TransferStatus> {
+        let (tx, rx) = oneshot::channel();
+        if self.status(id).is_some() {
+            self.waiters.insert(id, tx);
+        }
+        rx
+    }
+
+    /// Match the deposit with the oldest undelivered transfer to the account. The fees are paid
+    /// by the transferred amount, so the deposit is less than it by no more than the maximum
+    /// fee, the other deposits to the account e.g. staking payouts are not matched. The deposit
+    /// events carry no message hash to match the source with. Returns the matched one.
+    pub fn deposit(
+        &mut self,
+        dest: ChainKind,
+        who: &AccountId,
+        amount: Amount,
+    ) -> Option<&XcmTransfer> {
+        let max_fee = self.max_fee;
+        let transfer = self.transfers.iter_mut().find(|t| {
+            t.dest == dest
+                && &t.beneficiary == who
+                && amount <= t.amount
+                && t.amount - amount <= max_fee
+                && matches!(t.status, TransferStatus::Pending | TransferStatus::Delayed)
+        })?;
+        transfer.status = TransferStatus::Delivered;
+        transfer.deposited = Some(amount);
+        if let Some(waiter) = self.waiters.remove(&transfer.id) {
+            let _ = waiter.send(TransferStatus::Delivered);
+        }
+        Some(transfer)
+    }
+
+    /// Update the status of undelivered transfers by their age, returns the ones changed.
+    /// The delivered and failed transfers are dropped once returned.
+    pub fn check(&mut self, now: Instant) -> Vec<XcmTransfer> {
+        let mut changed = vec![];
+        for t in self.transfers.iter_mut() {
+            let elapsed = now.saturating_duration_since(t.sent_at);
+            let status = match t.status {
+                TransferStatus::Pending | TransferStatus::Delayed if elapsed >= self.timeout => {
+                    TransferStatus::Failed
+                }
+                TransferStatus::Pending if elapsed >= self.delay => TransferStatus::Delayed,
+                status => status,
+            };
+            if status != t.status || status == TransferStatus::Delivered {
+                t.status = status;
+                changed.push(t.clone());
+            }
+            if status == TransferStatus::Failed {
+                if let Some(waiter) = self.waiters.remove(&t.id) {
+                    let _ = waiter.send(TransferStatus::Failed);
+                }
+            }
+        }
+        self.transfers
+            .retain(|t| matches!(t.status, TransferStatus::Pending | TransferStatus::Delayed));
+        changed
+    }
+
+    pub fn status(&self, id: u64) -> Option<TransferStatus> {
+        self.transfers.iter().find(|t| t.id == id).map(|t| t.status)
+    }
+}
+
+/// Report the transfers changed since the last check, the failed ones are alerted.
+pub async fn check_transfers(xcm_tracker: Arc<Mutex<XcmTracker>>) {
+    loop {
+        for t in xcm_tracker.lock().await.check(Instant::now()) {
+            match t.status {
+                TransferStatus::Delivered => info!(
+                    "XCM transfer #{} delivered {:?} of {} to {}",
+                    t.id, t.deposited, t.amount, t.beneficiary
+                ),
+                TransferStatus::Delayed => warn!(
+                    "XCM transfer #{} of {} to {} is delayed, sent {:?} ago in block {:?}",
+                    t.id,
+                    t.amount,
+                    t.beneficiary,
+                    t.sent_at.elapsed(),
+                    t.source_block
+                ),
+                TransferStatus::Failed => error!(
+                    "[ALERT] XCM transfer #{} of {} to {} on {:?} chain failed, \
+                     not deposited in {:?}, sent in block {:?}",
+                    t.id, t.amount, t.beneficiary, t.dest, XCM_TIMEOUT, t.source_block
+                ),
+                TransferStatus::Pending => {}
+            }
+        }
+        task::sleep(Duration::from_secs(CHECK_INTERVAL)).await;
+    }
+}
+
+/// Match the deposits of currency on para chain with the transfers from relay chain.
+pub async fn listen_para_deposit(
+    para_subxt_client: &Client<HeikoRuntime>,
+    currency_id: CurrencyId,
+    xcm_tracker: Arc<Mutex<XcmTracker>>,
+) {
+    let sub = match para_subxt_client.subscribe_finalized_events().await {
+        Ok(sub) => sub,
+        Err(e) => return error!("failed to subscribe events of para chain: {:?}", e),
+    };
+    let decoder = para_subxt_client.events_decoder();
+    let mut sub = EventSubscription::<HeikoRuntime>::new(sub, &decoder);
+    sub.filter_event::<DepositedEvent<HeikoRuntime>>();
+    loop {
+        if let Some(event) = sub
+            .next()
+            .await
+            .and_then(|result_raw| -> Option<RawEvent> { result_raw.ok() })
+            .and_then(|raw| -> Option<DepositedEvent<HeikoRuntime>> {
+                DepositedEvent::<HeikoRuntime>::decode(&mut &raw.data[..]).ok()
+            })
+        {
+            if event.currency_id != currency_id {
+                continue;
+            }
+            let mut xcm_tracker = xcm_tracker.lock().await;
+            if let Some(t) = xcm_tracker.deposit(ChainKind::Para, &event.who, event.amount) {
+                info!("XCM transfer #{} deposited on para chain", t.id);
+            }
+        }
+    }
+}
+
+/// Match the deposits on relay chain with the transfers from para chain.
+pub async fn listen_relay_deposit(
+    relay_subxt_client: &Client<KusamaRuntime>,
+    xcm_tracker: Arc<Mutex<XcmTracker>>,
+) {
+    let sub = match relay_subxt_client.subscribe_finalized_events().await {
+        Ok(sub) => sub,
+        Err(e) => return error!("failed to subscribe events of relay chain: {:?}", e),
+    };
+    let decoder = relay_subxt_client.events_decoder();
+    let mut sub = EventSubscription::<KusamaRuntime>::new(sub, &decoder);
+    sub.filter_event::<DepositEvent<KusamaRuntime>>();
+    loop {
+        if let Some(event) = sub
+            .next()
+            .await
+            .and_then(|result_raw| -> Option<RawEvent> { result_raw.ok() })
+            .and_then(|raw| -> Option<DepositEvent<KusamaRuntime>> {
+                DepositEvent::<KusamaRuntime>::decode(&mut &raw.data[..]).ok()
+            })
+        {
+            let mut xcm_tracker = xcm_tracker.lock().await;
+            if let Some(t) = xcm_tracker.deposit(ChainKind::Relay, &event.who, event.amount) {
+                info!("XCM transfer #{} deposited on relay chain", t.id);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_xcm_tracker() {
+    use sp_keyring::AccountKeyring;
+
+    let (alice, bob) = (
+        AccountKeyring::Alice.to_account_id(),
+        AccountKeyring::Bob.to_account_id(),
+    );
+    let mut tracker = XcmTracker::new(Duration::from_secs(10), Duration::from_secs(20), 20);
+    let delivered = tracker.record(ChainKind::Para, alice.clone(), 100, None);
+    let failed = tracker.record(ChainKind::Para, bob.clone(), 100, None);
+    let mut delivered_rx = tracker.wait(delivered);
+    let mut failed_rx = tracker.wait(failed);
+
+    // the fees are paid from the amount, the deposit of another chain is not matched, nor the
+    // deposit less than the amount by more than the fees
+    assert!(tracker.deposit(ChainKind::Relay, &alice, 90).is_none());
+    assert!(tracker.deposit(ChainKind::Para, &alice, 110).is_none());
+    assert!(tracker.deposit(ChainKind::Para, &alice, 70).is_none());
+    assert_eq!(
+        tracker.deposit(ChainKind::Para, &alice, 90).unwrap().id,
+        delivered
+    );
+    assert_eq!(delivered_rx.try_recv(), Ok(TransferStatus::Delivered));
+
+    let now = Instant::now();
+    let changed = tracker.check(now + Duration::from_secs(10));
+    assert_eq!(changed.len(), 2);
+    assert_eq!(tracker.status(delivered), None);
+    assert_eq!(tracker.status(failed), Some(TransferStatus::Delayed));
+    assert_eq!(
+        tracker.check(now + Duration::from_secs(20))[0].status,
+        TransferStatus::Failed
+    );
+    assert_eq!(tracker.status(failed), None);
+    assert_eq!(failed_rx.try_recv(), Ok(TransferStatus::Failed));
+}
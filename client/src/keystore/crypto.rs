@@ -1,17 +1,77 @@
+use super::error::KeystoreError;
+
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use sp_runtime::{traits::IdentifyAccount, MultiSigner};
+use std::str::FromStr;
+
 pub use sp_core::{
     crypto::{set_default_ss58_version, AccountId32, Derive, Ss58AddressFormat, Ss58Codec},
     ecdsa, ed25519, sr25519, Pair, Public,
 };
 
+/// The signature scheme of the key in keystore.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyType {
+    Sr25519,
+    Ed25519,
+    Ecdsa,
+}
+
+impl KeyType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyType::Sr25519 => "sr25519",
+            KeyType::Ed25519 => "ed25519",
+            KeyType::Ecdsa => "ecdsa",
+        }
+    }
+}
+
+impl Default for KeyType {
+    fn default() -> Self {
+        KeyType::Sr25519
+    }
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for KeyType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sr25519" => Ok(KeyType::Sr25519),
+            "ed25519" => Ok(KeyType::Ed25519),
+            "ecdsa" => Ok(KeyType::Ecdsa),
+            _ => Err(format!("unknown key type: {}", s)),
+        }
+    }
+}
+
 pub trait Crypto: Sized {
     type Pair: Pair<Public = Self::Public>;
-    type Public: Public + Ss58Codec + AsRef<[u8]> + std::hash::Hash;
+    type Public: Public + Ss58Codec + AsRef<[u8]> + std::hash::Hash + Into<MultiSigner>;
 
-    fn pair_from_seed(seed: &str) -> Result<Self::Pair, ()>;
+    const KEY_TYPE: KeyType;
 
-    fn pair_from_seed_slice(slice: &[u8]) -> Result<Self::Pair, ()>;
+    fn pair_from_seed(seed: &str) -> Result<Self::Pair, KeystoreError> {
+        Self::Pair::from_string(seed, None).map_err(|_| KeystoreError::InvalidSeed)
+    }
+
+    fn pair_from_seed_slice(slice: &[u8]) -> Result<Self::Pair, KeystoreError>;
 
     fn address<P: Pair>(pair: &P) -> String;
+
+    /// The account id of public key, ecdsa public keys are hashed.
+    fn account_id(public: Self::Public) -> AccountId32 {
+        public.into().into_account()
+    }
 }
 
 pub struct Sr25519;
@@ -19,18 +79,14 @@ impl Crypto for Sr25519 {
     type Pair = sr25519::Pair;
     type Public = sr25519::Public;
 
-    fn pair_from_seed(seed: &str) -> Result<Self::Pair, ()> {
-        match Self::Pair::from_string(seed, None) {
-            Ok(pair) => Ok(pair),
-            Err(_) => Err(()),
-        }
-    }
+    const KEY_TYPE: KeyType = KeyType::Sr25519;
 
-    fn pair_from_seed_slice(slice: &[u8]) -> Result<Self::Pair, ()> {
-        match Self::Pair::from_seed_slice(slice).map_err(|_| ()) {
+    fn pair_from_seed_slice(slice: &[u8]) -> Result<Self::Pair, KeystoreError> {
+        match Self::Pair::from_seed_slice(slice) {
             Ok(pair) => Ok(pair),
             Err(_) => {
-                let sec = schnorrkel::SecretKey::from_ed25519_bytes(slice).map_err(|_| ())?;
+                let sec = schnorrkel::SecretKey::from_ed25519_bytes(slice)
+                    .map_err(|_| KeystoreError::InvalidSecretKey)?;
                 Ok(Self::Pair::from(sec))
             }
         }
@@ -40,3 +96,41 @@ impl Crypto for Sr25519 {
         pair.public().to_ss58check()
     }
 }
+
+pub struct Ed25519;
+impl Crypto for Ed25519 {
+    type Pair = ed25519::Pair;
+    type Public = ed25519::Public;
+
+    const KEY_TYPE: KeyType = KeyType::Ed25519;
+
+    fn pair_from_seed_slice(slice: &[u8]) -> Result<Self::Pair, KeystoreError> {
+        // the secret key of tweetnacl is the seed followed by the public key
+        let seed = if slice.len() == 64 {
+            &slice[..32]
+        } else {
+            slice
+        };
+        Self::Pair::from_seed_slice(seed).map_err(|_| KeystoreError::InvalidSecretKey)
+    }
+
+    fn address<P: Pair>(pair: &P) -> String {
+        pair.public().to_ss58check()
+    }
+}
+
+pub struct Ecdsa;
+impl Crypto for Ecdsa {
+    type Pair = ecdsa::Pair;
+    type Public = ecdsa::Public;
+
+    const KEY_TYPE: KeyType = KeyType::Ecdsa;
+
+    fn pair_from_seed_slice(slice: &[u8]) -> Result<Self::Pair, KeystoreError> {
+        Self::Pair::from_seed_slice(slice).map_err(|_| KeystoreError::InvalidSecretKey)
+    }
+
+    fn address<P: Pair>(pair: &P) -> String {
+        pair.public().to_ss58check()
+    }
+}
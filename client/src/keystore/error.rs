@@ -0,0 +1,46 @@
+use thiserror::Error as ThisError;
+
+use serde_json::Error as JsonError;
+use std::io::Error as IoError;
+
+#[derive(ThisError, Debug)]
+pub enum KeystoreError {
+    #[error("Io Error: `{0:?}`")]
+    Io(#[from] IoError),
+    #[error("Json Error: `{0:?}`")]
+    Json(#[from] JsonError),
+    #[error("Wrong password")]
+    WrongPassword,
+    #[error("Password longer than {0} bytes is not supported by keystore v1")]
+    PasswordTooLong(usize),
+    #[error("Corrupt pkcs8 header, the keystore is broken or the password is missing")]
+    CorruptHeader,
+    #[error("Bad pkcs8 divider")]
+    BadDivider,
+    #[error("Corrupt encoded data: {0}")]
+    CorruptEncoded(String),
+    #[error("Invalid scrypt params")]
+    InvalidKdfParams,
+    #[error("Failed to encrypt key")]
+    Encrypt,
+    #[error("Invalid seed")]
+    InvalidSeed,
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+    #[error("Invalid derivation path: {0}")]
+    InvalidDerivationPath(String),
+    #[error("Invalid secret key")]
+    InvalidSecretKey,
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Address {0} is not the address of the key")]
+    AddressMismatch(String),
+    #[error("Invalid signatories: {0}")]
+    InvalidSignatories(String),
+    #[error("Unsupported keystore version: {0}")]
+    UnsupportedVersion(String),
+    #[error("Unsupported encoding: {0}")]
+    UnsupportedEncoding(String),
+    #[error("Failed to read password: {0}")]
+    PasswordSource(String),
+}
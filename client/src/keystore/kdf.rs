@@ -0,0 +1,121 @@
+use super::error::KeystoreError;
+
+use rand::{thread_rng, Rng};
+use sodalite::{SecretboxKey, SECRETBOX_KEY_LEN};
+use zeroize::Zeroizing;
+
+pub const SALT_LENGTH: usize = 32;
+/// salt, N, p and r, the same layout as the scrypt header of polkadot-js.
+pub const KDF_HEADER_LENGTH: usize = SALT_LENGTH + 4 * 3;
+
+pub const DEFAULT_N: u32 = 1 << 15;
+pub const DEFAULT_P: u32 = 1;
+pub const DEFAULT_R: u32 = 8;
+
+/// The most expensive parameters accepted from a keystore file, the ones of polkadot-js, so a
+/// crafted file can not exhaust the memory or cpu of client.
+pub const MAX_N: u32 = 1 << 15;
+pub const MAX_P: u32 = 1;
+pub const MAX_R: u32 = 8;
+
+/// The length of key derived by scrypt, only the first 32 bytes are used as the secretbox key.
+const DERIVED_KEY_LENGTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScryptParams {
+    pub salt: [u8; SALT_LENGTH],
+    pub n: u32,
+    pub p: u32,
+    pub r: u32,
+}
+
+impl ScryptParams {
+    /// The default parameters with a random salt.
+    pub fn random() -> Self {
+        let mut salt = [0u8; SALT_LENGTH];
+        thread_rng().fill(&mut salt);
+        Self {
+            salt,
+            n: DEFAULT_N,
+            p: DEFAULT_P,
+            r: DEFAULT_R,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(KDF_HEADER_LENGTH);
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.n.to_le_bytes());
+        bytes.extend_from_slice(&self.p.to_le_bytes());
+        bytes.extend_from_slice(&self.r.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KeystoreError> {
+        if bytes.len() < KDF_HEADER_LENGTH {
+            return Err(KeystoreError::CorruptEncoded(
+                "scrypt header truncated".to_string(),
+            ));
+        }
+        let u32_at = |offset: usize| {
+            let mut buf = [0u8; 4];
+            buf.copy_from_slice(&bytes[offset..offset + 4]);
+            u32::from_le_bytes(buf)
+        };
+        let mut salt = [0u8; SALT_LENGTH];
+        salt.copy_from_slice(&bytes[..SALT_LENGTH]);
+        let params = Self {
+            salt,
+            n: u32_at(SALT_LENGTH),
+            p: u32_at(SALT_LENGTH + 4),
+            r: u32_at(SALT_LENGTH + 8),
+        };
+        params.check()?;
+        Ok(params)
+    }
+
+    /// Reject the parameters above the limits, and N which is not a power of two.
+    pub fn check(&self) -> Result<(), KeystoreError> {
+        if !self.n.is_power_of_two() || self.n > MAX_N || self.r > MAX_R || self.p > MAX_P {
+            return Err(KeystoreError::InvalidKdfParams);
+        }
+        Ok(())
+    }
+
+    /// Derive the secretbox key from the password.
+    pub fn derive_key(&self, password: &[u8]) -> Result<Zeroizing<SecretboxKey>, KeystoreError> {
+        self.check()?;
+        let params = scrypt::Params::new(self.n.trailing_zeros() as u8, self.r, self.p)
+            .map_err(|_| KeystoreError::InvalidKdfParams)?;
+        let mut derived = Zeroizing::new([0u8; DERIVED_KEY_LENGTH]);
+        scrypt::scrypt(password, &self.salt, &params, &mut derived[..])
+            .map_err(|_| KeystoreError::InvalidKdfParams)?;
+
+        let mut key = Zeroizing::new([0u8; SECRETBOX_KEY_LEN]);
+        key.copy_from_slice(&derived[..SECRETBOX_KEY_LEN]);
+        Ok(key)
+    }
+}
+
+#[test]
+fn test_check_params() {
+    let params = ScryptParams::random();
+    assert!(ScryptParams::from_bytes(&params.to_bytes()).is_ok());
+
+    for (n, p, r) in &[
+        (1 << 16, 1, 8),
+        (1 << 15, 2, 8),
+        (1 << 15, 1, 9),
+        (3 << 13, 1, 8),
+        (0, 1, 8),
+    ] {
+        let params = ScryptParams {
+            n: *n,
+            p: *p,
+            r: *r,
+            ..params.clone()
+        };
+        assert!(ScryptParams::from_bytes(&params.to_bytes()).is_err());
+        assert!(params.derive_key(b"password").is_err());
+    }
+}
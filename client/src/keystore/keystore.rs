@@ -1,11 +1,26 @@
 use super::crypto::*;
+use super::error::KeystoreError;
+use super::mnemonic::Derivation;
 use super::pkcs8;
 use super::AccountId;
 use serde::{Deserialize, Serialize};
+use sp_runtime::traits::{IdentifyAccount, Verify};
 use std::fs;
+use substrate_subxt::{PairSigner, Runtime, Signer};
+
+/// The version of keystore whose key is derived by scrypt.
+pub const KEYSTORE_VERSION: u8 = 2;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Keystore {
+    // the version of keystore format, the keystores without version are v1.
+    #[serde(default = "default_version")]
+    pub version: u8,
+
+    // the key type of signer, the keystores without key type are sr25519.
+    #[serde(default)]
+    pub key_type: KeyType,
+
     // the address of keystore.
     pub address: String,
 
@@ -18,17 +33,21 @@ pub struct Keystore {
     // the threshold of multi-signature.
     pub threshold: u16,
 
-    // The network of keystore, for 'polkadot' or 'ksm'.
+    // The network of keystore, for 'kusama', 'polkadot' or 'dev', 'ksm' for the old keystores.
     pub network: String,
 
     // the encoded data of keystore.
     pub encoded: String,
+
+    // how the key is derived, unknown for the imported and old keystores.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation: Option<Derivation>,
 }
 
 impl Keystore {
-    pub fn parse_from_file(path: String) -> Result<Self, ()> {
-        let data = fs::read_to_string(path).map_err(|_| ())?;
-        let keystore: Self = serde_json::from_str(&data).map_err(|_| ())?;
+    pub fn parse_from_file(path: String) -> Result<Self, KeystoreError> {
+        let data = fs::read_to_string(path)?;
+        let keystore: Self = serde_json::from_str(&data)?;
         Ok(keystore)
     }
 
@@ -36,33 +55,127 @@ impl Keystore {
         serde_json::to_string(&self).unwrap()
     }
 
-    pub fn encoded_bytes(&self) -> Vec<u8> {
+    pub fn encoded_bytes(&self) -> Result<Vec<u8>, KeystoreError> {
         let encoded = if self.encoded.starts_with("0x") {
             &self.encoded[2..]
         } else {
             &self.encoded
         };
-        hex::decode(encoded).unwrap_or(vec![])
-    }
-
-    pub fn into_pair<T: Crypto>(&self, password: Option<String>) -> Result<T::Pair, ()> {
-        let encoded = self.encoded_bytes();
+        let encoded =
+            hex::decode(encoded).map_err(|e| KeystoreError::CorruptEncoded(e.to_string()))?;
         if encoded.is_empty() {
-            return Err(());
+            return Err(KeystoreError::CorruptEncoded("empty".to_string()));
         }
-        match pkcs8::decode(&encoded[..], password) {
-            Ok((_, secret_key)) => T::pair_from_seed_slice(&secret_key[..]),
-            Err(_) => Err(()),
+        Ok(encoded)
+    }
+
+    pub fn into_pair<T: Crypto>(&self, password: Option<&str>) -> Result<T::Pair, KeystoreError> {
+        let (_, secret_key) = self.decrypt(password)?;
+        T::pair_from_seed_slice(&secret_key[..])
+    }
+
+    /// Decrypt the public key and secret key of keystore.
+    pub fn decrypt(
+        &self,
+        password: Option<&str>,
+    ) -> Result<(Vec<u8>, pkcs8::Secret), KeystoreError> {
+        self.decode(&self.encoded_bytes()?, password)
+    }
+
+    /// The account id of address, no need to decrypt the key.
+    pub fn account_id(&self) -> Result<AccountId, KeystoreError> {
+        AccountId::from_ss58check_with_version(&self.address)
+            .map(|(account_id, _)| account_id)
+            .map_err(|_| KeystoreError::InvalidAddress(self.address.clone()))
+    }
+
+    /// The account id of multi address.
+    pub fn multi_account_id(&self) -> Result<AccountId, KeystoreError> {
+        AccountId::from_ss58check_with_version(&self.multi_address)
+            .map(|(account_id, _)| account_id)
+            .map_err(|_| KeystoreError::InvalidAddress(self.multi_address.clone()))
+    }
+
+    /// Decrypt the key and return the account id of it.
+    pub fn decrypt_account_id(&self, password: Option<&str>) -> Result<AccountId, KeystoreError> {
+        Ok(match self.key_type {
+            KeyType::Sr25519 => Sr25519::account_id(self.into_pair::<Sr25519>(password)?.public()),
+            KeyType::Ed25519 => Ed25519::account_id(self.into_pair::<Ed25519>(password)?.public()),
+            KeyType::Ecdsa => Ecdsa::account_id(self.into_pair::<Ecdsa>(password)?.public()),
+        })
+    }
+
+    /// Decrypt the key and build the signer of runtime with the key type of keystore.
+    pub fn into_signer<T>(
+        &self,
+        password: Option<&str>,
+    ) -> Result<Box<dyn Signer<T> + Send + Sync>, KeystoreError>
+    where
+        T: Runtime,
+        T::Signature: From<sr25519::Signature> + From<ed25519::Signature> + From<ecdsa::Signature>,
+        <T::Signature as Verify>::Signer: From<sr25519::Public>
+            + From<ed25519::Public>
+            + From<ecdsa::Public>
+            + IdentifyAccount<AccountId = T::AccountId>,
+        PairSigner<T, sr25519::Pair>: Signer<T> + 'static,
+        PairSigner<T, ed25519::Pair>: Signer<T> + 'static,
+        PairSigner<T, ecdsa::Pair>: Signer<T> + 'static,
+    {
+        Ok(match self.key_type {
+            KeyType::Sr25519 => Box::new(PairSigner::<T, _>::new(
+                self.into_pair::<Sr25519>(password)?,
+            )),
+            KeyType::Ed25519 => Box::new(PairSigner::<T, _>::new(
+                self.into_pair::<Ed25519>(password)?,
+            )),
+            KeyType::Ecdsa => Box::new(PairSigner::<T, _>::new(self.into_pair::<Ecdsa>(password)?)),
+        })
+    }
+
+    fn decode(
+        &self,
+        encoded: &[u8],
+        password: Option<&str>,
+    ) -> Result<(Vec<u8>, pkcs8::Secret), KeystoreError> {
+        match self.version {
+            1 => pkcs8::decode_v1(encoded, password),
+            KEYSTORE_VERSION => pkcs8::decode(encoded, password),
+            v => Err(KeystoreError::UnsupportedVersion(v.to_string())),
         }
     }
 
-    pub fn get_other_signatories(&self) -> Result<Vec<AccountId>, ()> {
+    /// Re-encode the keystore to the current version with the same password.
+    pub fn migrate(&self, password: Option<&str>) -> Result<Self, KeystoreError> {
+        self.change_password(password, password)
+    }
+
+    /// Re-encode the keystore to the current version with the new password.
+    pub fn change_password(
+        &self,
+        password: Option<&str>,
+        new_password: Option<&str>,
+    ) -> Result<Self, KeystoreError> {
+        let (public_key, secret_key) = self.decrypt(password)?;
+        let encoded = pkcs8::encode(&secret_key[..], &public_key[..], new_password)?;
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            encoded: format!("0x{}", hex::encode(encoded)),
+            ..self.clone()
+        })
+    }
+
+    pub fn get_other_signatories(&self) -> Result<Vec<AccountId>, KeystoreError> {
         let mut other_signatories: Vec<AccountId> = vec![];
         for a in self.others.iter() {
-            let account_id = AccountId::from_string(&a).map_err(|_err| ())?;
+            let account_id = AccountId::from_string(&a)
+                .map_err(|_err| KeystoreError::InvalidAddress(a.clone()))?;
             other_signatories.push(account_id);
         }
         other_signatories.sort_by(|a, b| a.cmp(&b));
         Ok(other_signatories)
     }
 }
+
+fn default_version() -> u8 {
+    1
+}
@@ -0,0 +1,125 @@
+use super::error::KeystoreError;
+
+use bip39::{Language, Mnemonic, MnemonicType};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+pub const DEFAULT_WORDS: usize = 24;
+
+/// Where the key of keystore comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretSource {
+    // a BIP39 mnemonic phrase.
+    Mnemonic,
+    // a hex encoded seed.
+    Seed,
+    // the well-known development phrase, e.g. `//Alice`.
+    Dev,
+}
+
+/// How the key of keystore is derived, the secret itself is never recorded.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Derivation {
+    pub source: SecretSource,
+
+    // the word count of mnemonic.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<usize>,
+
+    // the derivation path like `//stake//0`, the password of path is dropped.
+    #[serde(default)]
+    pub path: String,
+
+    // whether the derivation path has a `///password` part.
+    #[serde(default)]
+    pub path_password: bool,
+}
+
+impl fmt::Display for Derivation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.words {
+            Some(words) => write!(f, "{:?} of {} words", self.source, words)?,
+            None => write!(f, "{:?}", self.source)?,
+        }
+        if !self.path.is_empty() {
+            write!(f, ", path {}", self.path)?;
+        }
+        if self.path_password {
+            write!(f, ", with password")?;
+        }
+        Ok(())
+    }
+}
+
+impl Derivation {
+    /// Describe the secret uri `<phrase or seed><path>` without keeping the secret.
+    pub fn from_suri(suri: &str) -> Result<Self, KeystoreError> {
+        let (secret, path) = split_suri(suri);
+        let (path, path_password) = match path.find("///") {
+            Some(i) => (&path[..i], true),
+            None => (path, false),
+        };
+        let (source, words) = if secret.is_empty() {
+            (SecretSource::Dev, None)
+        } else if secret.starts_with("0x") {
+            (SecretSource::Seed, None)
+        } else {
+            validate(secret)?;
+            (
+                SecretSource::Mnemonic,
+                Some(secret.split_whitespace().count()),
+            )
+        };
+        Ok(Self {
+            source,
+            words,
+            path: path.to_string(),
+            path_password,
+        })
+    }
+}
+
+/// Generate a new english mnemonic of the word count.
+pub fn generate(words: usize) -> Result<Zeroizing<String>, KeystoreError> {
+    let mnemonic_type = MnemonicType::for_word_count(words)
+        .map_err(|_| KeystoreError::InvalidMnemonic(format!("{} words", words)))?;
+    let mnemonic = Mnemonic::new(mnemonic_type, Language::English);
+    Ok(Zeroizing::new(mnemonic.phrase().to_string()))
+}
+
+/// Check the words and checksum of the english mnemonic.
+pub fn validate(phrase: &str) -> Result<(), KeystoreError> {
+    Mnemonic::validate(phrase, Language::English)
+        .map_err(|e| KeystoreError::InvalidMnemonic(e.to_string()))
+}
+
+/// Split the secret uri into the secret and the derivation path starting with `/`.
+pub fn split_suri(suri: &str) -> (&str, &str) {
+    let suri = suri.trim();
+    match suri.find('/') {
+        Some(i) => (suri[..i].trim_end(), &suri[i..]),
+        None => (suri, ""),
+    }
+}
+
+#[test]
+fn test_derivation() {
+    let phrase = generate(12).unwrap();
+    let derivation = Derivation::from_suri(&format!("{}//stake//0///secret", *phrase)).unwrap();
+    assert_eq!(
+        derivation,
+        Derivation {
+            source: SecretSource::Mnemonic,
+            words: Some(12),
+            path: "//stake//0".to_string(),
+            path_password: true,
+        }
+    );
+    assert_eq!(
+        Derivation::from_suri("//Alice").unwrap().source,
+        SecretSource::Dev
+    );
+    assert!(Derivation::from_suri("not a valid mnemonic").is_err());
+}
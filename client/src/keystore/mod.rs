@@ -1,6 +1,12 @@
 pub(crate) mod crypto;
+pub(crate) mod error;
+mod kdf;
 pub(crate) mod keystore;
+pub(crate) mod mnemonic;
+pub(crate) mod password;
 mod pkcs8;
+pub(crate) mod polkadot_js;
+pub(crate) mod remote_signer;
 pub(crate) mod wallet;
 
 use crate::common::primitives::AccountId;
@@ -0,0 +1,202 @@
+use super::error::KeystoreError;
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::os::unix::io::RawFd;
+use structopt::StructOpt;
+use zeroize::Zeroizing;
+
+/// The password of keystore, wiped from memory when dropped.
+pub type Password = Zeroizing<String>;
+
+/// Where to read the password of keystore from, the tty is prompted if none is given.
+#[derive(Debug, Default, StructOpt)]
+pub struct PasswordOpt {
+    /// the password of keystore, visible to other users of the host, prefer the other sources
+    #[structopt(short, long, parse(from_str = zeroizing))]
+    pub password: Option<Password>,
+
+    /// read the password of keystore from the first line of file
+    #[structopt(long, conflicts_with_all = &["password", "password-env", "password-fd"])]
+    pub password_file: Option<String>,
+
+    /// read the password of keystore from the environment variable
+    #[structopt(long, conflicts_with_all = &["password", "password-fd"])]
+    pub password_env: Option<String>,
+
+    /// read the password of keystore from the open file descriptor, e.g. `3` with `3<secret`
+    #[structopt(long, conflicts_with = "password")]
+    pub password_fd: Option<RawFd>,
+}
+
+impl PasswordOpt {
+    /// Read the password from the configured source, or prompt for it on the tty.
+    pub fn read(&self, prompt: &str) -> Result<Option<Password>, KeystoreError> {
+        if let Some(password) = &self.password {
+            return Ok(Some(password.clone()));
+        }
+        if let Some(password) =
+            read_source(&self.password_file, &self.password_env, self.password_fd)?
+        {
+            return Ok(Some(password));
+        }
+        Ok(rpassword::read_password_from_tty(Some(prompt))
+            .ok()
+            .map(Zeroizing::new))
+    }
+}
+
+/// Where to read the new password of keystore from, the tty is prompted twice if none is given.
+#[derive(Debug, Default, StructOpt)]
+pub struct NewPasswordOpt {
+    /// read the new password of keystore from the first line of file
+    #[structopt(long, conflicts_with_all = &["new-password-env", "new-password-fd"])]
+    pub new_password_file: Option<String>,
+
+    /// read the new password of keystore from the environment variable
+    #[structopt(long, conflicts_with = "new-password-fd")]
+    pub new_password_env: Option<String>,
+
+    /// read the new password of keystore from the open file descriptor, e.g. `4` with `4<secret`
+    #[structopt(long)]
+    pub new_password_fd: Option<RawFd>,
+}
+
+impl NewPasswordOpt {
+    /// Read the new password from the configured source, or prompt for it on the tty and for
+    /// its repetition. The keystore is never re-encoded without a password, so an empty or
+    /// missing one is an error.
+    pub fn read(&self) -> Result<Password, KeystoreError> {
+        let password = match read_source(
+            &self.new_password_file,
+            &self.new_password_env,
+            self.new_password_fd,
+        )? {
+            Some(password) => password,
+            None => {
+                let password = Zeroizing::new(rpassword::read_password_from_tty(Some(
+                    "Type new password:",
+                ))?);
+                let repeated = Zeroizing::new(rpassword::read_password_from_tty(Some(
+                    "Repeat new password:",
+                ))?);
+                if password != repeated {
+                    return Err(KeystoreError::PasswordSource(
+                        "the new passwords do not match".to_string(),
+                    ));
+                }
+                password
+            }
+        };
+        if password.is_empty() {
+            return Err(KeystoreError::PasswordSource(
+                "the new password is empty".to_string(),
+            ));
+        }
+        Ok(password)
+    }
+}
+
+/// Read the password from the file, the environment variable or the file descriptor, whichever
+/// is given.
+fn read_source(
+    file: &Option<String>,
+    env: &Option<String>,
+    fd: Option<RawFd>,
+) -> Result<Option<Password>, KeystoreError> {
+    if let Some(path) = file {
+        let content = Zeroizing::new(fs::read_to_string(path)?);
+        return Ok(Some(first_line(&content)));
+    }
+    if let Some(name) = env {
+        let value = std::env::var(name).map_err(|_| {
+            KeystoreError::PasswordSource(format!("environment variable {} is not set", name))
+        })?;
+        std::env::remove_var(name);
+        return Ok(Some(Zeroizing::new(value)));
+    }
+    if let Some(fd) = fd {
+        // the standard output and error are never the password
+        if fd < 0 || fd == 1 || fd == 2 {
+            return Err(KeystoreError::PasswordSource(format!(
+                "invalid file descriptor {}",
+                fd
+            )));
+        }
+        // reopened through /dev/fd, which fails if the descriptor is not open
+        let mut file = File::open(format!("/dev/fd/{}", fd))
+            .map_err(|e| KeystoreError::PasswordSource(format!("file descriptor {}: {}", fd, e)))?;
+        let mut content = Zeroizing::new(String::new());
+        file.read_to_string(&mut content)?;
+        return Ok(Some(first_line(&content)));
+    }
+    Ok(None)
+}
+
+/// Keep the password given on the command line in wiped memory.
+fn zeroizing(password: &str) -> Password {
+    Zeroizing::new(password.to_string())
+}
+
+/// The first line of content without the trailing newline.
+fn first_line(content: &str) -> Password {
+    Zeroizing::new(content.lines().next().unwrap_or_default().to_string())
+}
+
+#[test]
+fn test_read_password() {
+    let path = std::env::temp_dir().join("stake-client-test-password");
+    fs::write(&path, "secret\r\nignored\n").unwrap();
+    let opt = PasswordOpt {
+        password_file: Some(path.to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        opt.read("").unwrap().as_deref().map(|p| p.as_str()),
+        Some("secret")
+    );
+    fs::remove_file(path).unwrap();
+
+    std::env::set_var("STAKE_CLIENT_TEST_PASSWORD", "secret");
+    let opt = PasswordOpt {
+        password_env: Some("STAKE_CLIENT_TEST_PASSWORD".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        opt.read("").unwrap().as_deref().map(|p| p.as_str()),
+        Some("secret")
+    );
+    assert!(std::env::var("STAKE_CLIENT_TEST_PASSWORD").is_err());
+}
+
+#[test]
+fn test_read_password_fd() {
+    let opt = PasswordOpt {
+        password_fd: Some(2),
+        ..Default::default()
+    };
+    assert!(opt.read("").is_err());
+
+    // a descriptor which is not open
+    let opt = PasswordOpt {
+        password_fd: Some(1_000_000),
+        ..Default::default()
+    };
+    assert!(opt.read("").is_err());
+}
+
+#[test]
+fn test_read_new_password() {
+    let path = std::env::temp_dir().join("stake-client-test-new-password");
+    let opt = NewPasswordOpt {
+        new_password_file: Some(path.to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    fs::write(&path, "secret\n").unwrap();
+    assert_eq!(opt.read().unwrap().as_str(), "secret");
+
+    // the keystore would be written unencrypted
+    fs::write(&path, "\n").unwrap();
+    assert!(opt.read().is_err());
+    fs::remove_file(path).unwrap();
+}
@@ -1,7 +1,11 @@
+use super::error::KeystoreError;
+use super::kdf::{ScryptParams, KDF_HEADER_LENGTH};
+
 use rand::{thread_rng, Rng};
 use sodalite::{
     secretbox, secretbox_open, SecretboxKey, SecretboxNonce, SECRETBOX_KEY_LEN, SECRETBOX_NONCE_LEN,
 };
+use zeroize::Zeroizing;
 pub const SECRETBOX_BOXZEROBYTES: usize = 16;
 pub const SECRETBOX_ZEROBYTES: usize = 32;
 
@@ -11,88 +15,56 @@ pub const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112,
 pub const SEC_LENGTH: usize = 64;
 pub const SEED_LENGTH: usize = 32;
 
-pub fn decode(encoded: &[u8], passphrase: Option<String>) -> Result<(Vec<u8>, Vec<u8>), ()> {
-    let encoded_length = encoded.len();
-
-    let mut nonce: SecretboxNonce = [0u8; SECRETBOX_NONCE_LEN];
-    nonce.copy_from_slice(&encoded[0..SECRETBOX_NONCE_LEN]);
+/// The decrypted secret key, wiped from memory when dropped.
+pub type Secret = Zeroizing<Vec<u8>>;
 
+/// Decode the keystore v1 format, whose secretbox key is the zero-padded password.
+pub fn decode_v1(
+    encoded: &[u8],
+    passphrase: Option<&str>,
+) -> Result<(Vec<u8>, Secret), KeystoreError> {
     let msg = match passphrase {
         Some(passphrase) if !passphrase.is_empty() => {
             let pass_bytes = passphrase.as_bytes();
-            let mut key: SecretboxKey = [0u8; SECRETBOX_KEY_LEN];
+            if pass_bytes.len() > SECRETBOX_KEY_LEN {
+                return Err(KeystoreError::PasswordTooLong(SECRETBOX_KEY_LEN));
+            }
+            let mut key = Zeroizing::new([0u8; SECRETBOX_KEY_LEN]);
             key[..pass_bytes.len()].copy_from_slice(pass_bytes);
-
-            let mut encrypted =
-                vec![0u8; SECRETBOX_BOXZEROBYTES + encoded_length - SECRETBOX_NONCE_LEN];
-            encrypted[SECRETBOX_BOXZEROBYTES..].copy_from_slice(&encoded[SECRETBOX_NONCE_LEN..]);
-
-            let mut raw = vec![0u8; encrypted.len()];
-            secretbox_open(&mut raw, &encrypted, &nonce, &key).map_err(|_| ())?;
-
-            let mut decrypted = vec![0u8; raw.len() - SECRETBOX_ZEROBYTES];
-            decrypted.copy_from_slice(&raw[SECRETBOX_ZEROBYTES..]);
-            decrypted
+            open(encoded, &key)?
         }
-        _ => encoded.to_vec(),
+        _ => Zeroizing::new(encoded.to_vec()),
     };
+    parse(&msg)
+}
 
-    let mut header = [0u8; PKCS8_HEADER.len()];
-    header.copy_from_slice(&msg[..PKCS8_HEADER.len()]);
-
-    if header != PKCS8_HEADER {
-        return Err(());
-    }
-
-    let mut secret_key = [0u8; SEC_LENGTH];
-    let start: usize = PKCS8_HEADER.len();
-    let end: usize = PKCS8_HEADER.len() + SEC_LENGTH;
-    secret_key.copy_from_slice(&msg[start..end]);
-
-    let divider_offset = PKCS8_HEADER.len() + SEC_LENGTH;
-    let divider_end = divider_offset + PKCS8_DIVIDER.len();
-    let mut divider = [0u8; PKCS8_DIVIDER.len()];
-    divider.copy_from_slice(&msg[divider_offset..divider_end]);
-
-    if divider != PKCS8_DIVIDER {
-        let mut secret_key = [0u8; SEED_LENGTH];
-        let start: usize = PKCS8_HEADER.len();
-        let end: usize = PKCS8_HEADER.len() + SEED_LENGTH;
-        secret_key.copy_from_slice(&msg[start..end]);
-
-        let divider_offset = PKCS8_HEADER.len() + secret_key.len();
-        let divider_end = divider_offset + PKCS8_DIVIDER.len();
-        let mut divider = [0u8; PKCS8_DIVIDER.len()];
-        divider.copy_from_slice(&msg[divider_offset..divider_end]);
-
-        if divider != PKCS8_DIVIDER {
-            return Err(());
+/// Decode the keystore v2 format, the secretbox key is derived from the password by scrypt.
+pub fn decode(
+    encoded: &[u8],
+    passphrase: Option<&str>,
+) -> Result<(Vec<u8>, Secret), KeystoreError> {
+    let msg = match passphrase {
+        Some(passphrase) if !passphrase.is_empty() => {
+            let params = ScryptParams::from_bytes(encoded)?;
+            let key = params.derive_key(passphrase.as_bytes())?;
+            open(&encoded[KDF_HEADER_LENGTH..], &key)?
         }
-
-        let pub_offset = PKCS8_HEADER.len() + secret_key.len() + PKCS8_DIVIDER.len();
-        let mut public_key: Vec<u8> = vec![0u8; msg.len() - pub_offset];
-        public_key.copy_from_slice(&msg[pub_offset..]);
-
-        Ok((public_key.to_vec(), secret_key.to_vec()))
-    } else {
-        let pub_offset = PKCS8_HEADER.len() + secret_key.len() + PKCS8_DIVIDER.len();
-        let mut public_key = vec![0u8; msg.len() - pub_offset];
-        public_key.copy_from_slice(&msg[pub_offset..]);
-
-        Ok((public_key.to_vec(), secret_key.to_vec()))
-    }
+        _ => Zeroizing::new(encoded.to_vec()),
+    };
+    parse(&msg)
 }
 
+/// Encode to the keystore v2 format: scrypt header, nonce and the encrypted pkcs8 message.
 pub fn encode(
     secret_key: &[u8],
     public_key: &[u8],
-    passphrase: Option<String>,
-) -> Result<Vec<u8>, ()> {
+    passphrase: Option<&str>,
+) -> Result<Vec<u8>, KeystoreError> {
     let sec_length: usize = secret_key.len();
     let pub_length: usize = public_key.len();
 
     let encoded_length: usize = PKCS8_HEADER.len() + sec_length + PKCS8_DIVIDER.len() + pub_length;
-    let mut encoded = vec![0u8; encoded_length];
+    let mut encoded = Zeroizing::new(vec![0u8; encoded_length]);
 
     let end = PKCS8_HEADER.len();
     encoded[..end].copy_from_slice(&PKCS8_HEADER[..]);
@@ -108,27 +80,32 @@ pub fn encode(
     let start = PKCS8_HEADER.len() + sec_length + PKCS8_DIVIDER.len();
     encoded[start..].copy_from_slice(&public_key[..]);
 
-    let passphrase: String = match passphrase {
+    let passphrase = match passphrase {
         Some(v) if !v.is_empty() => v,
-        _ => return Ok(encoded),
+        _ => return Ok(encoded.to_vec()),
     };
 
-    let pass_bytes = passphrase.as_bytes();
+    let params = ScryptParams::random();
+    let key = params.derive_key(passphrase.as_bytes())?;
 
-    let mut key = [0u8; SECRETBOX_KEY_LEN];
-    key[..pass_bytes.len()].copy_from_slice(pass_bytes);
+    let mut result = params.to_bytes();
+    result.extend_from_slice(&seal(&encoded, &key)?);
+    Ok(result)
+}
 
+/// Encrypt the message, returns the nonce followed by the box.
+fn seal(encoded: &[u8], key: &SecretboxKey) -> Result<Vec<u8>, KeystoreError> {
     let mut rng = thread_rng();
     let mut nonce = [0u8; SECRETBOX_NONCE_LEN];
     rng.fill(&mut nonce);
 
-    let mut msg = vec![0u8; SECRETBOX_ZEROBYTES + encoded_length];
-    msg[SECRETBOX_ZEROBYTES..].copy_from_slice(&encoded[..]);
+    let mut msg = Zeroizing::new(vec![0u8; SECRETBOX_ZEROBYTES + encoded.len()]);
+    msg[SECRETBOX_ZEROBYTES..].copy_from_slice(encoded);
 
     let mut encrypted = vec![0u8; msg.len()];
-    secretbox(&mut encrypted, &msg, &nonce, &key).map_err(|_| ())?;
+    secretbox(&mut encrypted, &msg, &nonce, key).map_err(|_| KeystoreError::Encrypt)?;
 
-    let result_length: usize = encoded_length + SECRETBOX_NONCE_LEN + SECRETBOX_BOXZEROBYTES;
+    let result_length: usize = encoded.len() + SECRETBOX_NONCE_LEN + SECRETBOX_BOXZEROBYTES;
     let mut result = vec![0u8; result_length];
 
     result[..SECRETBOX_NONCE_LEN].copy_from_slice(&nonce[..]);
@@ -136,3 +113,88 @@ pub fn encode(
 
     Ok(result)
 }
+
+/// Decrypt the nonce followed by the box.
+fn open(encoded: &[u8], key: &SecretboxKey) -> Result<Secret, KeystoreError> {
+    let encoded_length = encoded.len();
+    if encoded_length < SECRETBOX_NONCE_LEN + SECRETBOX_BOXZEROBYTES {
+        return Err(KeystoreError::CorruptEncoded(
+            "encrypted data truncated".to_string(),
+        ));
+    }
+
+    let mut nonce: SecretboxNonce = [0u8; SECRETBOX_NONCE_LEN];
+    nonce.copy_from_slice(&encoded[0..SECRETBOX_NONCE_LEN]);
+
+    let mut encrypted = vec![0u8; SECRETBOX_BOXZEROBYTES + encoded_length - SECRETBOX_NONCE_LEN];
+    encrypted[SECRETBOX_BOXZEROBYTES..].copy_from_slice(&encoded[SECRETBOX_NONCE_LEN..]);
+
+    let mut raw = Zeroizing::new(vec![0u8; encrypted.len()]);
+    secretbox_open(&mut raw, &encrypted, &nonce, key).map_err(|_| KeystoreError::WrongPassword)?;
+
+    Ok(Zeroizing::new(raw[SECRETBOX_ZEROBYTES..].to_vec()))
+}
+
+/// Split the pkcs8 message into the public key and secret key.
+fn parse(msg: &[u8]) -> Result<(Vec<u8>, Secret), KeystoreError> {
+    if msg.len() < PKCS8_HEADER.len() + SEED_LENGTH + PKCS8_DIVIDER.len() {
+        return Err(KeystoreError::CorruptEncoded(
+            "pkcs8 message truncated".to_string(),
+        ));
+    }
+
+    let mut header = [0u8; PKCS8_HEADER.len()];
+    header.copy_from_slice(&msg[..PKCS8_HEADER.len()]);
+
+    if header != PKCS8_HEADER {
+        return Err(KeystoreError::CorruptHeader);
+    }
+
+    let divider_offset = PKCS8_HEADER.len() + SEC_LENGTH;
+    let divider_end = divider_offset + PKCS8_DIVIDER.len();
+
+    if msg.len() < divider_end || msg[divider_offset..divider_end] != PKCS8_DIVIDER {
+        let mut secret_key = Zeroizing::new([0u8; SEED_LENGTH]);
+        let start: usize = PKCS8_HEADER.len();
+        let end: usize = PKCS8_HEADER.len() + SEED_LENGTH;
+        secret_key.copy_from_slice(&msg[start..end]);
+
+        let divider_offset = PKCS8_HEADER.len() + secret_key.len();
+        let divider_end = divider_offset + PKCS8_DIVIDER.len();
+        let mut divider = [0u8; PKCS8_DIVIDER.len()];
+        divider.copy_from_slice(&msg[divider_offset..divider_end]);
+
+        if divider != PKCS8_DIVIDER {
+            return Err(KeystoreError::BadDivider);
+        }
+
+        let pub_offset = PKCS8_HEADER.len() + secret_key.len() + PKCS8_DIVIDER.len();
+        let mut public_key: Vec<u8> = vec![0u8; msg.len() - pub_offset];
+        public_key.copy_from_slice(&msg[pub_offset..]);
+
+        Ok((public_key, Zeroizing::new(secret_key.to_vec())))
+    } else {
+        let mut secret_key = Zeroizing::new([0u8; SEC_LENGTH]);
+        let start: usize = PKCS8_HEADER.len();
+        secret_key.copy_from_slice(&msg[start..divider_offset]);
+
+        let pub_offset = divider_end;
+        let mut public_key = vec![0u8; msg.len() - pub_offset];
+        public_key.copy_from_slice(&msg[pub_offset..]);
+
+        Ok((public_key, Zeroizing::new(secret_key.to_vec())))
+    }
+}
+
+#[test]
+fn test_encode_decode() {
+    let (secret_key, public_key) = ([1u8; SEC_LENGTH], [2u8; 32]);
+    let password = Some("a password longer than thirty two bytes");
+
+    let encoded = encode(&secret_key, &public_key, password).unwrap();
+    assert_eq!(
+        decode(&encoded, password).unwrap(),
+        (public_key.to_vec(), Zeroizing::new(secret_key.to_vec()))
+    );
+    assert!(decode(&encoded, Some("wrong")).is_err());
+}
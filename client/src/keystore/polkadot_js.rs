@@ -0,0 +1,176 @@
+use super::crypto::KeyType;
+use super::error::KeystoreError;
+use super::keystore::{Keystore, KEYSTORE_VERSION};
+use super::pkcs8::{self, Secret, PKCS8_HEADER};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use zeroize::Zeroizing;
+
+/// The encoding version of polkadot-js whose key is derived by scrypt, the same as keystore v2.
+pub const SCRYPT_VERSION: &str = "3";
+/// The encoding version of polkadot-js whose key is the zero-padded password, the same as
+/// keystore v1.
+pub const LEGACY_VERSION: &str = "2";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Encoding {
+    // the content of encoded, `["pkcs8", "sr25519"]`.
+    pub content: Vec<String>,
+
+    // the encryption of encoded, `["scrypt", "xsalsa20-poly1305"]`, `"xsalsa20-poly1305"` or
+    // `["none"]`.
+    #[serde(rename = "type")]
+    pub kind: Value,
+
+    // the version of encoding.
+    pub version: String,
+}
+
+/// The multisig metadata of keystore, kept in the `multisig` field of meta when exported.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigMeta {
+    pub threshold: u16,
+    pub others: Vec<String>,
+    pub multi_address: String,
+    pub network: String,
+}
+
+/// The JSON keystore exported by polkadot-js and subkey.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PolkadotJsKeystore {
+    // the encoded data of keystore, base64 or hex.
+    pub encoded: String,
+
+    pub encoding: Encoding,
+
+    // the address of keystore.
+    pub address: String,
+
+    // the name and other metadata of account.
+    #[serde(default)]
+    pub meta: Value,
+}
+
+impl PolkadotJsKeystore {
+    pub fn parse_from_file(path: String) -> Result<Self, KeystoreError> {
+        let data = fs::read_to_string(path)?;
+        let keystore: Self = serde_json::from_str(&data)?;
+        Ok(keystore)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self).unwrap()
+    }
+
+    pub fn encoded_bytes(&self) -> Result<Vec<u8>, KeystoreError> {
+        if let Some(encoded) = self.encoded.strip_prefix("0x") {
+            hex::decode(encoded).map_err(|e| KeystoreError::CorruptEncoded(e.to_string()))
+        } else {
+            base64::decode(&self.encoded).map_err(|e| KeystoreError::CorruptEncoded(e.to_string()))
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        match &self.encoding.kind {
+            Value::String(kind) => kind != "none",
+            Value::Array(kinds) => !kinds.iter().any(|kind| kind == "none"),
+            _ => true,
+        }
+    }
+
+    /// The multisig metadata of the keystore exported by the client, none for polkadot-js.
+    pub fn multisig_meta(&self) -> Option<MultisigMeta> {
+        self.meta
+            .get("multisig")
+            .and_then(|meta| serde_json::from_value(meta.clone()).ok())
+    }
+
+    /// The key type of keystore.
+    pub fn key_type(&self) -> Result<KeyType, KeystoreError> {
+        self.encoding
+            .content
+            .get(1)
+            .and_then(|c| c.parse().ok())
+            .ok_or_else(|| {
+                KeystoreError::UnsupportedEncoding(format!("{:?}", self.encoding.content))
+            })
+    }
+
+    /// The version of keystore with the same encoding.
+    pub fn keystore_version(&self) -> Result<u8, KeystoreError> {
+        match self.encoding.version.as_str() {
+            _ if !self.is_encrypted() => Ok(KEYSTORE_VERSION),
+            SCRYPT_VERSION => Ok(KEYSTORE_VERSION),
+            LEGACY_VERSION => Ok(1),
+            v => Err(KeystoreError::UnsupportedVersion(v.to_string())),
+        }
+    }
+
+    /// Export the keystore re-encoded with the secret key of polkadot-js, the multisig metadata
+    /// is kept in meta for the import. The unencrypted keystore is exported unencrypted.
+    pub fn from_keystore(
+        keystore: &Keystore,
+        name: &str,
+        password: Option<&str>,
+    ) -> Result<Self, KeystoreError> {
+        let password = match password {
+            _ if keystore.encoded_bytes()?.starts_with(&PKCS8_HEADER) => None,
+            Some(password) if !password.is_empty() => Some(password),
+            _ => None,
+        };
+        let (public_key, secret_key) = keystore.decrypt(password)?;
+        let secret_key = export_secret(keystore.key_type, &secret_key)?;
+        let encoded = pkcs8::encode(&secret_key[..], &public_key[..], password)?;
+        let kind = match password {
+            Some(_) => json!(["scrypt", "xsalsa20-poly1305"]),
+            None => json!(["none"]),
+        };
+        Ok(Self {
+            encoded: base64::encode(&encoded),
+            encoding: Encoding {
+                content: vec!["pkcs8".to_string(), keystore.key_type.to_string()],
+                kind,
+                version: SCRYPT_VERSION.to_string(),
+            },
+            address: keystore.address.clone(),
+            meta: json!({
+                "name": name,
+                "whenCreated": chrono::Utc::now().timestamp_millis(),
+                "multisig": MultisigMeta {
+                    threshold: keystore.threshold,
+                    others: keystore.others.clone(),
+                    multi_address: keystore.multi_address.clone(),
+                    network: keystore.network.clone(),
+                },
+            }),
+        })
+    }
+}
+
+/// Convert the sr25519 secret key of keystore, the canonical one of schnorrkel, to the ed25519
+/// expanded one kept by polkadot-js.
+fn export_secret(key_type: KeyType, secret_key: &[u8]) -> Result<Secret, KeystoreError> {
+    match key_type {
+        KeyType::Sr25519 => {
+            let secret_key = schnorrkel::SecretKey::from_bytes(secret_key)
+                .map_err(|_| KeystoreError::InvalidSecretKey)?;
+            Ok(Zeroizing::new(secret_key.to_ed25519_bytes().to_vec()))
+        }
+        _ => Ok(Zeroizing::new(secret_key.to_vec())),
+    }
+}
+
+/// Convert the sr25519 secret key of polkadot-js back to the canonical one of keystore.
+pub fn import_secret(key_type: KeyType, secret_key: &[u8]) -> Result<Secret, KeystoreError> {
+    match key_type {
+        KeyType::Sr25519 => {
+            let secret_key = schnorrkel::SecretKey::from_ed25519_bytes(secret_key)
+                .map_err(|_| KeystoreError::InvalidSecretKey)?;
+            Ok(Zeroizing::new(secret_key.to_bytes().to_vec()))
+        }
+        _ => Ok(Zeroizing::new(secret_key.to_vec())),
+    }
+}
@@ -0,0 +1,418 @@
+use async_std::{
+    io::{self, prelude::*, timeout, BufReader},
+    net::TcpStream,
+    os::unix::net::UnixStream,
+};
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::Ss58Codec;
+use sp_runtime::traits::SignedExtension;
+use std::str::FromStr;
+use std::time::Duration;
+use substrate_subxt::{Runtime, SignedExtra, SignedPayload, Signer, UncheckedExtrinsic};
+
+/// The environment variable holding the token which authenticates the client to the remote
+/// signer, required by the tcp and http endpoints.
+pub const REMOTE_SIGNER_TOKEN_ENV: &str = "REMOTE_SIGNER_TOKEN";
+
+/// How long to wait for the connection to the remote signer.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for the remote signer to respond once connected.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The request sent to the remote signer, one JSON object per line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignRequest {
+    // the ss58 address of account to sign with.
+    pub account: String,
+
+    // the hex encoded payload to sign, payloads longer than 256 bytes are already hashed.
+    pub payload: String,
+
+    // the token authenticating the client, sent as the bearer token of http endpoints instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// The response of the remote signer, one JSON object per line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignResponse {
+    // the hex encoded SCALE `MultiSignature` of payload.
+    #[serde(default)]
+    pub signature: Option<String>,
+
+    // the reason why the remote signer refused to sign.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// The way to reach the remote signer.
+#[async_trait]
+pub trait SignerTransport: Send + Sync {
+    async fn request(&self, request: SignRequest) -> Result<SignResponse, String>;
+}
+
+/// The endpoint of remote signer: `unix:<path>`, `tcp:<host:port>` or `http://<host:port>/<path>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Unix(String),
+    Tcp(String),
+    Http { addr: String, path: String },
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            Ok(Endpoint::Unix(path.to_string()))
+        } else if let Some(addr) = s.strip_prefix("tcp:") {
+            Ok(Endpoint::Tcp(addr.to_string()))
+        } else if let Some(url) = s.strip_prefix("http://") {
+            let (addr, path) = match url.find('/') {
+                Some(i) => (&url[..i], &url[i..]),
+                None => (url, "/"),
+            };
+            Ok(Endpoint::Http {
+                addr: addr.to_string(),
+                path: path.to_string(),
+            })
+        } else {
+            Err(format!("unknown remote signer endpoint: {}", s))
+        }
+    }
+}
+
+impl Endpoint {
+    /// The transport to the endpoint, authenticated by the token of `REMOTE_SIGNER_TOKEN`.
+    /// The unix socket is guarded by its file permissions, the token is optional there.
+    pub fn transport(&self) -> Result<RemoteTransport, String> {
+        let token = std::env::var(REMOTE_SIGNER_TOKEN_ENV).ok();
+        if token.is_none() && !matches!(self, Endpoint::Unix(_)) {
+            return Err(format!(
+                "{} must be set to authenticate to the remote signer {:?}",
+                REMOTE_SIGNER_TOKEN_ENV, self
+            ));
+        }
+        Ok(RemoteTransport {
+            endpoint: self.clone(),
+            token,
+            connect_timeout: CONNECT_TIMEOUT,
+            response_timeout: RESPONSE_TIMEOUT,
+        })
+    }
+}
+
+/// The transport to a remote signer endpoint.
+#[derive(Clone, Debug)]
+pub struct RemoteTransport {
+    endpoint: Endpoint,
+    token: Option<String>,
+    connect_timeout: Duration,
+    response_timeout: Duration,
+}
+
+#[async_trait]
+impl SignerTransport for RemoteTransport {
+    async fn request(&self, mut request: SignRequest) -> Result<SignResponse, String> {
+        let response = match &self.endpoint {
+            Endpoint::Unix(path) => {
+                request.token = self.token.clone();
+                let stream = timeout(self.connect_timeout, UnixStream::connect(path))
+                    .await
+                    .map_err(|e| format!("connect to remote signer: {}", e))?;
+                let body = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+                timeout(self.response_timeout, exchange_line(stream, &body)).await
+            }
+            Endpoint::Tcp(addr) => {
+                request.token = self.token.clone();
+                let stream = timeout(self.connect_timeout, TcpStream::connect(addr))
+                    .await
+                    .map_err(|e| format!("connect to remote signer: {}", e))?;
+                let body = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+                timeout(self.response_timeout, exchange_line(stream, &body)).await
+            }
+            Endpoint::Http { addr, path } => {
+                let stream = timeout(self.connect_timeout, TcpStream::connect(addr))
+                    .await
+                    .map_err(|e| format!("connect to remote signer: {}", e))?;
+                let body = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+                let token = self.token.as_deref();
+                timeout(
+                    self.response_timeout,
+                    exchange_http(stream, addr, path, token, &body),
+                )
+                .await
+            }
+        }
+        .map_err(|e| format!("remote signer: {}", e))?;
+        serde_json::from_str(&response).map_err(|e| e.to_string())
+    }
+}
+
+/// Write the request line and read the response line.
+async fn exchange_line<S: Read + Write + Unpin>(mut stream: S, body: &str) -> io::Result<String> {
+    stream.write_all(format!("{}\n", body).as_bytes()).await?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).await?;
+    Ok(line)
+}
+
+/// Post the request and read the body of response.
+async fn exchange_http<S: Read + Write + Unpin>(
+    mut stream: S,
+    addr: &str,
+    path: &str,
+    token: Option<&str>,
+    body: &str,
+) -> io::Result<String> {
+    let authorization = token
+        .map(|token| format!("Authorization: Bearer {}\r\n", token))
+        .unwrap_or_default();
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\n{}Content-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        addr,
+        authorization,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await?;
+    http_response_body(&response).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The body of a successful HTTP/1.x response, either delimited by `Content-Length`, chunked or
+/// up to the end of connection.
+fn http_response_body(response: &[u8]) -> Result<String, String> {
+    let head_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or("malformed http response: no end of headers")?;
+    let head = std::str::from_utf8(&response[..head_end])
+        .map_err(|_| "malformed http response: headers are not utf8")?;
+    let body = &response[head_end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    let mut status = status_line.splitn(3, ' ');
+    match (status.next(), status.next().map(str::parse::<u16>)) {
+        (Some(version), Some(Ok(code))) if version.starts_with("HTTP/1.") => {
+            if !(200..300).contains(&code) {
+                return Err(format!("remote signer responded: {}", status_line));
+            }
+        }
+        _ => return Err(format!("malformed http status line: {}", status_line)),
+    }
+
+    let mut chunked = false;
+    let mut content_length = None;
+    for line in lines {
+        let (name, value) = match line.find(':') {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => return Err(format!("malformed http header: {}", line)),
+        };
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.to_ascii_lowercase().ends_with("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("malformed content length: {}", value))?,
+            );
+        }
+    }
+
+    let body = if chunked {
+        decode_chunked(body)?
+    } else if let Some(length) = content_length {
+        body.get(..length)
+            .ok_or("truncated http response body")?
+            .to_vec()
+    } else {
+        body.to_vec()
+    };
+    String::from_utf8(body).map_err(|_| "http response body is not utf8".to_string())
+}
+
+/// Join the chunks of a chunked transfer encoded body, the trailers are ignored.
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = vec![];
+    loop {
+        let line_end = body
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or("malformed chunked body: no chunk size")?;
+        let size = std::str::from_utf8(&body[..line_end])
+            .ok()
+            .and_then(|line| line.split(';').next())
+            .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+            .ok_or("malformed chunked body: invalid chunk size")?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Ok(decoded);
+        }
+        if body.len() < size + 2 || &body[size..size + 2] != b"\r\n" {
+            return Err("malformed chunked body: truncated chunk".to_string());
+        }
+        decoded.extend_from_slice(&body[..size]);
+        body = &body[size + 2..];
+    }
+}
+
+/// The signer which sends the payload to an external signer process, the private key never
+/// lives in the client process.
+pub struct RemoteSigner<T: Runtime> {
+    account_id: T::AccountId,
+    nonce: Option<T::Index>,
+    transport: Box<dyn SignerTransport>,
+}
+
+impl<T: Runtime> RemoteSigner<T>
+where
+    T::AccountId: Ss58Codec,
+{
+    pub fn new<R: SignerTransport + 'static>(account_id: T::AccountId, transport: R) -> Self {
+        Self {
+            account_id,
+            nonce: None,
+            transport: Box::new(transport),
+        }
+    }
+
+    /// Ask the remote signer to sign the payload.
+    pub async fn sign_payload(&self, payload: &[u8]) -> Result<T::Signature, String> {
+        let request = SignRequest {
+            account: self.account_id.to_ss58check(),
+            payload: format!("0x{}", hex::encode(payload)),
+            token: None,
+        };
+        let response = self.transport.request(request).await?;
+        match (response.signature, response.error) {
+            (_, Some(error)) => Err(format!("remote signer refused to sign: {}", error)),
+            (Some(signature), None) => {
+                let signature = signature.strip_prefix("0x").unwrap_or(&signature);
+                let bytes = hex::decode(signature).map_err(|e| e.to_string())?;
+                T::Signature::decode(&mut &bytes[..]).map_err(|e| e.to_string())
+            }
+            (None, None) => Err("remote signer returned no signature".to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Signer<T> for RemoteSigner<T>
+where
+    T: Runtime,
+    T::AccountId: Into<T::Address> + Ss58Codec + 'static,
+    <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+        Send + Sync + 'static,
+{
+    fn account_id(&self) -> &T::AccountId {
+        &self.account_id
+    }
+
+    fn nonce(&self) -> Option<T::Index> {
+        self.nonce
+    }
+
+    async fn sign(&self, extrinsic: SignedPayload<T>) -> Result<UncheckedExtrinsic<T>, String> {
+        let payload = extrinsic.using_encoded(|payload| payload.to_vec());
+        let signature = self.sign_payload(&payload).await?;
+        let (call, extra, _) = extrinsic.deconstruct();
+        Ok(UncheckedExtrinsic::<T>::new_signed(
+            call,
+            self.account_id.clone().into(),
+            signature,
+            extra,
+        ))
+    }
+}
+
+/// Sign with a local pair, stands in for the remote signer process in tests.
+#[cfg(test)]
+struct MockTransport(sp_core::sr25519::Pair);
+
+#[cfg(test)]
+#[async_trait]
+impl SignerTransport for MockTransport {
+    async fn request(&self, request: SignRequest) -> Result<SignResponse, String> {
+        use sp_core::Pair;
+
+        let payload = hex::decode(&request.payload[2..]).map_err(|e| e.to_string())?;
+        let signature = sp_runtime::MultiSignature::from(self.0.sign(&payload));
+        Ok(SignResponse {
+            signature: Some(format!("0x{}", hex::encode(signature.encode()))),
+            error: None,
+        })
+    }
+}
+
+#[async_std::test]
+async fn test_remote_signer() {
+    use runtime::kusama::runtime::KusamaRuntime;
+    use sp_core::Pair;
+    use sp_runtime::traits::Verify;
+
+    let pair = sp_core::sr25519::Pair::from_string("//Alice", None).unwrap();
+    let account_id = pair.public().into();
+    let signer = RemoteSigner::<KusamaRuntime>::new(account_id, MockTransport(pair.clone()));
+
+    let payload = b"payload";
+    let signature = signer.sign_payload(payload).await.unwrap();
+    assert!(signature.verify(&payload[..], signer.account_id()));
+}
+
+#[async_std::test]
+async fn test_remote_signer_timeout() {
+    let listener = async_std::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    // accept and never respond
+    let _server = async_std::task::spawn(async move {
+        let (_stream, _) = listener.accept().await.unwrap();
+        async_std::future::pending::<()>().await
+    });
+
+    let transport = RemoteTransport {
+        endpoint: Endpoint::Tcp(addr),
+        token: Some("token".to_string()),
+        connect_timeout: Duration::from_secs(1),
+        response_timeout: Duration::from_millis(100),
+    };
+    let request = SignRequest {
+        account: String::new(),
+        payload: "0x00".to_string(),
+        token: None,
+    };
+    let error = transport.request(request).await.unwrap_err();
+    assert!(error.contains("timed out"), "{}", error);
+}
+
+#[test]
+fn test_http_response_body() {
+    let ok = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n{}{}";
+    assert_eq!(http_response_body(ok).unwrap(), "{}{}");
+    let chunked = b"HTTP/1.0 201 Created\r\nTransfer-Encoding: chunked\r\n\r\n\
+                    3\r\n{\"s\r\n5;ext=1\r\n\": 1}\r\n0\r\n\r\n";
+    assert_eq!(http_response_body(chunked).unwrap(), "{\"s\": 1}");
+
+    // malformed responses
+    for response in &[
+        &b"HTTP/1.1 500 Internal Server Error\r\n\r\n{}"[..],
+        b"HTTP/2 200\r\n\r\n{}",
+        b"HTTP/1.1 abc\r\n\r\n{}",
+        b"HTTP/1.1 200 OK\r\nno header\r\n\r\n{}",
+        b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n{}",
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n{}\r\n0\r\n\r\n",
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\n{}\r\n",
+        b"HTTP/1.1 200 OK",
+    ] {
+        assert!(http_response_body(response).is_err());
+    }
+}
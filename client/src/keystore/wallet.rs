@@ -1,11 +1,19 @@
 use super::crypto::*;
-use super::keystore::Keystore;
+use super::error::KeystoreError;
+use super::keystore::{Keystore, KEYSTORE_VERSION};
+use super::mnemonic::{self, Derivation};
+use super::password::{NewPasswordOpt, PasswordOpt};
 use super::pkcs8;
+use super::polkadot_js::{self, PolkadotJsKeystore};
 use super::AccountId;
+use crate::common::multisig::{ensure_sorted_and_insert, multi_account_id};
+use crate::common::profile::Network;
 
-use sp_core::{blake2_256, crypto::Ss58Codec, hexdisplay::HexDisplay, Decode, Encode};
+use sp_core::{crypto::Ss58Codec, hexdisplay::HexDisplay};
 use std::fs;
 use structopt::StructOpt;
+use tracing::warn;
+use zeroize::Zeroizing;
 
 #[derive(Debug, StructOpt)]
 pub struct CreateCmd {
@@ -20,117 +28,647 @@ pub struct CreateCmd {
     /// the other signatories of multi-signature accounts
     #[structopt(short, long)]
     pub other_signatories: Vec<String>,
+
+    /// the network of keystore: kusama, polkadot or dev
+    #[structopt(long, default_value = "dev")]
+    pub network: Network,
+
+    /// the key type of signer: sr25519, ed25519 or ecdsa
+    #[structopt(long, default_value = "sr25519")]
+    pub key_type: KeyType,
+
+    /// generate a new mnemonic instead of typing the seed or mnemonic
+    #[structopt(long)]
+    pub generate: bool,
+
+    /// the word count of generated mnemonic: 12, 15, 18, 21 or 24
+    #[structopt(long, default_value = "24")]
+    pub words: usize,
+
+    /// the derivation path of key, e.g. `//stake//0`
+    #[structopt(long)]
+    pub derivation_path: Option<String>,
+
+    #[structopt(flatten)]
+    pub password: PasswordOpt,
 }
 
 impl CreateCmd {
     /// Run the command
     pub fn run(&self) {
+        self.network.relay_chain().use_ss58_format();
         let mut other_addresses = vec![];
         for a in self.other_signatories.iter() {
-            println!("a:{:?}", a);
-            let _check = AccountId::from_ss58check(a).unwrap();
+            if let Err(e) = AccountId::from_ss58check(a) {
+                return println!("invalid other signatory {}: {:?}", a, e);
+            }
             other_addresses.push(a);
         }
 
         // create multi signature keystore
-        if let Some(seed) = rpassword::read_password_from_tty(Some("Type seed:")).ok() {
-            let password = rpassword::read_password_from_tty(Some("Type password:")).ok();
-            match create_keystore(
-                password,
-                self.threshold.clone(),
-                seed,
-                self.other_signatories.clone(),
-            ) {
-                Ok(keystore) => {
-                    // create keystore file
-                    let file_name = format!("{}.json", self.name);
-                    if let Err(e) = fs::write(file_name.clone(), keystore.to_json()) {
-                        println!("failed to write to file: {:?}", e);
-                    } else {
-                        println!("keystore file created: {}\n{:?}", file_name, keystore);
+        match self.read_suri() {
+            Ok(suri) => {
+                let password = match self.password.read("Type password:") {
+                    Ok(password) => password,
+                    Err(e) => return println!("{}", e),
+                };
+                let create = match self.key_type {
+                    KeyType::Sr25519 => create_keystore::<Sr25519>,
+                    KeyType::Ed25519 => create_keystore::<Ed25519>,
+                    KeyType::Ecdsa => create_keystore::<Ecdsa>,
+                };
+                match create(
+                    password.as_ref().map(|p| p.as_str()),
+                    self.threshold.clone(),
+                    &suri,
+                    self.other_signatories.clone(),
+                    self.network,
+                ) {
+                    Ok(keystore) => {
+                        // create keystore file
+                        let file_name = format!("{}.json", self.name);
+                        if let Err(e) = fs::write(file_name.clone(), keystore.to_json()) {
+                            println!("failed to write to file: {:?}", e);
+                        } else {
+                            println!("keystore file created: {}\n{:?}", file_name, keystore);
+                        }
+                    }
+                    Err(e) => {
+                        println!("create keystore error: {}", e);
                     }
                 }
-                Err(e) => {
-                    println!("create keystore error:{:?}", e);
+            }
+            Err(e) => println!("invalid seed: {}", e),
+        }
+    }
+
+    /// Generate or read the mnemonic or seed, and append the derivation path to it.
+    fn read_suri(&self) -> Result<Zeroizing<String>, KeystoreError> {
+        let secret = if self.generate {
+            let phrase = mnemonic::generate(self.words)?;
+            println!(
+                "write down the mnemonic, it is shown only once:\n\n    {}\n",
+                *phrase
+            );
+            phrase
+        } else {
+            Zeroizing::new(rpassword::read_password_from_tty(Some(
+                "Type seed or mnemonic:",
+            ))?)
+        };
+
+        // the mnemonic is typed again to catch the typos
+        let (phrase, _) = mnemonic::split_suri(&secret);
+        if !phrase.is_empty() && !phrase.starts_with("0x") {
+            mnemonic::validate(phrase)?;
+            let repeated =
+                Zeroizing::new(rpassword::read_password_from_tty(Some("Repeat mnemonic:"))?);
+            if repeated.split_whitespace().ne(phrase.split_whitespace()) {
+                return Err(KeystoreError::InvalidMnemonic(
+                    "the repeated mnemonic does not match".to_string(),
+                ));
+            }
+        }
+
+        match &self.derivation_path {
+            Some(path) if !path.starts_with('/') => {
+                Err(KeystoreError::InvalidDerivationPath(path.clone()))
+            }
+            Some(path) => Ok(Zeroizing::new(format!("{}{}", *secret, path))),
+            None => Ok(secret),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct MigrateCmd {
+    /// the keystore to migrate
+    #[structopt(short, long, default_value = "keystore.json")]
+    pub key_store: String,
+
+    #[structopt(flatten)]
+    pub password: PasswordOpt,
+}
+
+impl MigrateCmd {
+    /// Run the command
+    pub fn run(&self) {
+        let keystore = match get_keystore(self.key_store.to_string()) {
+            Ok(keystore) => keystore,
+            Err(e) => return println!("failed to get keystore: {}", e),
+        };
+        if keystore.version == KEYSTORE_VERSION {
+            return println!("keystore is already version {}", KEYSTORE_VERSION);
+        }
+
+        let password = match self.password.read("Type password:") {
+            Ok(password) => password,
+            Err(e) => return println!("{}", e),
+        };
+        let migrated = match keystore.migrate(password.as_ref().map(|p| p.as_str())) {
+            Ok(migrated) => migrated,
+            Err(e) => return println!("failed to decode keystore: {}", e),
+        };
+
+        // keep the old keystore until the new one is written
+        let backup = format!("{}.v{}.bak", self.key_store, keystore.version);
+        if let Err(e) = fs::copy(&self.key_store, &backup) {
+            return println!("failed to backup keystore: {:?}", e);
+        }
+        if let Err(e) = fs::write(&self.key_store, migrated.to_json()) {
+            println!("failed to write to file: {:?}", e);
+        } else {
+            println!(
+                "keystore migrated to version {}: {}, the old one is kept in {}",
+                KEYSTORE_VERSION, self.key_store, backup
+            );
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ImportCmd {
+    /// the polkadot-js JSON keystore to import
+    #[structopt(long)]
+    pub polkadot_js: String,
+
+    /// the keystore name
+    #[structopt(short, long, default_value = "keystore")]
+    pub name: String,
+
+    /// the threshold of multi-signature accounts, defaults to the one exported by the client
+    #[structopt(short, long)]
+    pub threshold: Option<u16>,
+
+    /// the other signatories of multi-signature accounts, default to the ones exported by the
+    /// client
+    #[structopt(short, long)]
+    pub other_signatories: Vec<String>,
+
+    /// the network of keystore: kusama, polkadot or dev
+    #[structopt(long, default_value = "dev")]
+    pub network: Network,
+
+    #[structopt(flatten)]
+    pub password: PasswordOpt,
+}
+
+impl ImportCmd {
+    /// Run the command
+    pub fn run(&self) {
+        self.network.relay_chain().use_ss58_format();
+        let password = match self.password.read("Type password:") {
+            Ok(password) => password,
+            Err(e) => return println!("{}", e),
+        };
+        match import_keystore(
+            self.polkadot_js.to_string(),
+            password.as_ref().map(|p| p.as_str()),
+            self.threshold,
+            self.other_signatories.clone(),
+            self.network,
+        ) {
+            Ok(keystore) => {
+                let file_name = format!("{}.json", self.name);
+                if let Err(e) = fs::write(file_name.clone(), keystore.to_json()) {
+                    println!("failed to write to file: {:?}", e);
+                } else {
+                    println!("keystore file imported: {}\n{:?}", file_name, keystore);
                 }
             }
+            Err(e) => {
+                println!("import keystore error: {}", e);
+            }
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ExportCmd {
+    /// the keystore to export
+    #[structopt(short, long, default_value = "keystore.json")]
+    pub key_store: String,
+
+    /// the account name shown in polkadot-js
+    #[structopt(short, long, default_value = "stake-client")]
+    pub name: String,
+
+    /// the polkadot-js JSON keystore to write
+    #[structopt(short, long, default_value = "polkadot-js.json")]
+    pub output: String,
+
+    #[structopt(flatten)]
+    pub password: PasswordOpt,
+}
+
+impl ExportCmd {
+    /// Run the command
+    pub fn run(&self) {
+        let keystore = match get_keystore(self.key_store.to_string()) {
+            Ok(keystore) => keystore,
+            Err(e) => return println!("failed to get keystore: {}", e),
+        };
+        let password = match self.password.read("Type password:") {
+            Ok(password) => password,
+            Err(e) => return println!("{}", e),
+        };
+        let exported = match PolkadotJsKeystore::from_keystore(
+            &keystore,
+            &self.name,
+            password.as_ref().map(|p| p.as_str()),
+        ) {
+            Ok(exported) => exported,
+            Err(e) => return println!("failed to export keystore: {}", e),
+        };
+        if let Err(e) = fs::write(&self.output, exported.to_json()) {
+            println!("failed to write to file: {:?}", e);
         } else {
-            println!("invalid seed")
+            println!("keystore exported: {}", self.output);
         }
     }
 }
 
-pub fn get_keystore(path: String) -> Result<Keystore, Box<dyn std::error::Error>> {
-    let k = Keystore::parse_from_file(path).map_err(|_err| "failed to get keystore from file")?;
-    Ok(k)
+#[derive(Debug, StructOpt)]
+pub struct InspectCmd {
+    /// the keystore to inspect
+    #[structopt(short, long, default_value = "keystore.json")]
+    pub key_store: String,
+}
+
+impl InspectCmd {
+    /// Run the command
+    pub fn run(&self) {
+        let keystore = match get_keystore(self.key_store.to_string()) {
+            Ok(keystore) => keystore,
+            Err(e) => return println!("failed to get keystore: {}", e),
+        };
+        println!("version:       {}", keystore.version);
+        println!("key type:      {}", keystore.key_type);
+        if let Some(derivation) = &keystore.derivation {
+            println!("derivation:    {}", derivation);
+        }
+        println!("network:       {}", keystore.network);
+        println!("address:       {}", keystore.address);
+        println!("multi address: {}", keystore.multi_address);
+        println!("threshold:     {}", keystore.threshold);
+        println!("signatories:");
+        for a in keystore.others.iter() {
+            println!("    {}", a);
+        }
+        match check_multi_address(&keystore) {
+            Ok(true) => println!("multi address matches the signatories and threshold"),
+            Ok(false) => {
+                println!("multi address does NOT match the signatories and threshold");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                println!("failed to recompute multi address: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct VerifyCmd {
+    /// the keystore to verify
+    #[structopt(short, long, default_value = "keystore.json")]
+    pub key_store: String,
+
+    #[structopt(flatten)]
+    pub password: PasswordOpt,
+}
+
+impl VerifyCmd {
+    /// Run the command
+    pub fn run(&self) {
+        let keystore = match get_keystore(self.key_store.to_string()) {
+            Ok(keystore) => keystore,
+            Err(e) => return println!("failed to get keystore: {}", e),
+        };
+        let password = match self.password.read("Type password:") {
+            Ok(password) => password,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let account_id = match keystore.decrypt_account_id(password.as_ref().map(|p| p.as_str())) {
+            Ok(account_id) => account_id,
+            Err(e) => {
+                println!("failed to decrypt keystore: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let address = AccountId::from_ss58check_with_version(&keystore.address)
+            .ok()
+            .map(|(a, _)| a);
+        if address != Some(account_id) {
+            println!("the decrypted key does not match the address of keystore");
+            std::process::exit(1);
+        }
+        println!("keystore verified: {}", keystore.address);
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ChangePasswordCmd {
+    /// the keystore to change password
+    #[structopt(short, long, default_value = "keystore.json")]
+    pub key_store: String,
+
+    // the old password of keystore
+    #[structopt(flatten)]
+    pub password: PasswordOpt,
+
+    // the new password of keystore
+    #[structopt(flatten)]
+    pub new_password: NewPasswordOpt,
+}
+
+impl ChangePasswordCmd {
+    /// Run the command
+    pub fn run(&self) {
+        let keystore = match get_keystore(self.key_store.to_string()) {
+            Ok(keystore) => keystore,
+            Err(e) => return println!("failed to get keystore: {}", e),
+        };
+        let password = match self.password.read("Type old password:") {
+            Ok(password) => password,
+            Err(e) => return println!("{}", e),
+        };
+        let new_password = match self.new_password.read() {
+            Ok(new_password) => new_password,
+            Err(e) => return println!("{}", e),
+        };
+        let changed = match keystore.change_password(
+            password.as_ref().map(|p| p.as_str()),
+            Some(new_password.as_str()),
+        ) {
+            Ok(changed) => changed,
+            Err(e) => return println!("failed to decode keystore: {}", e),
+        };
+
+        // keep the old keystore until the new one is written
+        let backup = format!("{}.bak", self.key_store);
+        if let Err(e) = fs::copy(&self.key_store, &backup) {
+            return println!("failed to backup keystore: {:?}", e);
+        }
+        if let Err(e) = fs::write(&self.key_store, changed.to_json()) {
+            println!("failed to write to file: {:?}", e);
+        } else {
+            println!(
+                "keystore password changed: {}, the old one is kept in {}",
+                self.key_store, backup
+            );
+        }
+    }
+}
+
+pub fn get_keystore(path: String) -> Result<Keystore, KeystoreError> {
+    Keystore::parse_from_file(path)
 }
 
-pub fn create_keystore(
-    password: Option<String>,
+pub fn create_keystore<C: Crypto>(
+    password: Option<&str>,
     threshold: u16,
-    seed: String,
+    suri: &str,
     others: Vec<String>,
-) -> Result<Keystore, Box<dyn std::error::Error>> {
+    network: Network,
+) -> Result<Keystore, KeystoreError> {
     // encoded data
     // let seed_hex = &hex::decode(seed).map_err(|_err| "invalid seed")?;
     // let pair = Sr25519::pair_from_seed(&seed_hex)
-    let pair = Sr25519::pair_from_seed(&seed).map_err(|_err| "failed to create pair from seed")?;
+    let derivation = Derivation::from_suri(suri)?;
+    let pair = C::pair_from_seed(suri)?;
     let (public_key, secret_key) = (pair.public().to_raw_vec(), pair.to_raw_vec());
-    let encoded = pkcs8::encode(&secret_key[..], &public_key[..], password)
-        .map_err(|_err| "failed to encode pair")?;
+    let encoded = pkcs8::encode(&secret_key[..], &public_key[..], password)?;
     // let addr = pair.public().to_ss58check();
     println!(
         "public key:{}",
         format!("0x{}", HexDisplay::from(&public_key.as_ref()))
     );
 
+    let keystore = multisig_keystore(
+        KEYSTORE_VERSION,
+        C::KEY_TYPE,
+        C::account_id(pair.public()),
+        encoded,
+        threshold,
+        others,
+        network,
+    )?;
+    Ok(Keystore {
+        derivation: Some(derivation),
+        ..keystore
+    })
+}
+
+/// The keystore of signer `who` with the multisig metadata.
+fn multisig_keystore(
+    version: u8,
+    key_type: KeyType,
+    who: AccountId,
+    encoded: Vec<u8>,
+    threshold: u16,
+    others: Vec<String>,
+    network: Network,
+) -> Result<Keystore, KeystoreError> {
     // multi signature address
     let mut other_signatories: Vec<AccountId> = vec![];
     for a in others.iter() {
-        let account_id = AccountId::from_string(&a).map_err(|_err| "invalid other account")?;
+        let account_id =
+            AccountId::from_string(&a).map_err(|_err| KeystoreError::InvalidAddress(a.clone()))?;
         other_signatories.push(account_id);
     }
-    let signatories = ensure_sorted_and_insert(other_signatories, pair.public().into())
-        .map_err(|_err| "failed to sort signatories")?;
+    let signatories = ensure_sorted_and_insert(other_signatories, who.clone())
+        .map_err(KeystoreError::InvalidSignatories)?;
     let id = multi_account_id(&signatories, threshold.clone());
 
+    let ss58_format = network.relay_chain().ss58_format();
     let k = Keystore {
-        address: format!("{}", pair.public()),
-        multi_address: id.to_string(),
+        version,
+        key_type,
+        address: who.to_ss58check_with_version(ss58_format),
+        multi_address: id.to_ss58check_with_version(ss58_format),
         others,
         threshold,
-        network: "ksm".to_string(),
+        network: network.name().to_string(),
         encoded: format!("0x{}", hex::encode(encoded)),
+        derivation: None,
     };
     Ok(k)
 }
 
-fn ensure_sorted_and_insert(
-    other_signatories: Vec<AccountId>,
-    who: AccountId,
-) -> Result<Vec<AccountId>, String> {
-    let mut signatories = other_signatories;
-    let mut maybe_last = None;
-    let mut index = 0;
-    for item in signatories.iter() {
-        if let Some(last) = maybe_last {
-            if last >= item {
-                return Err("SignatoriesOutOfOrder".into());
-            }
-        }
-        if item <= &who {
-            if item == &who {
-                return Err("SenderInSignatories".into());
-            }
-            index += 1;
-        }
-        maybe_last = Some(item);
+pub fn import_keystore(
+    path: String,
+    password: Option<&str>,
+    threshold: Option<u16>,
+    others: Vec<String>,
+    network: Network,
+) -> Result<Keystore, KeystoreError> {
+    let imported = PolkadotJsKeystore::parse_from_file(path)?;
+    let version = imported.keystore_version()?;
+    let key_type = imported.key_type()?;
+    let encoded = imported.encoded_bytes()?;
+    let (who, _) = AccountId::from_ss58check_with_version(&imported.address)
+        .map_err(|_err| KeystoreError::InvalidAddress(imported.address.clone()))?;
+
+    // the multisig metadata given on the command line takes precedence over the exported one
+    let meta = imported.multisig_meta();
+    let threshold = threshold
+        .or_else(|| meta.as_ref().map(|meta| meta.threshold))
+        .ok_or_else(|| KeystoreError::InvalidSignatories("threshold is not given".to_string()))?;
+    let others = match meta {
+        Some(meta) if others.is_empty() => meta.others,
+        _ => others,
+    };
+
+    let keystore = multisig_keystore(
+        version,
+        key_type,
+        who.clone(),
+        encoded,
+        threshold,
+        others,
+        network,
+    )?;
+    let password = if imported.is_encrypted() {
+        password
+    } else {
+        None
+    };
+    // re-encode the key with the secret key of keystore instead of the one of polkadot-js
+    let (public_key, secret_key) = keystore.decrypt(password)?;
+    let secret_key = polkadot_js::import_secret(key_type, &secret_key)?;
+    let encoded = pkcs8::encode(&secret_key[..], &public_key[..], password)?;
+    let keystore = Keystore {
+        version: KEYSTORE_VERSION,
+        encoded: format!("0x{}", hex::encode(encoded)),
+        ..keystore
+    };
+    // the multisig account is computed from the address, which must be the one of the key
+    if keystore.decrypt_account_id(password)? != who {
+        return Err(KeystoreError::AddressMismatch(imported.address));
     }
-    signatories.insert(index, who);
-    Ok(signatories)
+    Ok(keystore)
+}
+
+/// Recompute the multisig account from the signatories and threshold of keystore, and check it
+/// is the multi address of keystore.
+pub fn check_multi_address(keystore: &Keystore) -> Result<bool, KeystoreError> {
+    let (who, _) = AccountId::from_ss58check_with_version(&keystore.address)
+        .map_err(|_err| KeystoreError::InvalidAddress(keystore.address.clone()))?;
+    let (multi_address, _) = AccountId::from_ss58check_with_version(&keystore.multi_address)
+        .map_err(|_err| KeystoreError::InvalidAddress(keystore.multi_address.clone()))?;
+    let other_signatories = keystore.get_other_signatories()?;
+    let signatories = ensure_sorted_and_insert(other_signatories, who)
+        .map_err(KeystoreError::InvalidSignatories)?;
+    Ok(multi_account_id(&signatories, keystore.threshold) == multi_address)
 }
 
-fn multi_account_id(who: &[AccountId], threshold: u16) -> AccountId {
-    let entropy = (b"modlpy/utilisuba", who, threshold).using_encoded(blake2_256);
-    AccountId::decode(&mut &entropy[..]).unwrap_or_default()
+/// Warn if the keystore was created for another network than the one the client runs on.
+pub fn warn_network_mismatch(keystore_network: &str, network: Network) {
+    match keystore_network.parse::<Network>() {
+        Ok(n) if n == network => {}
+        _ => warn!(
+            "keystore is created for network {}, but running on {}",
+            keystore_network, network
+        ),
+    }
+}
+
+#[test]
+fn test_import_keystore() {
+    use sp_keyring::AccountKeyring;
+
+    let bob = AccountKeyring::Bob.to_account_id().to_ss58check();
+    let keystore =
+        create_keystore::<Sr25519>(Some("password"), 2, "//Alice", vec![bob], Network::Dev)
+            .unwrap();
+    let mut exported =
+        PolkadotJsKeystore::from_keystore(&keystore, "alice", Some("password")).unwrap();
+    let path = std::env::temp_dir().join("stake-client-test-polkadot-js.json");
+    let path_str = path.to_string_lossy().to_string();
+
+    // the multisig metadata comes back with the key
+    fs::write(&path, exported.to_json()).unwrap();
+    let imported = import_keystore(
+        path_str.clone(),
+        Some("password"),
+        None,
+        vec![],
+        Network::Dev,
+    )
+    .unwrap();
+    assert_eq!(
+        (
+            imported.threshold,
+            &imported.others,
+            &imported.multi_address
+        ),
+        (
+            keystore.threshold,
+            &keystore.others,
+            &keystore.multi_address
+        )
+    );
+
+    // an address which is not the one of the key
+    exported.address = AccountKeyring::Charlie.to_account_id().to_ss58check();
+    fs::write(&path, exported.to_json()).unwrap();
+    assert!(matches!(
+        import_keystore(path_str, Some("password"), None, vec![], Network::Dev),
+        Err(KeystoreError::AddressMismatch(_))
+    ));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_polkadot_js_secret_key() {
+    use sp_keyring::AccountKeyring;
+
+    // the polkadot-js JSON of the sr25519 key of secret seed
+    // 0xc8fa03532fb22ee1f7f6908b9c02b4e72483f0dbd66e4cd456b8f34c6230b849, the password is
+    // "stake-client"
+    const POLKADOT_JS: &str = r#"{
+        "encoded": "Y0ea1poJCyWCd+yPum+ZQZov+ySJgVEGV8lEzNEUjpcAgAAAAQAAAAgAAAB4N3tSV1e0lEJ/iQFPl9eZKPOTjRTrUeK33prbO5R/BCa29pVnncPKFL0blXQP9FX/p1McvgI7hmCvaZ2pIcBWw4N3piytybGhea6HRp/9SKaE1VbbPlYo3HmAX5o2E5QhihsQGfV46vcAm9YaXtaN0XOCYPY9ppJ9WbO6SbeoBnytxHfikGr056ow2MPlv5y6IHDKOjVkmYO92g1g",
+        "encoding": {
+            "content": ["pkcs8", "sr25519"],
+            "type": ["scrypt", "xsalsa20-poly1305"],
+            "version": "3"
+        },
+        "address": "5Gv8YYFu8H1btvmrJy9FjjAWfb99wrhV3uhPFoNEr918utyR",
+        "meta": { "genesisHash": "", "name": "subkey", "whenCreated": 1633046400000 }
+    }"#;
+    // the secret key of seed, canonical in keystore and ed25519 expanded in polkadot-js
+    const CANONICAL: &str = "382e06a1571eed24f6d35ff671f867c071ca2780d61e8f176bd0702a05ef9a0c\
+                             08ab741c277f1e77c2660ff5adf7bb88eff3c8ed532370071871baf35150aa25";
+    const ED25519: &str = "c0713108bdf26827b19ffeb28fc33f038e533e01b4f678bc588386532978d764\
+                           08ab741c277f1e77c2660ff5adf7bb88eff3c8ed532370071871baf35150aa25";
+    const PUBLIC: &str = "d6a3105d6768e956e9e5d41050ac29843f98561410d3a47f9dd5b3b227ab8746";
+
+    let path = std::env::temp_dir().join("stake-client-test-polkadot-js-secret.json");
+    fs::write(&path, POLKADOT_JS).unwrap();
+    let bob = AccountKeyring::Bob.to_account_id().to_ss58check();
+    let imported = import_keystore(
+        path.to_string_lossy().to_string(),
+        Some("stake-client"),
+        Some(2),
+        vec![bob],
+        Network::Dev,
+    )
+    .unwrap();
+    fs::remove_file(path).unwrap();
+    let (public_key, secret_key) = imported.decrypt(Some("stake-client")).unwrap();
+    assert_eq!(hex::encode(public_key), PUBLIC);
+    assert_eq!(hex::encode(&secret_key[..]), CANONICAL);
+
+    // the exported key is the one of polkadot-js again
+    let exported =
+        PolkadotJsKeystore::from_keystore(&imported, "subkey", Some("stake-client")).unwrap();
+    let encoded = exported.encoded_bytes().unwrap();
+    let (public_key, secret_key) = pkcs8::decode(&encoded, Some("stake-client")).unwrap();
+    assert_eq!(hex::encode(public_key), PUBLIC);
+    assert_eq!(hex::encode(&secret_key[..]), ED25519);
 }
@@ -1,24 +1,42 @@
+use crate::common::balance::BalanceReserve;
+use crate::common::constants::{ParaConstants, RelayConstants};
 use crate::common::error::Error;
+use crate::common::logger::{task_span, LogFormat, TaskMessage};
+use crate::common::metadata::{
+    ensure_compatible, para_requirements, xcm_requirements, ANCHORED_BOND_REQUIREMENTS,
+    BATCH_REQUIREMENTS, PROXY_REQUIREMENTS, RELAY_REQUIREMENTS,
+};
+use crate::common::multisig::check_pool_address;
+use crate::common::origin::DispatchOrigin;
 use crate::common::primitives::AccountId;
-use crate::keystore::{crypto::Sr25519, wallet::get_keystore};
+use crate::common::profile::{
+    build_client, ensure_para_id, ChainKind, ChainProfile, Network, XcmConfig,
+};
+use crate::common::upgrade::wait_runtime_upgrade;
+use crate::common::xcm_tracker::XcmTracker;
+use crate::keystore::{
+    password::PasswordOpt,
+    remote_signer::{Endpoint, RemoteSigner},
+    wallet::{get_keystore, warn_network_mismatch},
+};
 use crate::kusama::{listener, tasks};
 
 use async_std::sync::{Arc, Mutex};
-use frame_support::PalletId;
-use futures::join;
-use log::{error, info};
-use parallel_primitives::{Balance, CurrencyId, PriceWithDecimal};
+use futures::{
+    future::{join, select, Either},
+    pin_mut,
+};
+use parallel_primitives::CurrencyId;
 use runtime::heiko::runtime::HeikoRuntime;
-use runtime::kusama::runtime::KusamaRuntime;
-use sp_core::sr25519::Pair;
+use runtime::kusama::{api::XcmVersion, runtime::KusamaRuntime};
+use sp_core::{crypto::Ss58Codec, H256};
 use structopt::StructOpt;
-use substrate_subxt::{staking::Staking, system::System, ClientBuilder, PairSigner};
+use substrate_subxt::Signer;
 use tokio::sync::{mpsc, oneshot};
-use xcm::v0::{MultiLocation, Outcome};
+use tracing::{info, warn, Span};
 
 pub const LISTEN_INTERVAL: u64 = 24000; // 6 * block_time
 pub const TASK_INTERVAL: u64 = 6000;
-pub const MIN_BOND_BALANCE: u128 = 100_000_000_000_000;
 
 #[derive(Debug, StructOpt)]
 pub struct StartRelayCmd {
@@ -26,13 +44,17 @@ pub struct StartRelayCmd {
     #[structopt(short, long, default_value = "keystore.json")]
     pub key_store: String,
 
-    /// websocket server endpoint of para chain
-    #[structopt(long, default_value = "ws://127.0.0.1:9944")]
-    pub para_ws_server: String,
+    /// the network to stake on: kusama, polkadot or dev
+    #[structopt(long, default_value = "dev")]
+    pub network: Network,
 
-    /// websocket server endpoint of relay chain
-    #[structopt(long, default_value = "ws://127.0.0.1:9955")]
-    pub relay_ws_server: String,
+    /// websocket server endpoint of para chain, defaults to the endpoint of para chain profile
+    #[structopt(long)]
+    pub para_ws_server: Option<String>,
+
+    /// websocket server endpoint of relay chain, defaults to the endpoint of relay chain profile
+    #[structopt(long)]
+    pub relay_ws_server: Option<String>,
 
     /// data base server endpoint
     #[structopt(short, long, default_value = "http://127.0.0.1:1521")]
@@ -52,155 +74,333 @@ pub struct StartRelayCmd {
     )]
     pub para_pool_addr: String,
 
-    /// the password of keystore
-    #[structopt(short, long)]
-    pub password: Option<String>,
+    /// the parachain id of para chain, defaults to the parachain id of network
+    #[structopt(long)]
+    pub para_id: Option<u32>,
+
+    /// the XCM version of relay chain: v0, v1 or v2, defaults to the version of relay chain profile
+    #[structopt(long)]
+    pub xcm_version: Option<XcmVersion>,
+
+    /// the index of the transferred asset which pays the XCM fees on para chain
+    #[structopt(long, default_value = "0")]
+    pub xcm_fee_asset_item: u32,
+
+    #[structopt(flatten)]
+    pub password: PasswordOpt,
+
+    /// sign through the remote signer instead of the key of keystore:
+    /// unix:<path>, tcp:<host:port> or http://<host:port>/<path>, the tcp and http ones are
+    /// authenticated by the token of REMOTE_SIGNER_TOKEN environment variable
+    #[structopt(long)]
+    pub remote_signer: Option<Endpoint>,
+
+    /// how the multisig account dispatches the liquid staking calls: none, sudo or
+    /// governance[:<council threshold>], the council threshold defaults to 1
+    #[structopt(long, default_value = "sudo")]
+    pub origin: DispatchOrigin,
 
     /// temp use to decide which account create first multi-signature transaction
     #[structopt(short, long)]
     pub first: bool,
+
+    /// dispatch the staking calls of relay pool as its staking proxy instead of multisig,
+    /// only this signer needs to be online
+    #[structopt(long)]
+    pub proxy: bool,
+
+    /// combine bond and nominate, withdraw unbonded and the transfer to para chain into one
+    /// `utility.batch_all` multisig call each
+    #[structopt(long)]
+    pub batch: bool,
+
+    /// the validators to nominate with bond, required in batch mode and nominated by the proxy
+    /// right after bonding in proxy mode
+    #[structopt(long = "validator")]
+    pub validators: Vec<String>,
+
+    /// the balance the relay pool keeps on top of the fees, the multisig deposit and the
+    /// existential deposit when bonding
+    #[structopt(long, default_value = "10000000000")]
+    pub balance_buffer: u128,
+
+    /// the format of logs: text or json, the level is set by RUST_LOG
+    #[structopt(long, default_value = "text")]
+    pub log_format: LogFormat,
 }
 
 impl StartRelayCmd {
-    pub async fn run(&self) {
-        // get pair
-        let password: Option<String>;
-        match &self.password {
-            Some(p) => password = Some(p.to_string()),
-            None => password = rpassword::read_password_from_tty(Some("Type password:")).ok(),
-        }
+    pub async fn run(&self) -> Result<(), Error> {
+        let (para_chain, relay_chain) = (self.network.para_chain(), self.network.relay_chain());
+        relay_chain.use_ss58_format();
 
         // get keystore
-        let keystore = get_keystore(self.key_store.to_string()).unwrap();
+        let keystore = get_keystore(self.key_store.to_string())?;
         info!("{:?}", keystore);
+        warn_network_mismatch(&keystore.network, self.network);
 
-        let pair = keystore.into_pair::<Sr25519>(password).unwrap();
+        // the relay pool is the multisig account the signatories approve for
+        check_pool_address(&self.relay_pool_addr, &keystore.multi_account_id()?)
+            .map_err(|e| Error::Other(format!("{}, the multisig calls can not be executed", e)))?;
+
+        // get signers
+        let (relay_signer, para_signer): (
+            Box<dyn Signer<KusamaRuntime> + Send + Sync>,
+            Box<dyn Signer<HeikoRuntime> + Send + Sync>,
+        ) = match &self.remote_signer {
+            Some(endpoint) => {
+                let account_id = keystore.account_id()?;
+                let transport = endpoint.transport().map_err(Error::Other)?;
+                (
+                    Box::new(RemoteSigner::<KusamaRuntime>::new(
+                        account_id.clone(),
+                        transport.clone(),
+                    )),
+                    Box::new(RemoteSigner::<HeikoRuntime>::new(account_id, transport)),
+                )
+            }
+            None => {
+                // the password is wiped once the signers are built
+                let password = self.password.read("Type password:")?;
+                let password = password.as_ref().map(|p| p.as_str());
+                (
+                    keystore.into_signer::<KusamaRuntime>(password)?,
+                    keystore.into_signer::<HeikoRuntime>(password)?,
+                )
+            }
+        };
 
         // get other signatories
-        let other_signatories = keystore.get_other_signatories().unwrap();
+        let other_signatories = keystore.get_other_signatories()?;
+
+        let mut validators = vec![];
+        for v in self.validators.iter() {
+            let validator = AccountId::from_string(v)
+                .map_err(|_| Error::Other(format!("invalid validator address: {}", v)))?;
+            validators.push(validator);
+        }
+        if self.batch && !self.proxy && validators.is_empty() {
+            return Err(Error::Other(
+                "batch mode bonds and nominates at once, --validator is required".to_string(),
+            ));
+        }
 
         let temporary_cmd = TemporaryCmd {
-            relay_ws_server: self.relay_ws_server.clone(),
-            para_ws_server: self.para_ws_server.clone(),
-            relay_key_pair: pair.clone(),
-            para_key_pair: pair.clone(),
+            relay_profile: relay_chain,
+            para_profile: para_chain,
+            relay_ws_server: relay_chain.endpoint(&self.relay_ws_server),
+            para_ws_server: para_chain.endpoint(&self.para_ws_server),
+            currency_id: self.network.staking_currency(),
+            xcm: XcmConfig {
+                version: self
+                    .xcm_version
+                    .unwrap_or_else(|| relay_chain.xcm_version()),
+                para_id: self.para_id.unwrap_or_else(|| self.network.para_id()),
+                fee_asset_item: self.xcm_fee_asset_item,
+                dest_weight: 0,
+            },
+            relay_signer,
+            para_signer,
             relay_pool_addr: self.relay_pool_addr.clone(),
             para_pool_addr: self.para_pool_addr.to_string(),
             relay_multi_other_signatories: other_signatories.clone(),
+            threshold: keystore.threshold,
             para_multi_other_signatories: other_signatories.clone(),
-            first: self.first,
+            origin: self.origin,
+            // the proxy is the only signer, it does what the first signer does
+            first: self.first || self.proxy,
+            proxy: self.proxy,
+            batch: self.batch,
+            validators,
+            balance_buffer: self.balance_buffer,
         };
         let r = run(&temporary_cmd).await;
         info!("relaychain client finished {:?}", r);
+        r
     }
 }
 
+/// The tasks sent by the listeners. The tasks reading storage carry the finalized block hash of
+/// relay chain the listener decided at, every storage read of the task is made at that block.
 pub enum TasksType {
-    RelayBond,
-    RelayBondExtra,
+    RelayBond(H256),
+    RelayBondExtra(H256),
     ParaRecordRewards(Amount),
     ParaRecordSlash(Amount),
     ParaUnstake(AccountId, Amount),
-    RelayUnbonded(AccountId, Amount),
-    RelayEraIndexChanged(u32),
+    RelayUnbonded(AccountId, Amount, H256),
+    RelayEraIndexChanged(u32, H256),
     RelayWithdrawUnbonded(AccountId, Amount),
 }
+
+impl TasksType {
+    /// The span the task is executed in, on the chain it submits to.
+    pub fn span(&self) -> Span {
+        match self {
+            TasksType::RelayBond(at) => task_span(ChainKind::Relay, "RelayBond", None, Some(*at)),
+            TasksType::RelayBondExtra(at) => {
+                task_span(ChainKind::Relay, "RelayBondExtra", None, Some(*at))
+            }
+            TasksType::ParaRecordRewards(amount) => {
+                task_span(ChainKind::Para, "ParaRecordRewards", Some(*amount), None)
+            }
+            TasksType::ParaRecordSlash(amount) => {
+                task_span(ChainKind::Para, "ParaRecordSlash", Some(*amount), None)
+            }
+            TasksType::ParaUnstake(_, amount) => {
+                task_span(ChainKind::Relay, "ParaUnstake", Some(*amount), None)
+            }
+            TasksType::RelayUnbonded(_, amount, at) => {
+                task_span(ChainKind::Relay, "RelayUnbonded", Some(*amount), Some(*at))
+            }
+            TasksType::RelayEraIndexChanged(_, at) => {
+                task_span(ChainKind::Relay, "RelayEraIndexChanged", None, Some(*at))
+            }
+            TasksType::RelayWithdrawUnbonded(_, amount) => task_span(
+                ChainKind::Relay,
+                "RelayWithdrawUnbonded",
+                Some(*amount),
+                None,
+            ),
+        }
+    }
+
+    /// The task with its span and the sender of its result, to be sent to the dispatcher.
+    pub fn message(self, response: oneshot::Sender<u64>) -> TaskMessage<Self> {
+        let span = self.span();
+        (self, span, response)
+    }
+}
+
 pub type Amount = u128;
 
 //todo this is a TemporaryCmd receive arguments
 pub struct TemporaryCmd {
+    pub relay_profile: ChainProfile,
+    pub para_profile: ChainProfile,
     pub relay_ws_server: String,
     pub para_ws_server: String,
-    pub relay_key_pair: Pair,
-    pub para_key_pair: Pair,
+    pub xcm: XcmConfig,
+    pub currency_id: CurrencyId,
+    pub relay_signer: Box<dyn Signer<KusamaRuntime> + Send + Sync>,
+    pub para_signer: Box<dyn Signer<HeikoRuntime> + Send + Sync>,
     pub relay_pool_addr: String,
     pub para_pool_addr: String,
     pub relay_multi_other_signatories: Vec<AccountId>,
+    /// The threshold of the multisig accounts.
+    pub threshold: u16,
     pub para_multi_other_signatories: Vec<AccountId>,
+    pub origin: DispatchOrigin,
     pub first: bool,
+    pub proxy: bool,
+    pub batch: bool,
+    pub validators: Vec<AccountId>,
+    pub balance_buffer: u128,
 }
 
 pub async fn run(cmd: &TemporaryCmd) -> Result<(), Error> {
-    // initial relaychain client
-    let relay_subxt_client = ClientBuilder::<KusamaRuntime>::new()
-        .set_url(cmd.relay_ws_server.clone())
-        .register_type_size::<<KusamaRuntime as System>::AccountId>("T::AccountId")
-        .register_type_size::<<KusamaRuntime as Staking>::CandidateReceipt>(
-            "CandidateReceipt<Hash>",
-        )
-        .register_type_size::<u32>("CoreIndex")
-        .register_type_size::<u32>("GroupIndex")
-        .register_type_size::<PalletId>("ParaId")
-        .register_type_size::<MultiLocation>("MultiLocation")
-        .register_type_size::<Outcome>("xcm::v0::Outcome")
-        .register_type_size::<Outcome>("Outcome")
-        .register_type_size::<([u8; 4], u64)>("MessageId")
-        .skip_type_sizes_check()
-        .build()
-        .await
-        .map_err(|e| {
-            error!("subxt_client error: {:?}", e);
-            Error::SubxtError(e)
-        })?;
-    // let pair = Pair::from_string(cmd.relay_key_store, None).unwrap();
-    let pair = cmd.relay_key_pair.clone();
-    let relay_signer = PairSigner::<KusamaRuntime, Pair>::new(pair);
-    // initial parachain client
-    let para_subxt_client = ClientBuilder::<HeikoRuntime>::new()
-        .set_url(cmd.para_ws_server.clone())
-        .register_type_size::<CurrencyId>("CurrencyIdOf<T>")
-        .register_type_size::<CurrencyId>("Currency<T>")
-        .register_type_size::<CurrencyId>("Currency")
-        .register_type_size::<Balance>("BalanceOf<T>")
-        .register_type_size::<<HeikoRuntime as System>::AccountId>("T::AccountId")
-        .register_type_size::<CurrencyId>("T::CurrencyId")
-        .register_type_size::<Balance>("T::Balance")
-        .register_type_size::<CurrencyId>("T::OracleKey")
-        .register_type_size::<PriceWithDecimal>("T::OracleValue")
-        .register_type_size::<CurrencyId>("CurrencyId")
-        .register_type_size::<PalletId>("ParaId")
-        .register_type_size::<MultiLocation>("MultiLocation")
-        .register_type_size::<Outcome>("xcm::v0::Outcome")
-        .register_type_size::<Outcome>("Outcome")
-        .register_type_size::<([u8; 4], u64)>("MessageId")
-        .skip_type_sizes_check()
-        .build()
-        .await
-        .map_err(|e| {
-            error!("subxt_client error: {:?}", e);
-            Error::SubxtError(e)
-        })?;
-    // let pair = Pair::from_string(cmd.para_key_store, None).unwrap();
-    let pair = cmd.para_key_pair.clone();
-    let para_signer = PairSigner::<HeikoRuntime, Pair>::new(pair);
-    // initial channel
-    let (system_rpc_tx, system_rpc_rx) = mpsc::channel::<(TasksType, oneshot::Sender<u64>)>(10);
-
     // todo put this to database, because this will be lost when the client restart
     let withdraw_unbonded_amount = Arc::new(Mutex::new(0));
+    let unbonded_era_index_list = Arc::new(Mutex::new(vec![]));
+    let xcm_tracker = Arc::new(Mutex::new(XcmTracker::default()));
+    // held by the dispatcher while a task is running
+    let dispatch_lock = Arc::new(Mutex::new(()));
+    // the tasks queued and the one received are kept when the clients are rebuilt
+    let (system_rpc_tx, mut system_rpc_rx) = mpsc::channel::<TaskMessage<TasksType>>(10);
+    let mut pending = None;
+
+    loop {
+        // initial relaychain client
+        let relay_subxt_client =
+            build_client::<KusamaRuntime>(cmd.relay_profile, &cmd.relay_ws_server).await?;
+        ensure_compatible(&relay_subxt_client, "relay chain", RELAY_REQUIREMENTS)?;
+        ensure_compatible(
+            &relay_subxt_client,
+            "relay chain",
+            xcm_requirements(cmd.xcm.version),
+        )?;
+        if cmd.proxy {
+            ensure_compatible(&relay_subxt_client, "relay chain", PROXY_REQUIREMENTS)?;
+        } else {
+            ensure_compatible(
+                &relay_subxt_client,
+                "relay chain",
+                ANCHORED_BOND_REQUIREMENTS,
+            )?;
+        }
+        if cmd.batch {
+            ensure_compatible(&relay_subxt_client, "relay chain", BATCH_REQUIREMENTS)?;
+        }
+
+        // read the pallet constants which the client depends on
+        let constants = RelayConstants::fetch(&relay_subxt_client).await?;
+        let reserve = BalanceReserve::relay(&constants, cmd.threshold, cmd.balance_buffer);
+
+        // initial parachain client
+        let para_subxt_client =
+            build_client::<HeikoRuntime>(cmd.para_profile, &cmd.para_ws_server).await?;
+        ensure_compatible(
+            &para_subxt_client,
+            "para chain",
+            &para_requirements(cmd.origin),
+        )?;
+        ensure_para_id(&para_subxt_client, cmd.xcm.para_id).await?;
+        let xcm = XcmConfig {
+            dest_weight: ParaConstants::fetch(&para_subxt_client)?.xcm_dest_weight(),
+            ..cmd.xcm
+        };
+
+        // initial multi threads to listen on-chain status
+        let l = listener::listener(
+            &relay_subxt_client,
+            &para_subxt_client,
+            system_rpc_tx.clone(),
+            cmd.relay_pool_addr.clone(),
+            withdraw_unbonded_amount.clone(),
+            reserve,
+            cmd.currency_id,
+            xcm_tracker.clone(),
+        );
 
-    // initial multi threads to listen on-chain status
-    let l = listener::listener(
-        &relay_subxt_client,
-        &para_subxt_client,
-        system_rpc_tx,
-        cmd.relay_pool_addr.clone(),
-        withdraw_unbonded_amount.clone(),
-    );
-
-    // initial task to receive order and dive
-    let t = tasks::dispatch(
-        &relay_subxt_client,
-        &para_subxt_client,
-        &relay_signer,
-        &para_signer,
-        system_rpc_rx,
-        cmd.relay_multi_other_signatories.clone(),
-        cmd.relay_pool_addr.clone(),
-        cmd.para_pool_addr.clone(),
-        cmd.first,
-        withdraw_unbonded_amount.clone(),
-    );
-    join!(l, t);
-    Ok(())
+        // initial task to receive order and dive
+        let t = tasks::dispatch(
+            &relay_subxt_client,
+            &para_subxt_client,
+            &*cmd.relay_signer,
+            &*cmd.para_signer,
+            cmd.origin,
+            &mut system_rpc_rx,
+            &mut pending,
+            cmd.relay_multi_other_signatories.clone(),
+            cmd.threshold,
+            cmd.relay_pool_addr.clone(),
+            cmd.para_pool_addr.clone(),
+            cmd.first,
+            cmd.proxy,
+            cmd.batch,
+            cmd.validators.clone(),
+            xcm,
+            xcm_tracker.clone(),
+            withdraw_unbonded_amount.clone(),
+            constants,
+            reserve,
+            unbonded_era_index_list.clone(),
+            dispatch_lock.clone(),
+        );
+
+        // pause the dispatcher once the runtime is upgraded, the clients will be rebuilt
+        let u = async {
+            wait_runtime_upgrade(&relay_subxt_client, &para_subxt_client).await?;
+            let _paused = dispatch_lock.lock().await;
+            warn!("dispatcher paused, rebuild clients with the new metadata");
+            Ok::<(), Error>(())
+        };
+
+        let j = join(l, t);
+        pin_mut!(j, u);
+        match select(j, u).await {
+            Either::Left(_) => return Ok(()),
+            Either::Right((r, _)) => r?,
+        }
+    }
 }
@@ -1,8 +1,12 @@
 use super::kusama;
+use super::AccountId;
 use super::KusamaRuntime;
 use super::TasksType;
-use super::MIN_BOND_BALANCE;
 use super::{LISTEN_INTERVAL, TASK_INTERVAL};
+use crate::common::anchor::{finalized_anchor, BlockEvents, LISTEN_ANCHOR_INTERVAL};
+use crate::common::balance::{estimate_fee, BalanceReserve};
+use crate::common::logger::TaskMessage;
+use crate::common::xcm_tracker::{check_transfers, listen_para_deposit, XcmTracker};
 
 use async_std::{
     sync::{Arc, Mutex},
@@ -10,22 +14,25 @@ use async_std::{
 };
 use core::marker::PhantomData;
 use futures::join;
-use log::{debug, error, info};
+use parallel_primitives::CurrencyId;
 use runtime::heiko::runtime::HeikoRuntime;
 use runtime::pallets::liquid_staking::UnstakedEvent;
 use runtime::pallets::staking::{RewardEvent, SlashEvent, UnbondedEvent, WithdrawnEvent};
-use sp_core::Decode;
-use std::str::FromStr;
+use sp_core::{crypto::Ss58Codec, Decode};
 use std::time::Duration;
 use substrate_subxt::{system::System, Client, EventSubscription, RawEvent};
 use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info};
 
 pub async fn listener(
     relay_subxt_client: &Client<KusamaRuntime>,
     para_subxt_client: &Client<HeikoRuntime>,
-    system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     pool_addr: String,
     withdraw_unbonded_amount: Arc<Mutex<u128>>,
+    reserve: BalanceReserve,
+    currency_id: CurrencyId,
+    xcm_tracker: Arc<Mutex<XcmTracker>>,
 ) {
     // start future-1 listening relaychain multisig-account balance
     let l1 = listen_agent_balance(
@@ -33,6 +40,7 @@ pub async fn listener(
         system_rpc_tx.clone(),
         pool_addr.clone(),
         withdraw_unbonded_amount.clone(),
+        reserve,
     );
     // start future-2 listening relaychain slash&reward
     let l2 = listen_reward(relay_subxt_client.clone(), system_rpc_tx.clone());
@@ -41,21 +49,25 @@ pub async fn listener(
     let l5 = listen_unbonded_event(system_rpc_tx.clone(), relay_subxt_client);
     let l6 = listen_relay_chain_era(system_rpc_tx.clone(), relay_subxt_client);
     let l7 = listen_withdraw_unbonded_event(system_rpc_tx.clone(), relay_subxt_client);
+    // reconcile the transfers to para chain with its deposits
+    let l8 = listen_para_deposit(para_subxt_client, currency_id, xcm_tracker.clone());
+    let l9 = check_transfers(xcm_tracker.clone());
 
     info!("listener join");
-    join!(l1, l2, l3, l4, l5, l6, l7);
+    join!(l1, l2, l3, l4, l5, l6, l7, l8, l9);
 }
 
 async fn listen_agent_balance(
     subxt_relay_client: Client<KusamaRuntime>,
-    system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     pool_addr: String,
     withdraw_unbonded_amount: Arc<Mutex<u128>>,
+    reserve: BalanceReserve,
 ) {
-    let account_id: <KusamaRuntime as System>::AccountId =
-        sp_core::ed25519::Public::from_str(&pool_addr)
-            .unwrap()
-            .into();
+    let account_id = match AccountId::from_string(&pool_addr) {
+        Ok(account_id) => account_id,
+        Err(e) => return error!("invalid pool address {}: {:?}", pool_addr, e),
+    };
     let account = kusama::api::AccountStore::<KusamaRuntime> {
         account: account_id.clone(),
     };
@@ -65,14 +77,33 @@ async fn listen_agent_balance(
 
     info!("loop listen balance");
     loop {
-        match subxt_relay_client.fetch(&account, None).await {
+        // decide at the finalized anchor block, the bond task reads storage at the same block
+        let at = match finalized_anchor(&subxt_relay_client, LISTEN_ANCHOR_INTERVAL).await {
+            Ok(at) => at,
+            Err(e) => {
+                error!("failed to get finalized head: {:?}", e);
+                task::sleep(Duration::from_millis(LISTEN_INTERVAL)).await;
+                continue;
+            }
+        };
+        // the fee of bond_extra hardly changes with the amount, estimate it once per round
+        let call = kusama::api::staking_bond_extra_call::<KusamaRuntime>(reserve.min_bond);
+        let fee = match estimate_fee(&subxt_relay_client, call, Some(at)).await {
+            Ok(fee) => fee,
+            Err(e) => {
+                error!("failed to estimate the fee of bond: {:?}", e);
+                task::sleep(Duration::from_millis(LISTEN_INTERVAL)).await;
+                continue;
+            }
+        };
+        match subxt_relay_client.fetch(&account, Some(at)).await {
             Ok(account_store) => {
                 info!(
                     "account id: {:?}, account_store: {:?}",
                     &account_id, &account_store
                 );
                 let bond_controller: Option<<KusamaRuntime as System>::AccountId> =
-                    subxt_relay_client.fetch(&bond, None).await.unwrap();
+                    subxt_relay_client.fetch(&bond, Some(at)).await.unwrap();
                 info!("bond_controller: {:?}", &bond_controller);
                 let (resp_tx, resp_rx) = oneshot::channel();
                 let wa = *withdraw_unbonded_amount.lock().await;
@@ -80,23 +111,27 @@ async fn listen_agent_balance(
                     let free = account_store.data.free;
                     let misc_frozen = account_store.data.misc_frozen;
                     //for now, make the loop interval longer.
-                    if free - misc_frozen >= MIN_BOND_BALANCE + wa {
+                    let spendable = reserve.spendable(free, misc_frozen, fee);
+                    // the withdrawn amount is not subtracted from the bond, which every signatory
+                    // derives from storage alone; no bond is decided until it is transferred
+                    if wa > 0 {
+                        info!("withdrawn {} waiting to be transferred, bond deferred", wa);
+                    } else if spendable >= reserve.min_bond {
                         info!(
-                            "free - misc_frozen:{:?}, withdraw_unbonded_amount:{:?}",
-                            free - misc_frozen,
-                            withdraw_unbonded_amount
+                            "spendable:{:?}, fee:{:?}, withdraw_unbonded_amount:{:?}",
+                            spendable, fee, wa
                         );
                         match bond_controller {
                             Some(_bond) => {
                                 system_rpc_tx
                                     .clone()
-                                    .try_send((TasksType::RelayBondExtra, resp_tx))
+                                    .try_send(TasksType::RelayBondExtra(at).message(resp_tx))
                                     .ok();
                             }
                             None => {
                                 system_rpc_tx
                                     .clone()
-                                    .try_send((TasksType::RelayBond, resp_tx))
+                                    .try_send(TasksType::RelayBond(at).message(resp_tx))
                                     .ok();
                             }
                         }
@@ -117,7 +152,7 @@ async fn listen_agent_balance(
 
 async fn listen_reward(
     subxt_relay_client: Client<KusamaRuntime>,
-    mut system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    mut system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
 ) {
     let sub = subxt_relay_client
         .subscribe_finalized_events()
@@ -139,7 +174,7 @@ async fn listen_reward(
                 info!("Receive Event: {:?}", &event);
                 let (resp_tx, resp_rx) = oneshot::channel();
                 system_rpc_tx
-                    .try_send((TasksType::ParaRecordRewards(event.amount), resp_tx))
+                    .try_send(TasksType::ParaRecordRewards(event.amount).message(resp_tx))
                     .ok();
                 let _res = resp_rx.await.ok();
                 info!("Record reword event finished");
@@ -151,7 +186,7 @@ async fn listen_reward(
 
 async fn listen_slash(
     subxt_relay_client: Client<KusamaRuntime>,
-    mut system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    mut system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
 ) {
     let sub = subxt_relay_client
         .subscribe_finalized_events()
@@ -173,7 +208,7 @@ async fn listen_slash(
                 info!("Receive Event: {:?}", &event);
                 let (resp_tx, resp_rx) = oneshot::channel();
                 system_rpc_tx
-                    .try_send((TasksType::ParaRecordSlash(event.amount), resp_tx))
+                    .try_send(TasksType::ParaRecordSlash(event.amount).message(resp_tx))
                     .ok();
                 let _res = resp_rx.await.ok();
                 info!("Record slash event finished");
@@ -185,7 +220,7 @@ async fn listen_slash(
 
 /// listen to the unstaked event
 async fn listen_unstaked_event(
-    mut system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    mut system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     para_subxt_client: &Client<HeikoRuntime>,
 ) {
     let sub = para_subxt_client
@@ -208,7 +243,7 @@ async fn listen_unstaked_event(
                 info!("Received Unstaked event: {:?}", &event);
                 let (resp_tx, resp_rx) = oneshot::channel();
                 system_rpc_tx
-                    .try_send((TasksType::ParaUnstake(event.account, event.amount), resp_tx))
+                    .try_send(TasksType::ParaUnstake(event.account, event.amount).message(resp_tx))
                     .ok();
                 let _res = resp_rx.await.ok();
 
@@ -221,7 +256,7 @@ async fn listen_unstaked_event(
 
 /// listen to the unbonded event
 async fn listen_unbonded_event(
-    mut system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    mut system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     relay_subxt_client: &Client<KusamaRuntime>,
 ) {
     let sub = relay_subxt_client
@@ -229,25 +264,26 @@ async fn listen_unbonded_event(
         .await
         .unwrap();
     let decoder = relay_subxt_client.events_decoder();
-    let mut sub = EventSubscription::<KusamaRuntime>::new(sub, &decoder);
-    sub.filter_event::<UnbondedEvent<KusamaRuntime>>();
+    let mut sub = BlockEvents::new::<UnbondedEvent<KusamaRuntime>>(sub, &decoder);
     loop {
         info!("loop listen unbonded event");
+        // handled at the block containing the event
         match sub
             .next()
             .await
-            .and_then(|result_raw| -> Option<RawEvent> { result_raw.ok() })
-            .and_then(|raw| -> Option<UnbondedEvent<KusamaRuntime>> {
-                UnbondedEvent::<KusamaRuntime>::decode(&mut &raw.data[..]).ok()
+            .and_then(|result_raw| result_raw.ok())
+            .and_then(|(at, raw)| {
+                UnbondedEvent::<KusamaRuntime>::decode(&mut &raw.data[..])
+                    .ok()
+                    .map(|event| (at, event))
             }) {
-            Some(event) => {
+            Some((at, event)) => {
                 info!("Received Unbonded event: {:?}", &event);
                 let (resp_tx, resp_rx) = oneshot::channel();
                 system_rpc_tx
-                    .try_send((
-                        TasksType::RelayUnbonded(event.account, event.amount),
-                        resp_tx,
-                    ))
+                    .try_send(
+                        TasksType::RelayUnbonded(event.account, event.amount, at).message(resp_tx),
+                    )
                     .ok();
                 let _res = resp_rx.await.ok();
             }
@@ -258,7 +294,7 @@ async fn listen_unbonded_event(
 
 /// listen to the withdraw unbonded event
 async fn listen_relay_chain_era(
-    mut system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    mut system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     relay_subxt_client: &Client<KusamaRuntime>,
 ) {
     let mut current_era_index: u32 = 0;
@@ -267,17 +303,26 @@ async fn listen_relay_chain_era(
         let store = kusama::api::CurrentEraStore::<KusamaRuntime> {
             _runtime: PhantomData,
         };
-        match relay_subxt_client.fetch(&store, None).await {
+        // every signatory sees the new era at the same anchor block
+        let at = match finalized_anchor(relay_subxt_client, LISTEN_ANCHOR_INTERVAL).await {
+            Ok(at) => at,
+            Err(e) => {
+                error!("failed to get finalized anchor: {:?}", e);
+                task::sleep(Duration::from_millis(TASK_INTERVAL)).await;
+                continue;
+            }
+        };
+        match relay_subxt_client.fetch(&store, Some(at)).await {
             Ok(era) => {
                 if let Some(era_index) = era {
                     if era_index != current_era_index {
                         current_era_index = era_index;
                         let (resp_tx, resp_rx) = oneshot::channel();
                         system_rpc_tx
-                            .try_send((
-                                TasksType::RelayEraIndexChanged(current_era_index.clone()),
-                                resp_tx,
-                            ))
+                            .try_send(
+                                TasksType::RelayEraIndexChanged(current_era_index.clone(), at)
+                                    .message(resp_tx),
+                            )
                             .ok();
                         let _res = resp_rx.await.ok();
                         info!("Current EraIndex changed {:?}", current_era_index);
@@ -294,7 +339,7 @@ async fn listen_relay_chain_era(
 
 /// listen to the withdraw unbonded event
 async fn listen_withdraw_unbonded_event(
-    mut system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    mut system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     relay_subxt_client: &Client<KusamaRuntime>,
 ) {
     let sub = relay_subxt_client
@@ -316,10 +361,10 @@ async fn listen_withdraw_unbonded_event(
                 info!("Received Withdrawn event: {:?}", &event);
                 let (resp_tx, resp_rx) = oneshot::channel();
                 system_rpc_tx
-                    .try_send((
-                        TasksType::RelayWithdrawUnbonded(event.account, event.amount),
-                        resp_tx,
-                    ))
+                    .try_send(
+                        TasksType::RelayWithdrawUnbonded(event.account, event.amount)
+                            .message(resp_tx),
+                    )
                     .ok();
                 let _res = resp_rx.await.ok();
             }
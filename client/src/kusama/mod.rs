@@ -5,7 +5,7 @@ mod transaction;
 
 use crate::common::primitives::AccountId;
 use crate::common::primitives::Amount;
-use crate::kusama::client::{TasksType, LISTEN_INTERVAL, MIN_BOND_BALANCE, TASK_INTERVAL};
+use crate::kusama::client::{TasksType, LISTEN_INTERVAL, TASK_INTERVAL};
 
 use runtime::heiko::{self, runtime::HeikoRuntime};
 use runtime::kusama::{self, runtime::KusamaRuntime};
@@ -5,9 +5,16 @@ use super::KusamaRuntime;
 use super::TasksType;
 use super::TASK_INTERVAL;
 
-use crate::common::primitives::{AccountId, RELAY_CHAIN_ERA_LOCKED};
+use crate::common::balance::BalanceReserve;
+use crate::common::constants::RelayConstants;
+use crate::common::logger::TaskMessage;
+use crate::common::origin::DispatchOrigin;
+use crate::common::primitives::AccountId;
+use crate::common::profile::{ChainKind, XcmConfig};
+use crate::common::xcm_tracker::XcmTracker;
 use crate::kusama::transaction::{
-    do_relay_unbond, do_relay_withdraw_unbonded, do_xcm_transfer_to_para_chain,
+    do_proxy_relay_unbond, do_proxy_relay_withdraw_unbonded, do_relay_unbond,
+    do_relay_withdraw_unbonded, do_xcm_transfer_to_para_chain,
 };
 
 use async_std::{
@@ -15,174 +22,446 @@ use async_std::{
     task,
 };
 use core::marker::PhantomData;
-use log::{info, warn};
 use runtime::kusama;
-use sp_keyring::AccountKeyring;
+use sp_core::{crypto::Ss58Codec, H256};
 use std::time;
 use substrate_subxt::{Client, Signer};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn, Instrument};
 
 pub async fn dispatch(
     relay_subxt_client: &Client<KusamaRuntime>,
     para_subxt_client: &Client<HeikoRuntime>,
     relay_signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
     para_signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
-    mut system_rpc_rx: mpsc::Receiver<(TasksType, oneshot::Sender<u64>)>,
+    origin: DispatchOrigin,
+    system_rpc_rx: &mut mpsc::Receiver<TaskMessage<TasksType>>,
+    pending: &mut Option<TaskMessage<TasksType>>,
     others: Vec<AccountId>,
+    threshold: u16,
     relay_pool_addr: String,
     para_pool_addr: String,
     first: bool,
+    proxy: bool,
+    batch: bool,
+    validators: Vec<AccountId>,
+    xcm: XcmConfig,
+    xcm_tracker: Arc<Mutex<XcmTracker>>,
     withdraw_unbonded_amount: Arc<Mutex<u128>>,
+    constants: RelayConstants,
+    reserve: BalanceReserve,
+    unbonded_era_index_list: Arc<Mutex<Vec<(AccountId, u32, Amount)>>>,
+    dispatch_lock: Arc<Mutex<()>>,
 ) {
-    // todo put this to database, because this will be lost when the client restart
-    let mut unbonded_era_index_list: Vec<(AccountId, u32, Amount)> = vec![];
     loop {
-        // try_next won't go on util finish this task
-        match system_rpc_rx.recv().await {
-            Some((task_type, response)) => match task_type {
-                TasksType::RelayBond => {
-                    info!("Start bond task");
-                    relay_bond(
-                        relay_subxt_client,
-                        relay_signer,
-                        others.clone(),
-                        relay_pool_addr.clone(),
-                        first,
-                    )
-                    .await;
-                    response.send(0).unwrap();
-                }
+        // the task is kept pending until it runs, so it survives the dispatcher being dropped
+        // when the clients are rebuilt, the listener waiting for its response may be gone then
+        if pending.is_none() {
+            *pending = system_rpc_rx.recv().await;
+        }
+        // hold the lock until the task finished, the dispatcher is paused while it is taken
+        let _running = dispatch_lock.lock().await;
+        let task = pending.take();
+        let mut unbonded_era_index_list = unbonded_era_index_list.lock().await;
+        match task {
+            // the task and the calls it submits are logged in its span
+            Some((task_type, span, response)) => {
+                async {
+                    match task_type {
+                        TasksType::RelayBond(at) if batch && !proxy => {
+                            info!("Start bond and nominate task");
+                            if !first {
+                                task::sleep(time::Duration::from_millis(TASK_INTERVAL)).await;
+                            }
+                            let _ = transaction::do_relay_batch_bond_and_nominate(
+                                others.clone(),
+                                threshold,
+                                relay_pool_addr.clone(),
+                                validators.clone(),
+                                relay_subxt_client,
+                                relay_signer,
+                                reserve,
+                                first,
+                                at,
+                            )
+                            .await
+                            .map_err(|e| warn!("error do_relay_batch_bond_and_nominate: {:?}", e));
+                            response.send(0).ok();
+                        }
 
-                TasksType::RelayBondExtra => {
-                    info!("Start bond extra task");
-                    relay_bond_extra(
-                        relay_subxt_client,
-                        relay_signer,
-                        others.clone(),
-                        relay_pool_addr.clone(),
-                        first,
-                    )
-                    .await;
-                    response.send(0).unwrap();
-                }
+                        TasksType::RelayBond(at) => {
+                            info!("Start bond task");
+                            relay_bond(
+                                relay_subxt_client,
+                                relay_signer,
+                                others.clone(),
+                                threshold,
+                                relay_pool_addr.clone(),
+                                validators.clone(),
+                                first,
+                                proxy,
+                                reserve,
+                                at,
+                            )
+                            .await;
+                            response.send(0).ok();
+                        }
 
-                TasksType::ParaRecordRewards(amount) => {
-                    info!("Start record rewards task");
-                    para_record_rewards(
-                        para_subxt_client,
-                        para_signer,
-                        others.clone(),
-                        relay_pool_addr.clone(),
-                        amount,
-                        first,
-                    )
-                    .await;
-                    response.send(0).unwrap();
-                }
+                        TasksType::RelayBondExtra(at) => {
+                            info!("Start bond extra task");
+                            relay_bond_extra(
+                                relay_subxt_client,
+                                relay_signer,
+                                others.clone(),
+                                threshold,
+                                relay_pool_addr.clone(),
+                                first,
+                                proxy,
+                                reserve,
+                                at,
+                            )
+                            .await;
+                            response.send(0).ok();
+                        }
 
-                TasksType::ParaRecordSlash(amount) => {
-                    info!("Start record slash task");
-                    para_record_slash(
-                        para_subxt_client,
-                        para_signer,
-                        others.clone(),
-                        relay_pool_addr.clone(),
-                        amount,
-                        first,
-                    )
-                    .await;
-                }
+                        TasksType::ParaRecordRewards(amount) => {
+                            info!("Start record rewards task");
+                            para_record_rewards(
+                                para_subxt_client,
+                                para_signer,
+                                origin,
+                                others.clone(),
+                                threshold,
+                                relay_pool_addr.clone(),
+                                amount,
+                                first,
+                            )
+                            .await;
+                            response.send(0).ok();
+                        }
 
-                TasksType::ParaUnstake(_account_id, amount) => {
-                    info!("Start unbond task");
-                    start_unstake_task(&relay_subxt_client, amount.clone(), first.clone()).await;
-                    response.send(0).unwrap();
-                }
+                        TasksType::ParaRecordSlash(amount) => {
+                            info!("Start record slash task");
+                            para_record_slash(
+                                para_subxt_client,
+                                para_signer,
+                                origin,
+                                others.clone(),
+                                threshold,
+                                relay_pool_addr.clone(),
+                                amount,
+                                first,
+                            )
+                            .await;
+                        }
 
-                TasksType::RelayUnbonded(_agent, amount) => {
-                    info!("Found Unbonded event");
-                    let store = kusama::api::CurrentEraStore::<KusamaRuntime> {
-                        _runtime: PhantomData,
-                    };
-                    match relay_subxt_client.fetch(&store, None).await {
-                        Ok(era) => {
-                            if let Some(era_index) = era {
-                                let ctrl = AccountKeyring::Eve.to_account_id().into();
-                                info!("Record Unbonded era index:{:?}", era_index);
-                                unbonded_era_index_list.push((ctrl, era_index, amount));
+                        TasksType::ParaUnstake(_account_id, amount) => {
+                            info!("Start unbond task");
+                            if proxy {
+                                let _ = do_proxy_relay_unbond(
+                                    relay_pool_addr.clone(),
+                                    &relay_subxt_client,
+                                    relay_signer,
+                                    amount,
+                                )
+                                .await
+                                .map_err(|e| warn!("error do_proxy_relay_unbond: {:?}", e));
+                            } else {
+                                if !first {
+                                    task::sleep(time::Duration::from_millis(TASK_INTERVAL)).await;
+                                }
+                                let _ = do_relay_unbond(
+                                    others.clone(),
+                                    threshold,
+                                    relay_pool_addr.clone(),
+                                    &relay_subxt_client,
+                                    relay_signer,
+                                    amount,
+                                    first,
+                                )
+                                .await
+                                .map_err(|e| warn!("error do_relay_unbond: {:?}", e));
                             }
+                            response.send(0).ok();
                         }
-                        Err(e) => {
-                            warn!("error fetch CurrentEraStore: {:?}", e);
+
+                        TasksType::RelayUnbonded(_agent, amount, at) => {
+                            info!("Found Unbonded event");
+                            let store = kusama::api::CurrentEraStore::<KusamaRuntime> {
+                                _runtime: PhantomData,
+                            };
+                            match relay_subxt_client.fetch(&store, Some(at)).await {
+                                Ok(era) => {
+                                    // the pool is its own controller
+                                    match (era, AccountId::from_string(&relay_pool_addr)) {
+                                        (Some(era_index), Ok(ctrl)) => {
+                                            info!(
+                                                "Record Unbonded era index:{:?}, withdrawable \
+                                                 after {} eras ({} sessions)",
+                                                era_index,
+                                                constants.bonding_duration,
+                                                constants.unbonding_sessions()
+                                            );
+                                            unbonded_era_index_list.push((ctrl, era_index, amount));
+                                        }
+                                        (_, Err(e)) => warn!("invalid pool address: {:?}", e),
+                                        (None, _) => {}
+                                    }
+                                }
+                                Err(e) => {
+                                    warn!("error fetch CurrentEraStore: {:?}", e);
+                                }
+                            }
+                            response.send(0).ok();
                         }
-                    }
-                    response.send(0).unwrap();
-                }
 
-                TasksType::RelayEraIndexChanged(era_index) => {
-                    info!("Start RelayEraIndexChanged task");
-                    let mut count = 0;
-                    for (_ctr, era, amount) in unbonded_era_index_list.clone().into_iter() {
-                        if era_index.clone() - era < RELAY_CHAIN_ERA_LOCKED {
-                            break;
+                        TasksType::RelayEraIndexChanged(era_index, at) if batch && !proxy => {
+                            info!("Start RelayEraIndexChanged task");
+                            let (count, amount) = due_unbonded(
+                                &unbonded_era_index_list,
+                                era_index,
+                                constants.bonding_duration,
+                            );
+                            if count != 0 {
+                                // withdrawn and transferred at once, the entries are removed on
+                                // the Withdrawn event of the executed batch
+                                if !first {
+                                    task::sleep(time::Duration::from_millis(TASK_INTERVAL)).await;
+                                }
+                                match transaction::do_relay_batch_withdraw_and_transfer(
+                                    others.clone(),
+                                    threshold,
+                                    relay_pool_addr.clone(),
+                                    para_pool_addr.clone(),
+                                    xcm,
+                                    amount,
+                                    &relay_subxt_client,
+                                    relay_signer,
+                                    first,
+                                    at,
+                                )
+                                .await
+                                {
+                                    Ok(()) => {
+                                        track_transfer_to_para_chain(
+                                            &xcm_tracker,
+                                            &para_pool_addr,
+                                            amount,
+                                            None,
+                                        )
+                                        .await
+                                    }
+                                    Err(e) => {
+                                        error!(
+                                            "error do_relay_batch_withdraw_and_transfer: {:?}",
+                                            e
+                                        )
+                                    }
+                                }
+                            }
+                            response.send(0).ok();
                         }
-                        let _ = do_relay_withdraw_unbonded(&relay_subxt_client, first)
-                            .await
-                            .map_err(|e| info!("error do_relay_withdraw_unbonded: {:?}", e));
-                        *withdraw_unbonded_amount.lock().await += amount;
-                        info!(
-                            "after add withdraw unbonded amount {:?}",
-                            withdraw_unbonded_amount
-                        );
-                        count += 1;
-                    }
-                    if count != 0 {
-                        for i in (count - 1)..0 {
-                            unbonded_era_index_list.remove(i);
+
+                        TasksType::RelayEraIndexChanged(era_index, at) => {
+                            info!("Start RelayEraIndexChanged task");
+                            let (due, _) = due_unbonded(
+                                &unbonded_era_index_list,
+                                era_index,
+                                constants.bonding_duration,
+                            );
+                            let mut count = 0;
+                            for (_ctr, _era, amount) in unbonded_era_index_list[..due].to_vec() {
+                                let result = if proxy {
+                                    do_proxy_relay_withdraw_unbonded(
+                                        relay_pool_addr.clone(),
+                                        &relay_subxt_client,
+                                        relay_signer,
+                                        at,
+                                    )
+                                    .await
+                                } else {
+                                    if !first {
+                                        task::sleep(time::Duration::from_millis(TASK_INTERVAL))
+                                            .await;
+                                    }
+                                    do_relay_withdraw_unbonded(
+                                        others.clone(),
+                                        threshold,
+                                        relay_pool_addr.clone(),
+                                        &relay_subxt_client,
+                                        relay_signer,
+                                        first,
+                                        at,
+                                    )
+                                    .await
+                                };
+                                if let Err(e) = result {
+                                    // kept in the list, retried on the next era
+                                    error!("error do_relay_withdraw_unbonded: {:?}", e);
+                                    break;
+                                }
+                                *withdraw_unbonded_amount.lock().await += amount;
+                                info!(
+                                    "after add withdraw unbonded amount {:?}",
+                                    withdraw_unbonded_amount
+                                );
+                                count += 1;
+                            }
+                            unbonded_era_index_list.drain(..count);
+                            response.send(0).ok();
+                        }
+
+                        TasksType::RelayWithdrawUnbonded(agent, amount) if batch && !proxy => {
+                            // the batch executed, the withdrawn amount is transferred in it
+                            if AccountId::from_string(&relay_pool_addr).ok() == Some(agent) {
+                                let count = withdrawn_unbonded(&unbonded_era_index_list, amount);
+                                unbonded_era_index_list.drain(..count);
+                                info!("{} unbonded entries withdrawn and transferred", count);
+                            }
+                            response.send(0).ok();
+                        }
+
+                        TasksType::RelayWithdrawUnbonded(agent, _)
+                            if AccountId::from_string(&relay_pool_addr).ok() != Some(agent) =>
+                        {
+                            // withdrawn by another staker, nothing to transfer
+                            response.send(0).ok();
                         }
-                    }
-                    response.send(0).unwrap();
-                }
 
-                TasksType::RelayWithdrawUnbonded(_agent, amount) => {
-                    info!("Start XCM transfer to para chain task");
+                        TasksType::RelayWithdrawUnbonded(_, amount) => {
+                            info!("Start XCM transfer to para chain task");
 
-                    let _ = do_xcm_transfer_to_para_chain(
-                        &relay_subxt_client,
-                        para_pool_addr.clone(),
-                        amount.clone(),
-                        first,
-                    )
-                    .await
-                    .map_err(|e| info!("error do_xcm_transfer_to_para_chain: {:?}", e));
+                            match do_xcm_transfer_to_para_chain(
+                                &relay_subxt_client,
+                                para_pool_addr.clone(),
+                                xcm,
+                                amount.clone(),
+                                first,
+                            )
+                            .await
+                            {
+                                Ok(Some(block)) => {
+                                    track_transfer_to_para_chain(
+                                        &xcm_tracker,
+                                        &para_pool_addr,
+                                        amount,
+                                        Some(block),
+                                    )
+                                    .await
+                                }
+                                Ok(None) => {}
+                                Err(e) => info!("error do_xcm_transfer_to_para_chain: {:?}", e),
+                            }
 
-                    *withdraw_unbonded_amount.lock().await -= amount;
-                    info!(
-                        "after sub withdraw unbonded amount {:?}",
-                        withdraw_unbonded_amount
-                    );
+                            {
+                                let mut withdrawn = withdraw_unbonded_amount.lock().await;
+                                *withdrawn = withdrawn.saturating_sub(amount);
+                                info!("after sub withdraw unbonded amount {:?}", *withdrawn);
+                            }
+                            response.send(0).ok();
+                        }
+                    }
                 }
-            },
+                .instrument(span)
+                .await
+            }
             None => info!("dispatch pending..."),
         }
         task::sleep(time::Duration::from_millis(TASK_INTERVAL)).await;
     }
 }
 
+/// The number of the oldest unbonded entries whose bonding duration has passed at the era, and
+/// the sum of their amounts.
+fn due_unbonded(
+    unbonded: &[(AccountId, u32, Amount)],
+    era_index: u32,
+    bonding_duration: u32,
+) -> (usize, Amount) {
+    let due = unbonded
+        .iter()
+        .take_while(|(_, era, _)| era_index.saturating_sub(*era) >= bonding_duration);
+    due.fold((0, 0), |(count, sum), (_, _, amount)| {
+        (count + 1, sum + amount)
+    })
+}
+
+/// The number of the oldest unbonded entries covered by the withdrawn amount.
+fn withdrawn_unbonded(unbonded: &[(AccountId, u32, Amount)], withdrawn: Amount) -> usize {
+    let mut left = withdrawn;
+    unbonded
+        .iter()
+        .take_while(|(_, _, amount)| match left.checked_sub(*amount) {
+            Some(l) => {
+                left = l;
+                true
+            }
+            None => false,
+        })
+        .count()
+}
+
+/// Track the transfer to the pool of para chain until it is deposited.
+async fn track_transfer_to_para_chain(
+    xcm_tracker: &Arc<Mutex<XcmTracker>>,
+    para_pool_addr: &str,
+    amount: Amount,
+    source_block: Option<H256>,
+) {
+    match AccountId::from_string(para_pool_addr) {
+        Ok(pool) => {
+            xcm_tracker
+                .lock()
+                .await
+                .record(ChainKind::Para, pool, amount, source_block);
+        }
+        Err(e) => warn!("invalid pool address {}: {:?}", para_pool_addr, e),
+    }
+}
+
 async fn relay_bond(
     subxt_relay_client: &Client<KusamaRuntime>,
     relay_signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
+    validators: Vec<AccountId>,
     first: bool,
+    proxy: bool,
+    reserve: BalanceReserve,
+    at: H256,
 ) {
     info!("relay_bond");
-    if first {
+    if proxy {
+        let bonded = transaction::do_proxy_relay_bond(
+            pool_addr.clone(),
+            &subxt_relay_client,
+            relay_signer,
+            reserve,
+            at,
+        )
+        .await
+        .map_err(|e| warn!("error do_proxy_relay_bond: {:?}", e));
+        // the proxy nominates right after bonding, nothing to approve in between
+        if bonded.is_ok() && !validators.is_empty() {
+            let _ = transaction::do_proxy_relay_nominate(
+                pool_addr,
+                &subxt_relay_client,
+                relay_signer,
+                validators,
+            )
+            .await
+            .map_err(|e| warn!("error do_proxy_relay_nominate: {:?}", e));
+        }
+    } else if first {
         let _ = transaction::do_first_relay_bond(
             others.clone(),
+            threshold,
             pool_addr,
             &subxt_relay_client,
             relay_signer,
+            reserve,
+            at,
         )
         .await
         .map_err(|e| warn!("error do_first_relay_bond: {:?}", e));
@@ -190,9 +469,12 @@ async fn relay_bond(
         task::sleep(time::Duration::from_millis(TASK_INTERVAL)).await;
         let _ = transaction::do_last_relay_bond(
             others.clone(),
+            threshold,
             pool_addr,
             &subxt_relay_client,
             relay_signer,
+            reserve,
+            at,
         )
         .await
         .map_err(|e| warn!("error do_last_relay_bond: {:?}", e));
@@ -203,16 +485,33 @@ async fn relay_bond_extra(
     subxt_relay_client: &Client<KusamaRuntime>,
     relay_signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     first: bool,
+    proxy: bool,
+    reserve: BalanceReserve,
+    at: H256,
 ) {
     info!("relay_bond_extra");
-    if first {
+    if proxy {
+        let _ = transaction::do_proxy_relay_bond_extra(
+            pool_addr,
+            &subxt_relay_client,
+            relay_signer,
+            reserve,
+            at,
+        )
+        .await
+        .map_err(|e| warn!("error do_proxy_relay_bond_extra: {:?}", e));
+    } else if first {
         let _ = transaction::do_first_relay_bond_extra(
             others.clone(),
+            threshold,
             pool_addr,
             &subxt_relay_client,
             relay_signer,
+            reserve,
+            at,
         )
         .await
         .map_err(|e| warn!("error do_first_relay_bond_extra: {:?}", e));
@@ -220,9 +519,12 @@ async fn relay_bond_extra(
         task::sleep(time::Duration::from_millis(TASK_INTERVAL)).await;
         let _ = transaction::do_last_relay_bond_extra(
             others.clone(),
+            threshold,
             pool_addr,
             &subxt_relay_client,
             relay_signer,
+            reserve,
+            at,
         )
         .await
         .map_err(|e| warn!("error do_last_relay_bond_extra: {:?}", e));
@@ -232,7 +534,9 @@ async fn relay_bond_extra(
 async fn para_record_rewards(
     subxt_para_client: &Client<HeikoRuntime>,
     para_signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     amount: Amount,
     first: bool,
@@ -241,9 +545,11 @@ async fn para_record_rewards(
     if first {
         let _ = transaction::do_first_para_record_rewards(
             others.clone(),
+            threshold,
             pool_addr,
             &subxt_para_client,
             para_signer,
+            origin,
             amount,
         )
         .await
@@ -251,9 +557,11 @@ async fn para_record_rewards(
     } else {
         let _ = transaction::do_last_para_record_rewards(
             others.clone(),
+            threshold,
             pool_addr,
             &subxt_para_client,
             para_signer,
+            origin,
             amount,
         )
         .await
@@ -264,7 +572,9 @@ async fn para_record_rewards(
 async fn para_record_slash(
     subxt_para_client: &Client<HeikoRuntime>,
     para_signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     amount: Amount,
     first: bool,
@@ -273,9 +583,11 @@ async fn para_record_slash(
     if first {
         let _ = transaction::do_first_para_record_slash(
             others.clone(),
+            threshold,
             pool_addr,
             &subxt_para_client,
             para_signer,
+            origin,
             amount,
         )
         .await
@@ -283,9 +595,11 @@ async fn para_record_slash(
     } else {
         let _ = transaction::do_last_para_record_slash(
             others.clone(),
+            threshold,
             pool_addr,
             &subxt_para_client,
             para_signer,
+            origin,
             amount,
         )
         .await
@@ -293,17 +607,14 @@ async fn para_record_slash(
     }
 }
 
-/// start withdraw task, ws_server: ws://127.0.0.1:9944
-pub(crate) async fn start_unstake_task(
-    relay_subxt_client: &Client<KusamaRuntime>,
-    amount: Amount,
-    first: bool,
-) {
-    // controller do unbond, current controller is Eve
-    // current controller is Eve
-    if first {
-        let _ = do_relay_unbond(&relay_subxt_client, amount.clone())
-            .await
-            .map_err(|e| warn!("error do_relay_unbond: {:?}", e));
-    }
+#[test]
+fn test_due_unbonded() {
+    use sp_keyring::AccountKeyring;
+
+    let a = AccountKeyring::Alice.to_account_id();
+    let unbonded = vec![(a.clone(), 10, 100), (a.clone(), 12, 200), (a, 20, 300)];
+    assert_eq!(due_unbonded(&unbonded, 40, 28), (2, 300));
+    assert_eq!(due_unbonded(&unbonded, 30, 28), (0, 0));
+    assert_eq!(withdrawn_unbonded(&unbonded, 300), 2);
+    assert_eq!(withdrawn_unbonded(&unbonded, 299), 1);
 }
@@ -6,151 +6,281 @@ use super::HeikoRuntime;
 use super::KusamaRuntime;
 use super::Multisig;
 
+use crate::common::anchor::anchor_block;
+use crate::common::balance::{estimate_fee, BalanceReserve};
 use crate::common::error::Error;
-use crate::common::primitives::{FOR_MOCK_SEED, FROM_RELAY_CHAIN_SEED, XCM_DEST_WEIGHT};
-use crate::kusama::client::MIN_BOND_BALANCE;
+use crate::common::logger::{record_amount, record_call_hash};
+use crate::common::origin::DispatchOrigin;
+use crate::common::primitives::FROM_RELAY_CHAIN_SEED;
+use crate::common::profile::XcmConfig;
 
 use async_std::task;
-use core::marker::PhantomData;
-use log::{info, warn};
 use runtime::pallets::liquid_staking::{RecordRewardsCall, RecordSlashCall};
 use runtime::pallets::multisig::Timepoint;
-use sp_core::{crypto::Ss58Codec, Pair};
-use sp_keyring::AccountKeyring;
-use std::str::FromStr;
+use runtime::pallets::proxy::ProxyType;
+use runtime::pallets::utility::BatchAllCall;
+use runtime::pallets::xcm_pallet::XcmTransferCall;
+use sp_core::{crypto::Ss58Codec, Pair, H256};
 use std::time::Duration;
 use substrate_subxt::{
-    staking, sudo, Call, Client, Error as SubError, ExtrinsicSuccess, PairSigner, Runtime, Signer,
+    staking, Call, Client, Encoded, Error as SubError, ExtrinsicSuccess, PairSigner, Runtime,
+    Signer,
 };
-use xcm::v0::{Junction, MultiAsset, MultiLocation, NetworkId};
+use tracing::{info, warn};
+
+/// The bond amounts are derived from the block of every `BOND_ANCHOR_INTERVAL` blocks, so the
+/// signatories approving around the same time derive the same amount.
+const BOND_ANCHOR_INTERVAL: u32 = 100;
+
+/// How many anchor blocks before the latest the last signatory looks back for the multisig.
+const BOND_ANCHOR_LOOKBACK: u32 = 3;
 
 /// The first wallet to call withdraw. No need use 'TimePoint' and call 'approve_as_multi'.
 pub(crate) async fn do_first_relay_bond(
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     subxt_client: &Client<KusamaRuntime>,
     signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: BalanceReserve,
+    at: H256,
 ) -> Result<(), Error> {
     info!("do_first_relay_bond");
-    // 1.1 construct staking bond call
-    //TODO change to controller address, change the payee type
-    let ctrl = AccountKeyring::Eve.to_account_id().into();
-    let call = kusama::api::staking_bond_call::<KusamaRuntime>(
-        &ctrl,
-        MIN_BOND_BALANCE,
-        staking::RewardDestination::Staked,
-    );
-
-    let account_id = AccountId::from_string(&pool_addr)
-        .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
-
-    // check again if the balance is correct
-    let _ = check_balance(subxt_client, account_id.clone()).await?;
-
-    let call_hash = kusama::api::multisig_call_hash(subxt_client, call)
-        .map_err(|e| Error::ClientRuntimeError(e))?;
-    let when = get_time_point::<KusamaRuntime>(subxt_client, account_id.clone(), call_hash).await;
-    if let Some(_) = when {
-        warn!("timepoint {:?} exists, multisig already initial", when);
-        return Err(Error::Other("timepoint exists".to_string()));
-    }
-    info!("multisig timepoint: {:?}", when);
-
-    // FIXME, implement clone call
-    let call = kusama::api::staking_bond_call::<KusamaRuntime>(
-        &ctrl,
-        MIN_BOND_BALANCE,
-        staking::RewardDestination::Staked,
-    );
-
-    let mc = kusama::api::multisig_approve_as_multi_call::<
-        KusamaRuntime,
-        staking::BondCall<KusamaRuntime>,
-    >(subxt_client, 2, others, None, call, 0u64)
-    .map_err(|e| Error::ClientRuntimeError(e))?;
-    // 1.2 initial the multisg call
-    let result = subxt_client
-        .watch(mc, signer)
-        .await
-        .map_err(|e| Error::SubxtError(e))?;
-    info!("do_first_relay_bond result: {:?}", result);
-    Ok(())
+    do_first_anchored_bond(
+        others,
+        threshold,
+        pool_addr,
+        subxt_client,
+        signer,
+        reserve,
+        false,
+        at,
+    )
+    .await
 }
 
-async fn check_balance(
+/// The signer opening the multisig must be able to reserve `DepositBase + DepositFactor * threshold`.
+async fn check_depositor_balance(
     subxt_client: &Client<KusamaRuntime>,
-    account_id: AccountId,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: &BalanceReserve,
+    at: H256,
 ) -> Result<(), Error> {
+    let deposit = reserve.multisig_deposit;
     let account = kusama::api::AccountStore::<KusamaRuntime> {
-        account: account_id,
+        account: signer.account_id().clone(),
     };
     subxt_client
-        .fetch(&account, None)
+        .fetch(&account, Some(at))
         .await
         .map_err(|e| Error::SubxtError(e))?
         .and_then(|account_store| -> Option<()> {
             let free = account_store.data.free;
             let misc_frozen = account_store.data.misc_frozen;
-            if free - misc_frozen >= MIN_BOND_BALANCE {
-                info!("can initial new multisig");
+            if free.saturating_sub(misc_frozen) >= deposit + reserve.existential_deposit {
                 return Some(());
             }
             None
         })
-        .ok_or(Error::Other(
-            "free - misc_frozen < MIN_BOND_BALANCE, cann't initial new multisig".to_string(),
-        ))
+        .ok_or(Error::Other(format!(
+            "signer can't reserve multisig deposit {}, cann't initial new multisig",
+            deposit
+        )))
 }
 
 /// If the wallet is the last one need to get 'TimePoint' and call 'as_multi'.
 pub(crate) async fn do_last_relay_bond(
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     subxt_client: &Client<KusamaRuntime>,
     signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: BalanceReserve,
+    at: H256,
 ) -> Result<(), Error> {
     info!("do_last_relay_bond");
-    let ctrl = AccountKeyring::Eve.to_account_id().into();
-    let call = kusama::api::staking_bond_call::<KusamaRuntime>(
-        &ctrl,
-        MIN_BOND_BALANCE,
-        staking::RewardDestination::Staked,
-    );
-    let public = sp_core::ed25519::Public::from_str(&pool_addr)
+    do_last_anchored_bond(
+        others,
+        threshold,
+        pool_addr,
+        subxt_client,
+        signer,
+        reserve,
+        false,
+        at,
+    )
+    .await
+}
+
+/// The amount the pool bonds, its spendable balance at the anchor block. Every input, the fee
+/// included, is read at the anchor block, so every signatory derives the same amount.
+async fn bond_amount(
+    subxt_client: &Client<KusamaRuntime>,
+    account_id: AccountId,
+    reserve: &BalanceReserve,
+    anchor: H256,
+) -> Result<Amount, Error> {
+    let call = kusama::api::staking_bond_extra_call::<KusamaRuntime>(reserve.min_bond);
+    let fee = estimate_fee(subxt_client, call, Some(anchor)).await?;
+    let account = kusama::api::AccountStore::<KusamaRuntime> {
+        account: account_id,
+    };
+    let account_store = subxt_client
+        .fetch(&account, Some(anchor))
+        .await
+        .map_err(|e| Error::SubxtError(e))?
+        .ok_or_else(|| Error::Other(format!("pool account not found at {:?}", anchor)))?;
+    let spendable = reserve.spendable(account_store.data.free, account_store.data.misc_frozen, fee);
+    Ok(spendable)
+}
+
+/// The amount derived from the anchor block of `at`, which must be no less than the minimum bond.
+async fn latest_bond_amount(
+    subxt_client: &Client<KusamaRuntime>,
+    account_id: AccountId,
+    reserve: &BalanceReserve,
+    at: H256,
+) -> Result<(H256, Amount), Error> {
+    let anchor = anchor_block(subxt_client, at, BOND_ANCHOR_INTERVAL, 0).await?;
+    let amount = bond_amount(subxt_client, account_id, reserve, anchor).await?;
+    if amount < reserve.min_bond {
+        return Err(Error::Other(format!(
+            "bond amount {} at {:?} < min bond, cann't bond",
+            amount, anchor
+        )));
+    }
+    Ok((anchor, amount))
+}
+
+/// `batch_all[remark(anchor), bond]`, the anchor is embedded so the signatories build the
+/// identical call from the same block. Bond extra if the pool is already bonded.
+fn anchored_bond_call(
+    subxt_client: &Client<KusamaRuntime>,
+    account_id: AccountId,
+    anchor: H256,
+    amount: Amount,
+    bonded: bool,
+) -> Result<BatchAllCall<KusamaRuntime>, Error> {
+    let remark = kusama::api::system_remark_call::<KusamaRuntime>(anchor.as_bytes().to_vec());
+    let bond = if bonded {
+        let call = kusama::api::staking_bond_extra_call::<KusamaRuntime>(amount);
+        kusama::api::utility_encode_call(subxt_client, call)?
+    } else {
+        // the pool is its own controller
+        let ctrl = account_id.into();
+        let call = kusama::api::staking_bond_call::<KusamaRuntime>(
+            &ctrl,
+            amount,
+            staking::RewardDestination::Staked,
+        );
+        kusama::api::utility_encode_call(subxt_client, call)?
+    };
+    Ok(kusama::api::utility_batch_all_call::<KusamaRuntime>(vec![
+        kusama::api::utility_encode_call(subxt_client, remark)?,
+        bond,
+    ]))
+}
+
+/// Open the multisig bonding the amount derived from the latest anchor block.
+async fn do_first_anchored_bond(
+    others: Vec<AccountId>,
+    threshold: u16,
+    pool_addr: String,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: BalanceReserve,
+    bonded: bool,
+    at: H256,
+) -> Result<(), Error> {
+    let account_id = AccountId::from_string(&pool_addr)
         .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
-    let call_hash = kusama::api::multisig_call_hash(subxt_client, call)
+
+    // 1.1 construct the bond call from the anchor block
+    let (anchor, amount) =
+        latest_bond_amount(subxt_client, account_id.clone(), &reserve, at).await?;
+    let _ = check_depositor_balance(subxt_client, signer, &reserve, at).await?;
+
+    let call = anchored_bond_call(subxt_client, account_id.clone(), anchor, amount, bonded)?;
+    let call_hash = kusama::api::multisig_call_hash(subxt_client, call.clone())
         .map_err(|e| Error::ClientRuntimeError(e))?;
-    let when = get_time_point::<KusamaRuntime>(subxt_client, public.into(), call_hash).await;
-    if None == when {
-        warn!("timepoint is null, multisig must initial first");
-        return Err(Error::Other("timepoint is null".to_string()));
+    record_call_hash(&call_hash);
+    record_amount(amount);
+    let when = get_time_point::<KusamaRuntime>(subxt_client, account_id.clone(), call_hash).await;
+    if let Some(_) = when {
+        warn!("timepoint {:?} exists, multisig already initial", when);
+        return Err(Error::Other("timepoint exists".to_string()));
     }
-    info!("multisig timepoint: {:?}", when);
+    info!("bond {} derived from block {:?}", amount, anchor);
 
-    // FIXME: clone call
-    let call = kusama::api::staking_bond_call::<KusamaRuntime>(
-        &ctrl,
-        MIN_BOND_BALANCE,
-        staking::RewardDestination::Staked,
-    );
-    // 3.1 approve the call and execute it
-    let mc = kusama::api::multisig_as_multi_call::<KusamaRuntime, staking::BondCall<KusamaRuntime>>(
-        subxt_client,
-        2,
-        others,
-        when,
-        call,
-        false,
-        1_000_000_000_000,
-    )?;
+    let mc = kusama::api::multisig_approve_as_multi_call::<
+        KusamaRuntime,
+        BatchAllCall<KusamaRuntime>,
+    >(subxt_client, threshold, others, None, call, 0u64)
+    .map_err(|e| Error::ClientRuntimeError(e))?;
+    // 1.2 initial the multisg call
     let result = subxt_client
         .watch(mc, signer)
         .await
         .map_err(|e| Error::SubxtError(e))?;
-    info!("multisig_as_multi_call result {:?}", result);
+    info!("do_first_anchored_bond result: {:?}", result);
     Ok(())
 }
 
+/// Approve the multisig opened by the first signatory, found by rebuilding the bond call from
+/// the recent anchor blocks.
+async fn do_last_anchored_bond(
+    others: Vec<AccountId>,
+    threshold: u16,
+    pool_addr: String,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: BalanceReserve,
+    bonded: bool,
+    at: H256,
+) -> Result<(), Error> {
+    let account_id = AccountId::from_string(&pool_addr)
+        .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
+    for back in 0..=BOND_ANCHOR_LOOKBACK {
+        let anchor = anchor_block(subxt_client, at, BOND_ANCHOR_INTERVAL, back).await?;
+        let amount = bond_amount(subxt_client, account_id.clone(), &reserve, anchor).await?;
+        let call = anchored_bond_call(subxt_client, account_id.clone(), anchor, amount, bonded)?;
+        let call_hash = kusama::api::multisig_call_hash(subxt_client, call.clone())
+            .map_err(|e| Error::ClientRuntimeError(e))?;
+        let when =
+            get_time_point::<KusamaRuntime>(subxt_client, account_id.clone(), call_hash).await;
+        if when.is_none() {
+            continue;
+        }
+        record_call_hash(&call_hash);
+        record_amount(amount);
+        info!(
+            "multisig timepoint: {:?}, bond {} derived from block {:?}",
+            when, amount, anchor
+        );
+
+        // 3.1 approve the call and execute it
+        let mc = kusama::api::multisig_as_multi_call::<KusamaRuntime, BatchAllCall<KusamaRuntime>>(
+            subxt_client,
+            threshold,
+            others,
+            when,
+            call,
+            false,
+            1_000_000_000_000,
+        )?;
+        let result = subxt_client
+            .watch(mc, signer)
+            .await
+            .map_err(|e| Error::SubxtError(e))?;
+        info!("multisig_as_multi_call result {:?}", result);
+        return Ok(());
+    }
+    warn!("timepoint is null, multisig must initial first");
+    Err(Error::Other("timepoint is null".to_string()))
+}
+
+/// The multisig is opened after the block the task was decided at, so it is looked up at the
+/// latest block.
 pub(crate) async fn get_time_point<T: Runtime + Multisig>(
     subxt_client: &Client<T>,
     multisig_account: T::AccountId,
@@ -176,86 +306,202 @@ pub(crate) async fn get_time_point<T: Runtime + Multisig>(
 
 pub(crate) async fn do_first_relay_bond_extra(
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     subxt_client: &Client<KusamaRuntime>,
     signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: BalanceReserve,
+    at: H256,
 ) -> Result<(), Error> {
     info!("do_first_relay_bond_extra");
-    // 1.1 construct staking bond call
-    let call = kusama::api::staking_bond_extra_call::<KusamaRuntime>(MIN_BOND_BALANCE);
+    do_first_anchored_bond(
+        others,
+        threshold,
+        pool_addr,
+        subxt_client,
+        signer,
+        reserve,
+        true,
+        at,
+    )
+    .await
+}
+
+pub(crate) async fn do_last_relay_bond_extra(
+    others: Vec<AccountId>,
+    threshold: u16,
+    pool_addr: String,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: BalanceReserve,
+    at: H256,
+) -> Result<(), Error> {
+    info!("do_last_relay_bond_extra");
+    do_last_anchored_bond(
+        others,
+        threshold,
+        pool_addr,
+        subxt_client,
+        signer,
+        reserve,
+        true,
+        at,
+    )
+    .await
+}
 
+/// Bond from the pool through `proxy.proxy`, no other signatory needs to approve.
+pub(crate) async fn do_proxy_relay_bond(
+    pool_addr: String,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: BalanceReserve,
+    at: H256,
+) -> Result<(), Error> {
+    info!("do_proxy_relay_bond");
     let account_id = AccountId::from_string(&pool_addr)
         .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
-    // check again if the balance is correct
-    let _ = check_balance(subxt_client, account_id.clone()).await?;
-    let call_hash = kusama::api::multisig_call_hash(subxt_client, call.clone())
-        .map_err(|e| Error::ClientRuntimeError(e))?;
-    let when = get_time_point::<KusamaRuntime>(subxt_client, account_id.clone(), call_hash).await;
-    if let Some(_) = when {
-        warn!("timepoint {:?} exists, multisig already initial", when);
-        return Err(Error::Other("timepoint exists".to_string()));
-    }
-    info!("multisig timepoint: {:?}", when);
+    let (_, amount) = latest_bond_amount(subxt_client, account_id.clone(), &reserve, at).await?;
 
-    let mc = kusama::api::multisig_approve_as_multi_call::<
-        KusamaRuntime,
-        kusama::api::BondExtraCall<KusamaRuntime>,
-    >(subxt_client, 2, others, None, call, 0u64)
-    .map_err(|e| Error::ClientRuntimeError(e))?;
-    // 1.2 initial the multisg call
-    let result = subxt_client
-        .watch(mc, signer)
-        .await
-        .map_err(|e| Error::SubxtError(e))?;
-    info!("do_first_relay_bond result: {:?}", result);
+    // the pool is its own controller, so the proxy can dispatch the calls of controller too
+    let ctrl = account_id.into();
+    let call = kusama::api::staking_bond_call::<KusamaRuntime>(
+        &ctrl,
+        amount,
+        staking::RewardDestination::Staked,
+    );
+    let result = do_proxy_relay_call(pool_addr, subxt_client, signer, call).await?;
+    info!("do_proxy_relay_bond result: {:?}", result);
     Ok(())
 }
 
-pub(crate) async fn do_last_relay_bond_extra(
-    others: Vec<AccountId>,
+/// Bond extra from the pool through `proxy.proxy`, no other signatory needs to approve.
+pub(crate) async fn do_proxy_relay_bond_extra(
     pool_addr: String,
     subxt_client: &Client<KusamaRuntime>,
     signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: BalanceReserve,
+    at: H256,
 ) -> Result<(), Error> {
-    info!("do_last_relay_bond_extra");
-    let call = kusama::api::staking_bond_extra_call::<KusamaRuntime>(MIN_BOND_BALANCE);
-    let public = sp_core::ed25519::Public::from_str(&pool_addr)
+    info!("do_proxy_relay_bond_extra");
+    let account_id = AccountId::from_string(&pool_addr)
         .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
-    let call_hash = kusama::api::multisig_call_hash(subxt_client, call.clone())
-        .map_err(|e| Error::ClientRuntimeError(e))?;
-    let when = get_time_point::<KusamaRuntime>(subxt_client, public.into(), call_hash).await;
-    if None == when {
-        warn!("timepoint is null, multisig must initial first");
-        return Err(Error::Other("timepoint is null".to_string()));
-    }
-    info!("multisig timepoint: {:?}", when);
+    let (_, amount) = latest_bond_amount(subxt_client, account_id, &reserve, at).await?;
 
-    // 3.1 approve the call and execute it
-    let mc = kusama::api::multisig_as_multi_call::<
-        KusamaRuntime,
-        kusama::api::BondExtraCall<KusamaRuntime>,
-    >(
-        subxt_client,
-        2,
-        others,
-        when,
-        call,
-        false,
-        1_000_000_000_000,
-    )?;
+    let call = kusama::api::staking_bond_extra_call::<KusamaRuntime>(amount);
+    let result = do_proxy_relay_call(pool_addr, subxt_client, signer, call).await?;
+    info!("do_proxy_relay_bond_extra result: {:?}", result);
+    Ok(())
+}
+
+/// Nominate the validators from the pool through `proxy.proxy`, the pool must be its own
+/// controller.
+pub(crate) async fn do_proxy_relay_nominate(
+    pool_addr: String,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    validators: Vec<AccountId>,
+) -> Result<(), Error> {
+    info!("do_proxy_relay_nominate");
+    let call = kusama::api::staking_nominate_call::<KusamaRuntime>(
+        validators.into_iter().map(Into::into).collect(),
+    );
+    let result = do_proxy_relay_call(pool_addr, subxt_client, signer, call).await?;
+    info!("do_proxy_relay_nominate result: {:?}", result);
+    Ok(())
+}
+
+/// Unbond from the pool through `proxy.proxy`, the pool must be its own controller.
+pub(crate) async fn do_proxy_relay_unbond(
+    pool_addr: String,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    amount: Amount,
+) -> Result<(), Error> {
+    info!("do_proxy_relay_unbond");
+    let call = kusama::api::staking_unbond_call::<KusamaRuntime>(amount);
+    let result = do_proxy_relay_call(pool_addr, subxt_client, signer, call).await?;
+    info!("do_proxy_relay_unbond result: {:?}", result);
+    Ok(())
+}
+
+/// Withdraw unbonded of the pool through `proxy.proxy`, the pool must be its own controller.
+pub(crate) async fn do_proxy_relay_withdraw_unbonded(
+    pool_addr: String,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    at: H256,
+) -> Result<(), Error> {
+    info!("do_proxy_relay_withdraw_unbonded");
+    let stash = AccountId::from_string(&pool_addr)
+        .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
+    let num_slashing_spans = get_num_slashing_spans(subxt_client, stash, at).await?;
+    let call = kusama::api::staking_withdraw_unbonded_call::<KusamaRuntime>(num_slashing_spans);
+    let result = do_proxy_relay_call(pool_addr, subxt_client, signer, call).await?;
+    info!("do_proxy_relay_withdraw_unbonded result: {:?}", result);
+    Ok(())
+}
+
+/// Dispatch the staking call on behalf of the pool, the signer must be a staking proxy of pool.
+async fn do_proxy_relay_call<C: Call<KusamaRuntime> + Send + Sync>(
+    pool_addr: String,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    call: C,
+) -> Result<ExtrinsicSuccess<KusamaRuntime>, Error> {
+    let real = AccountId::from_string(&pool_addr)
+        .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
+    let proxy_type =
+        get_staking_proxy_type(subxt_client, real.clone(), signer.account_id().clone()).await?;
+    let proxy_call = kusama::api::proxy_call(subxt_client, real, Some(proxy_type), call)
+        .map_err(|e| Error::ClientRuntimeError(e))?;
     let result = subxt_client
-        .watch(mc, signer)
+        .watch(proxy_call, signer)
         .await
         .map_err(|e| Error::SubxtError(e))?;
-    info!("multisig_as_multi_call result {:?}", result);
-    Ok(())
+
+    // the extrinsic succeeds even if the proxied call fails, the result is in the event
+    match result.find_event_raw("Proxy", "ProxyExecuted") {
+        Some(event) if event.data.first() == Some(&0) => Ok(result),
+        Some(event) => Err(Error::Other(format!(
+            "proxied call failed: 0x{}",
+            hex::encode(&event.data)
+        ))),
+        None => Err(Error::Other("proxied call is not executed".to_string())),
+    }
+}
+
+/// The type of proxy which the delegate can dispatch the staking calls of real with.
+async fn get_staking_proxy_type(
+    subxt_client: &Client<KusamaRuntime>,
+    real: AccountId,
+    delegate: AccountId,
+) -> Result<ProxyType, Error> {
+    let store = kusama::api::ProxiesStore::<KusamaRuntime> { real: real.clone() };
+    let (proxies, _) = subxt_client
+        .fetch(&store, None)
+        .await
+        .map_err(|e| Error::SubxtError(e))?
+        .unwrap_or_default();
+    proxies
+        .into_iter()
+        .find(|p| p.delegate == delegate && p.delay == 0 && p.proxy_type.allows_staking())
+        .map(|p| p.proxy_type)
+        .ok_or_else(|| {
+            Error::Other(format!(
+                "{} is not a staking proxy of {} without delay",
+                delegate, real
+            ))
+        })
 }
 
 pub(crate) async fn do_first_para_record_rewards(
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     amount: Amount,
 ) -> Result<(), Error> {
     info!("do_first_para_record_rewards");
@@ -266,9 +512,11 @@ pub(crate) async fn do_first_para_record_rewards(
         heiko::api::liquid_staking_record_rewards_call::<HeikoRuntime>(account_id.clone(), amount);
     let result = first_para_record_reward_and_slash::<RecordRewardsCall<HeikoRuntime>>(
         others,
+        threshold,
         account_id,
         subxt_client,
         signer,
+        origin,
         inner_call,
     )
     .await?;
@@ -278,9 +526,11 @@ pub(crate) async fn do_first_para_record_rewards(
 
 pub(crate) async fn do_last_para_record_rewards(
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     amount: Amount,
 ) -> Result<(), Error> {
     info!("do_last_para_record_rewards");
@@ -291,9 +541,11 @@ pub(crate) async fn do_last_para_record_rewards(
         heiko::api::liquid_staking_record_rewards_call::<HeikoRuntime>(account_id.clone(), amount);
     let result = last_para_record_reward_and_slash::<RecordRewardsCall<HeikoRuntime>>(
         others,
+        threshold,
         account_id,
         subxt_client,
         signer,
+        origin,
         inner_call,
     )
     .await?;
@@ -303,9 +555,11 @@ pub(crate) async fn do_last_para_record_rewards(
 
 pub(crate) async fn do_first_para_record_slash(
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     amount: Amount,
 ) -> Result<(), Error> {
     info!("do_first_para_record_slash");
@@ -315,9 +569,11 @@ pub(crate) async fn do_first_para_record_slash(
         heiko::api::liquid_staking_record_slash_call::<HeikoRuntime>(account_id.clone(), amount);
     let result = first_para_record_reward_and_slash::<RecordSlashCall<HeikoRuntime>>(
         others,
+        threshold,
         account_id,
         subxt_client,
         signer,
+        origin,
         inner_call,
     )
     .await?;
@@ -327,9 +583,11 @@ pub(crate) async fn do_first_para_record_slash(
 
 pub(crate) async fn do_last_para_record_slash(
     others: Vec<AccountId>,
+    threshold: u16,
     pool_addr: String,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     amount: Amount,
 ) -> Result<(), Error> {
     info!("do_last_para_record_slash");
@@ -340,9 +598,11 @@ pub(crate) async fn do_last_para_record_slash(
         heiko::api::liquid_staking_record_slash_call::<HeikoRuntime>(account_id.clone(), amount);
     let result = last_para_record_reward_and_slash::<RecordSlashCall<HeikoRuntime>>(
         others,
+        threshold,
         account_id,
         subxt_client,
         signer,
+        origin,
         inner_call,
     )
     .await?;
@@ -353,23 +613,19 @@ pub(crate) async fn do_last_para_record_slash(
 // TODO try to integrate `first_para_record_reward_and_slash` and `last_para_record_reward_and_slash`
 async fn first_para_record_reward_and_slash<C: Call<HeikoRuntime> + Send + Sync>(
     others: Vec<AccountId>,
+    threshold: u16,
     account_id: AccountId,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     inner_call: C,
 ) -> Result<ExtrinsicSuccess<HeikoRuntime>, Error> {
-    // 1.2 construct sudo call
-    let inner_call_encoded = subxt_client
-        .encode(inner_call)
-        .map_err(|e| Error::SubxtError(e))?;
-    let sudo_call = sudo::SudoCall::<HeikoRuntime> {
-        _runtime: PhantomData,
-        call: &inner_call_encoded,
-    };
+    // 1.2 wrap the call for the dispatch origin
+    let call = origin.wrap(subxt_client, inner_call)?;
 
     // check if timepoint already exist.
-    let call_hash = heiko::api::multisig_call_hash(subxt_client, sudo_call.clone())
-        .map_err(|e| Error::ClientRuntimeError(e))?;
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
     //FIXME, multisig accout should change
     let when = get_time_point::<HeikoRuntime>(subxt_client, account_id.clone(), call_hash).await;
     if let Some(_) = when {
@@ -379,11 +635,9 @@ async fn first_para_record_reward_and_slash<C: Call<HeikoRuntime> + Send + Sync>
     info!("multisig timepoint: {:?}", when);
 
     // 1.3 construct multisig call
-    let multisig_call = heiko::api::multisig_approve_as_multi_call::<
-        HeikoRuntime,
-        sudo::SudoCall<HeikoRuntime>,
-    >(subxt_client, 2, others, None, sudo_call, 0u64)
-    .map_err(|e| Error::ClientRuntimeError(e))?;
+    let multisig_call = heiko::api::multisig_approve_as_multi_encoded_call::<HeikoRuntime>(
+        threshold, others, None, &call, 0u64,
+    );
     // 1.2 initial the multisg call
     let result = subxt_client
         .watch(multisig_call, signer)
@@ -394,23 +648,19 @@ async fn first_para_record_reward_and_slash<C: Call<HeikoRuntime> + Send + Sync>
 
 async fn last_para_record_reward_and_slash<C: Call<HeikoRuntime> + Send + Sync>(
     others: Vec<AccountId>,
+    threshold: u16,
     account_id: AccountId,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     inner_call: C,
 ) -> Result<ExtrinsicSuccess<HeikoRuntime>, Error> {
-    // 1.2 construct sudo call
-    let inner_call_encoded = subxt_client
-        .encode(inner_call)
-        .map_err(|e| Error::SubxtError(e))?;
-    let sudo_call = sudo::SudoCall::<HeikoRuntime> {
-        _runtime: PhantomData,
-        call: &inner_call_encoded,
-    };
+    // 1.2 wrap the call for the dispatch origin
+    let call = origin.wrap(subxt_client, inner_call)?;
 
     // check if timepoint already exist.
-    let call_hash = heiko::api::multisig_call_hash(subxt_client, sudo_call.clone())
-        .map_err(|e| Error::ClientRuntimeError(e))?;
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
 
     //TODO this `loop` is really a temporary check way.
     let mut check_times = 0u8;
@@ -438,17 +688,14 @@ async fn last_para_record_reward_and_slash<C: Call<HeikoRuntime> + Send + Sync>(
     info!("multisig timepoint: {:?}", when);
 
     // 1.3 construct multisig call
-    let multisig_call =
-        heiko::api::multisig_as_multi_call::<HeikoRuntime, sudo::SudoCall<HeikoRuntime>>(
-            subxt_client,
-            2,
-            others,
-            when,
-            sudo_call,
-            false,
-            1_000_000_000_000,
-        )
-        .map_err(|e| Error::ClientRuntimeError(e))?;
+    let multisig_call = heiko::api::multisig_as_multi_encoded_call::<HeikoRuntime>(
+        threshold,
+        others,
+        when,
+        call,
+        false,
+        1_000_000_000_000,
+    );
     // 1.2 initial the multisg call
     let result = subxt_client
         .watch(multisig_call, signer)
@@ -457,82 +704,328 @@ async fn last_para_record_reward_and_slash<C: Call<HeikoRuntime> + Send + Sync>(
     Ok(result)
 }
 
+/// Unbond from the pool through its multisig, the pool is its own controller.
 pub(crate) async fn do_relay_unbond(
+    others: Vec<AccountId>,
+    threshold: u16,
+    pool_addr: String,
     subxt_client: &Client<KusamaRuntime>,
-    amount: u128,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    amount: Amount,
+    first: bool,
 ) -> Result<(), Error> {
-    info!("Create relay chain unbond transaction");
-    let pair = sp_core::sr25519::Pair::from_string(&FOR_MOCK_SEED, None)
-        .map_err(|_err| SubError::Other("failed to create pair from seed".to_string()))?;
-    let signer = PairSigner::<KusamaRuntime, sp_core::sr25519::Pair>::new(pair.clone());
+    info!("do_relay_unbond");
     let call = kusama::api::staking_unbond_call::<KusamaRuntime>(amount);
-    let result = subxt_client.watch(call, &signer).await.map_err(|e| {
-        info!("do_relay_unbond error {:?}", e);
-        SubError::Other("failed to create unbond transaction".to_string())
-    })?;
-
-    info!("Replay chain call result {:?}", result);
-    Ok(())
+    let calls = vec![kusama::api::utility_encode_call(subxt_client, call)?];
+    do_relay_multisig_batch(
+        others,
+        threshold,
+        pool_addr,
+        subxt_client,
+        signer,
+        calls,
+        first,
+    )
+    .await
 }
 
+/// Withdraw the unbonded of pool through its multisig, the pool is its own controller.
 pub(crate) async fn do_relay_withdraw_unbonded(
+    others: Vec<AccountId>,
+    threshold: u16,
+    pool_addr: String,
     subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
     first: bool,
+    at: H256,
 ) -> Result<(), Error> {
-    if first {
-        info!("Create relay chain withdraw unbonded transaction");
-        let pair = sp_core::sr25519::Pair::from_string(&FOR_MOCK_SEED, None)
-            .map_err(|_err| SubError::Other("failed to create pair from seed".to_string()))?;
-        let signer = PairSigner::<KusamaRuntime, sp_core::sr25519::Pair>::new(pair.clone());
-        let call = kusama::api::staking_withdraw_unbonded_call::<KusamaRuntime>(0);
-        let result = subxt_client.watch(call, &signer).await.map_err(|e| {
-            println!("{:?}", e);
-            SubError::Other("failed to create withdraw unbonded transaction".to_string())
-        })?;
+    info!("do_relay_withdraw_unbonded");
+    let stash = AccountId::from_string(&pool_addr)
+        .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
+    let num_slashing_spans = get_num_slashing_spans(subxt_client, stash, at).await?;
+    info!(
+        "withdraw unbonded num_slashing_spans: {:?}",
+        num_slashing_spans
+    );
+    let call = kusama::api::staking_withdraw_unbonded_call::<KusamaRuntime>(num_slashing_spans);
+    let calls = vec![kusama::api::utility_encode_call(subxt_client, call)?];
+    do_relay_multisig_batch(
+        others,
+        threshold,
+        pool_addr,
+        subxt_client,
+        signer,
+        calls,
+        first,
+    )
+    .await
+}
 
-        info!("Replay chain call result {:?}", result);
-    }
-    Ok(())
+/// The number of slashing spans of the stash, zero if the stash has never been slashed.
+async fn get_num_slashing_spans(
+    subxt_client: &Client<KusamaRuntime>,
+    stash: AccountId,
+    at: H256,
+) -> Result<u32, Error> {
+    let store = kusama::api::SlashingSpansStore::<KusamaRuntime> { stash };
+    let num_slashing_spans = subxt_client
+        .fetch(&store, Some(at))
+        .await
+        .map_err(|e| Error::SubxtError(e))?
+        .map(|spans| spans.num_slashing_spans())
+        .unwrap_or(0);
+    Ok(num_slashing_spans)
 }
 
 pub(crate) async fn do_xcm_transfer_to_para_chain(
     subxt_client: &Client<KusamaRuntime>,
     pool_addr: String,
+    xcm: XcmConfig,
     amount: Amount,
     first: bool,
-) -> Result<(), Error> {
+) -> Result<Option<H256>, Error> {
     if first {
         info!("do_xcm_transfer_to_para_chain");
-        let pool_account_id = AccountId::from_string(&pool_addr)
-            .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
-
         info!("Create relay chain xcm reserve transfer assets transaction");
         let pair = sp_core::sr25519::Pair::from_string(&FROM_RELAY_CHAIN_SEED, None)
             .map_err(|_err| SubError::Other("failed to create pair from seed".to_string()))?;
         let signer = PairSigner::<KusamaRuntime, sp_core::sr25519::Pair>::new(pair.clone());
 
-        let dest = MultiLocation::X1(Junction::Parachain(2000));
-        let beneficiary = MultiLocation::X1(Junction::AccountId32 {
-            network: NetworkId::Any,
-            id: pool_account_id.into(),
-        });
-        let assets = vec![MultiAsset::ConcreteFungible {
-            id: MultiLocation::Null,
-            amount,
-        }];
-        let call = kusama::api::reserve_transfer_assets_call::<KusamaRuntime>(
-            dest,
-            beneficiary,
-            assets,
-            XCM_DEST_WEIGHT as u64,
-        );
-        let result = subxt_client.watch(call, &signer).await.map_err(|e| {
-            println!("{:?}", e);
+        let call = xcm_transfer_to_para_chain_call(pool_addr, xcm, amount)?;
+        let result = call.watch(subxt_client, &signer).await.map_err(|e| {
+            warn!("error xcm reserve transfer assets: {:?}", e);
             SubError::Other("failed to create xcm reserve transfer assets transaction".to_string())
         })?;
 
         info!("Replay chain call result {:?}", result);
+        return Ok(Some(result.block));
+    }
+
+    Ok(None)
+}
+
+/// The call to transfer the amount to the pool of para chain through XCM.
+fn xcm_transfer_to_para_chain_call(
+    pool_addr: String,
+    xcm: XcmConfig,
+    amount: Amount,
+) -> Result<XcmTransferCall<KusamaRuntime>, Error> {
+    let pool_account_id = AccountId::from_string(&pool_addr)
+        .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
+    Ok(XcmTransferCall::to_para_chain(
+        xcm.version,
+        xcm.para_id,
+        pool_account_id.into(),
+        amount,
+        xcm.fee_asset_item,
+        xcm.dest_weight,
+    ))
+}
+
+/// `[remark(anchor), bond, nominate]`, the same anchored bond as the non-batch path followed by
+/// the nomination. The pool is bonded as its own controller.
+fn anchored_bond_and_nominate_calls(
+    subxt_client: &Client<KusamaRuntime>,
+    account_id: AccountId,
+    anchor: H256,
+    amount: Amount,
+    validators: Vec<AccountId>,
+) -> Result<Vec<Encoded>, Error> {
+    let remark = kusama::api::system_remark_call::<KusamaRuntime>(anchor.as_bytes().to_vec());
+    let ctrl = account_id.into();
+    let bond = kusama::api::staking_bond_call::<KusamaRuntime>(
+        &ctrl,
+        amount,
+        staking::RewardDestination::Staked,
+    );
+    let nominate = kusama::api::staking_nominate_call::<KusamaRuntime>(
+        validators.into_iter().map(Into::into).collect(),
+    );
+    Ok(vec![
+        kusama::api::utility_encode_call(subxt_client, remark)?,
+        kusama::api::utility_encode_call(subxt_client, bond)?,
+        kusama::api::utility_encode_call(subxt_client, nominate)?,
+    ])
+}
+
+/// Bond the amount derived from the anchor block and nominate the validators in one multisig
+/// call, the pool is bonded as its own controller so that it can withdraw the unbonded in the
+/// batch with the transfer. The last signatory looks back for the anchor the multisig was
+/// opened with.
+pub(crate) async fn do_relay_batch_bond_and_nominate(
+    others: Vec<AccountId>,
+    threshold: u16,
+    pool_addr: String,
+    validators: Vec<AccountId>,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    reserve: BalanceReserve,
+    first: bool,
+    at: H256,
+) -> Result<(), Error> {
+    info!("do_relay_batch_bond_and_nominate");
+    let account_id = AccountId::from_string(&pool_addr)
+        .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
+    if first {
+        let (anchor, amount) =
+            latest_bond_amount(subxt_client, account_id.clone(), &reserve, at).await?;
+        let _ = check_depositor_balance(subxt_client, signer, &reserve, at).await?;
+        let calls =
+            anchored_bond_and_nominate_calls(subxt_client, account_id, anchor, amount, validators)?;
+        record_amount(amount);
+        info!("bond {} derived from block {:?}", amount, anchor);
+        return do_relay_multisig_batch(
+            others,
+            threshold,
+            pool_addr,
+            subxt_client,
+            signer,
+            calls,
+            first,
+        )
+        .await;
+    }
+
+    for back in 0..=BOND_ANCHOR_LOOKBACK {
+        let anchor = anchor_block(subxt_client, at, BOND_ANCHOR_INTERVAL, back).await?;
+        let amount = bond_amount(subxt_client, account_id.clone(), &reserve, anchor).await?;
+        let calls = anchored_bond_and_nominate_calls(
+            subxt_client,
+            account_id.clone(),
+            anchor,
+            amount,
+            validators.clone(),
+        )?;
+        let call = kusama::api::utility_batch_all_call::<KusamaRuntime>(calls.clone());
+        let call_hash = kusama::api::multisig_call_hash(subxt_client, call)
+            .map_err(|e| Error::ClientRuntimeError(e))?;
+        let when =
+            get_time_point::<KusamaRuntime>(subxt_client, account_id.clone(), call_hash).await;
+        if when.is_none() {
+            continue;
+        }
+        record_amount(amount);
+        info!("bond {} derived from block {:?}", amount, anchor);
+        return do_relay_multisig_batch(
+            others,
+            threshold,
+            pool_addr,
+            subxt_client,
+            signer,
+            calls,
+            first,
+        )
+        .await;
     }
+    warn!("timepoint is null, multisig must initial first");
+    Err(Error::Other("timepoint is null".to_string()))
+}
 
+/// Withdraw the unbonded and transfer it back to the pool of para chain in one multisig call,
+/// the pool must be its own controller.
+pub(crate) async fn do_relay_batch_withdraw_and_transfer(
+    others: Vec<AccountId>,
+    threshold: u16,
+    pool_addr: String,
+    para_pool_addr: String,
+    xcm: XcmConfig,
+    amount: Amount,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    first: bool,
+    at: H256,
+) -> Result<(), Error> {
+    info!("do_relay_batch_withdraw_and_transfer");
+    let stash = AccountId::from_string(&pool_addr)
+        .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
+    let num_slashing_spans = get_num_slashing_spans(subxt_client, stash, at).await?;
+    let withdraw = kusama::api::staking_withdraw_unbonded_call::<KusamaRuntime>(num_slashing_spans);
+    let transfer = xcm_transfer_to_para_chain_call(para_pool_addr, xcm, amount)?;
+    let calls = vec![
+        kusama::api::utility_encode_call(subxt_client, withdraw)?,
+        transfer.encode(subxt_client)?,
+    ];
+    do_relay_multisig_batch(
+        others,
+        threshold,
+        pool_addr,
+        subxt_client,
+        signer,
+        calls,
+        first,
+    )
+    .await
+}
+
+/// The offset of the dispatch result in `MultisigExecuted`, after the approving account, the
+/// timepoint, the multisig account and the call hash.
+const MULTISIG_EXECUTED_RESULT: usize = 32 + 8 + 32 + 32;
+
+/// Approve the calls of pool as one `utility.batch_all` multisig call, or execute it as the last
+/// signatory. Either all or none of the calls are dispatched.
+async fn do_relay_multisig_batch(
+    others: Vec<AccountId>,
+    threshold: u16,
+    pool_addr: String,
+    subxt_client: &Client<KusamaRuntime>,
+    signer: &(dyn Signer<KusamaRuntime> + Send + Sync),
+    calls: Vec<Encoded>,
+    first: bool,
+) -> Result<(), Error> {
+    let account_id = AccountId::from_string(&pool_addr)
+        .map_err(|_e| Error::Other("parse pool_addr to account id error".to_string()))?;
+    let call = kusama::api::utility_batch_all_call::<KusamaRuntime>(calls);
+    let call_hash = kusama::api::multisig_call_hash(subxt_client, call.clone())
+        .map_err(|e| Error::ClientRuntimeError(e))?;
+    record_call_hash(&call_hash);
+    let when = get_time_point::<KusamaRuntime>(subxt_client, account_id, call_hash).await;
+    info!("multisig timepoint: {:?}", when);
+
+    let result = if first {
+        if let Some(_) = when {
+            warn!("timepoint {:?} exists, multisig already initial", when);
+            return Err(Error::Other("timepoint exists".to_string()));
+        }
+        let mc = kusama::api::multisig_approve_as_multi_call::<
+            KusamaRuntime,
+            BatchAllCall<KusamaRuntime>,
+        >(subxt_client, threshold, others, None, call, 0u64)
+        .map_err(|e| Error::ClientRuntimeError(e))?;
+        subxt_client
+            .watch(mc, signer)
+            .await
+            .map_err(|e| Error::SubxtError(e))?
+    } else {
+        if None == when {
+            warn!("timepoint is null, multisig must initial first");
+            return Err(Error::Other("timepoint is null".to_string()));
+        }
+        let mc = kusama::api::multisig_as_multi_call::<KusamaRuntime, BatchAllCall<KusamaRuntime>>(
+            subxt_client,
+            threshold,
+            others,
+            when,
+            call,
+            false,
+            1_000_000_000_000,
+        )
+        .map_err(|e| Error::ClientRuntimeError(e))?;
+        let result = subxt_client
+            .watch(mc, signer)
+            .await
+            .map_err(|e| Error::SubxtError(e))?;
+        // the extrinsic succeeds even if the batch fails, the result is in the event
+        match result.find_event_raw("Multisig", "MultisigExecuted") {
+            Some(event) if event.data.get(MULTISIG_EXECUTED_RESULT) == Some(&0) => result,
+            Some(event) => {
+                return Err(Error::Other(format!(
+                    "multisig batch failed: 0x{}",
+                    hex::encode(&event.data)
+                )))
+            }
+            None => return Err(Error::Other("multisig batch is not executed".to_string())),
+        }
+    };
+    info!("do_relay_multisig_batch result: {:?}", result);
     Ok(())
 }
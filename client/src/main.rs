@@ -4,19 +4,38 @@ mod keystore;
 mod kusama;
 mod parallel;
 
-use crate::command::StakeClient;
+use crate::command::{KeystoreCmd, MultisigCmd, StakeClient};
+use crate::common::error::Error;
+use crate::common::logger;
 use structopt::StructOpt;
 
 #[async_std::main]
 async fn main() {
-    env_logger::init();
-    run().await;
+    let cmd = StakeClient::from_args();
+    logger::init(cmd.log_format());
+    run(cmd).await;
 }
 
-async fn run() {
-    match StakeClient::from_args() {
+async fn run(cmd: StakeClient) {
+    match cmd {
         StakeClient::Create(cmd) => cmd.run(),
-        StakeClient::StartPara(cmd) => cmd.run().await,
-        StakeClient::StartRelay(cmd) => cmd.run().await,
+        StakeClient::StartPara(cmd) => exit_on_error(cmd.run().await),
+        StakeClient::StartRelay(cmd) => exit_on_error(cmd.run().await),
+        StakeClient::Check(cmd) => cmd.run().await,
+        StakeClient::Keystore(KeystoreCmd::Migrate(cmd)) => cmd.run(),
+        StakeClient::Keystore(KeystoreCmd::Import(cmd)) => cmd.run(),
+        StakeClient::Keystore(KeystoreCmd::Export(cmd)) => cmd.run(),
+        StakeClient::Keystore(KeystoreCmd::Inspect(cmd)) => cmd.run(),
+        StakeClient::Keystore(KeystoreCmd::Verify(cmd)) => cmd.run(),
+        StakeClient::Keystore(KeystoreCmd::ChangePassword(cmd)) => cmd.run(),
+        StakeClient::Multisig(MultisigCmd::Derive(cmd)) => cmd.run(),
+        StakeClient::Multisig(MultisigCmd::Verify(cmd)) => cmd.run(),
+    }
+}
+
+fn exit_on_error(result: Result<(), Error>) {
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
     }
 }
@@ -1,21 +1,37 @@
+use crate::common::balance::BalanceReserve;
+use crate::common::error::Error;
+use crate::common::logger::{LogFormat, TaskMessage};
+use crate::common::metadata::{
+    ensure_compatible, para_requirements, RELAY_DEPOSIT_REQUIREMENTS, RELAY_REQUIREMENTS,
+    XTOKENS_REQUIREMENTS,
+};
+use crate::common::multisig::{check_para_pool_address, check_pool_address};
+use crate::common::origin::DispatchOrigin;
 use crate::common::primitives::{AccountId, TasksType};
-use crate::keystore::{crypto::Sr25519, wallet::get_keystore};
-use crate::parallel::{listener, tasks};
+use crate::common::profile::{build_client, ensure_para_id, ChainProfile, Network};
+use crate::common::upgrade::wait_runtime_upgrade;
+use crate::common::xcm_tracker::XcmTracker;
+use crate::keystore::{
+    error::KeystoreError,
+    password::PasswordOpt,
+    remote_signer::{Endpoint, RemoteSigner},
+    wallet::{get_keystore, warn_network_mismatch},
+};
+use crate::parallel::{listener, tasks, tasks::RelayTransfer};
 
 use async_std::sync::{Arc, Mutex};
-use frame_support::PalletId;
-use futures::join;
-use parallel_primitives::{Balance, CurrencyId, PriceWithDecimal};
-use runtime::error::Error;
-use runtime::heiko::{api::ValidatorSet, runtime::HeikoRuntime};
-use runtime::kusama::runtime::KusamaRuntime as RelayRuntime;
+use futures::{
+    future::{join, select, Either},
+    pin_mut,
+};
+use parallel_primitives::CurrencyId;
+use runtime::heiko::runtime::HeikoRuntime;
+use runtime::RelayRuntime;
 use sp_core::crypto::Ss58Codec;
 use structopt::StructOpt;
-use substrate_subxt::staking::Staking;
-use substrate_subxt::system::System;
-use substrate_subxt::{ClientBuilder, PairSigner};
-use tokio::sync::{mpsc, oneshot};
-use xcm::v0::{MultiLocation, Outcome};
+use substrate_subxt::Signer;
+use tokio::sync::mpsc;
+use tracing::info;
 
 #[derive(Debug, StructOpt)]
 pub struct StartParaCmd {
@@ -23,13 +39,17 @@ pub struct StartParaCmd {
     #[structopt(short, long, default_value = "keystore.json")]
     pub key_store: String,
 
-    /// websocket server endpoint of para chain
-    #[structopt(long, default_value = "ws://127.0.0.1:9944")]
-    pub para_ws_server: String,
+    /// the network to stake on: kusama, polkadot or dev
+    #[structopt(long, default_value = "dev")]
+    pub network: Network,
 
-    /// websocket server endpoint of relay chain
-    #[structopt(long, default_value = "ws://127.0.0.1:9955")]
-    pub relay_ws_server: String,
+    /// websocket server endpoint of para chain, defaults to the endpoint of para chain profile
+    #[structopt(long)]
+    pub para_ws_server: Option<String>,
+
+    /// websocket server endpoint of relay chain, defaults to the endpoint of relay chain profile
+    #[structopt(long)]
+    pub relay_ws_server: Option<String>,
 
     /// data base server endpoint
     #[structopt(short, long, default_value = "http://127.0.0.1:1521")]
@@ -42,160 +62,219 @@ pub struct StartParaCmd {
     )]
     pub para_pool_addr: String,
 
-    /// the password of keystore
-    #[structopt(short, long)]
-    pub password: Option<String>,
+    /// the parachain id of para chain, defaults to the parachain id of network
+    #[structopt(long)]
+    pub para_id: Option<u32>,
+
+    #[structopt(flatten)]
+    pub password: PasswordOpt,
+
+    /// sign through the remote signer instead of the key of keystore:
+    /// unix:<path>, tcp:<host:port> or http://<host:port>/<path>, the tcp and http ones are
+    /// authenticated by the token of REMOTE_SIGNER_TOKEN environment variable
+    #[structopt(long)]
+    pub remote_signer: Option<Endpoint>,
+
+    /// how the multisig account dispatches the liquid staking calls: none, sudo or
+    /// governance[:<council threshold>], the council threshold defaults to 1
+    #[structopt(long, default_value = "sudo")]
+    pub origin: DispatchOrigin,
+
+    /// transfer the withdrawn amount to the relay pool through XCM and wait for its deposit
+    #[structopt(long)]
+    pub transfer_to_relay: bool,
+
+    /// pool address of relay chain to transfer to, checked against the multisig address of keystore
+    #[structopt(long)]
+    pub relay_pool_addr: Option<String>,
 
     /// temp use to decide which account create first multi-signature transaction
     #[structopt(short, long)]
     pub first: bool,
+
+    /// the balance of staking currency the para pool keeps when withdrawing
+    #[structopt(long, default_value = "10000000000")]
+    pub balance_buffer: u128,
+
+    /// the format of logs: text or json, the level is set by RUST_LOG
+    #[structopt(long, default_value = "text")]
+    pub log_format: LogFormat,
 }
 
 impl StartParaCmd {
-    pub async fn run(&self) {
-        // get pair
-        let password: Option<String>;
-        match &self.password {
-            Some(p) => password = Some(p.to_string()),
-            None => password = rpassword::read_password_from_tty(Some("Type password:")).ok(),
-        }
+    pub async fn run(&self) -> Result<(), Error> {
+        let (para_chain, relay_chain) = (self.network.para_chain(), self.network.relay_chain());
+        para_chain.use_ss58_format();
 
         // get keystore
-        let keystore = get_keystore(self.key_store.to_string()).unwrap();
-        println!("{:?}", keystore);
+        let keystore = get_keystore(self.key_store.to_string())?;
+        info!("{:?}", keystore);
+        warn_network_mismatch(&keystore.network, self.network);
+
+        // the para pool is the account of liquid staking pallet the multisig account stakes for
+        check_para_pool_address(&self.para_pool_addr).map_err(Error::Other)?;
 
-        let pair = keystore.into_pair::<Sr25519>(password).unwrap();
+        // get signer
+        let para_signer: Box<dyn Signer<HeikoRuntime> + Send + Sync> = match &self.remote_signer {
+            Some(endpoint) => Box::new(RemoteSigner::<HeikoRuntime>::new(
+                keystore.account_id()?,
+                endpoint.transport().map_err(Error::Other)?,
+            )),
+            None => {
+                let password = self.password.read("Type password:")?;
+                keystore.into_signer::<HeikoRuntime>(password.as_ref().map(|p| p.as_str()))?
+            }
+        };
+
+        let relay_transfer = if self.transfer_to_relay {
+            let stash = keystore.multi_account_id()?;
+            // the relay pool is the multisig account of the same signatories
+            if let Some(addr) = &self.relay_pool_addr {
+                check_pool_address(addr, &stash).map_err(Error::Other)?;
+            }
+            Some(RelayTransfer {
+                stash,
+                currency_id: self.network.staking_currency(),
+                version: para_chain.xcm_version(),
+            })
+        } else {
+            None
+        };
 
         // get other signatories
-        let other_signatories = keystore.get_other_signatories().unwrap();
+        let other_signatories = keystore.get_other_signatories()?;
         let r = run(
             keystore.threshold,
-            pair,
+            &*para_signer,
             other_signatories,
-            &self.para_ws_server,
-            &self.relay_ws_server,
+            para_chain,
+            relay_chain,
+            self.para_id.unwrap_or_else(|| self.network.para_id()),
+            &para_chain.endpoint(&self.para_ws_server),
+            &relay_chain.endpoint(&self.relay_ws_server),
             &self.para_pool_addr,
             &keystore.multi_address,
-            CurrencyId::KSM,
+            self.network.staking_currency(),
+            self.origin,
             self.first,
+            relay_transfer,
+            BalanceReserve::para(self.balance_buffer),
         )
         .await;
-        println!("para chain client finished:{:?}", r);
+        info!("para chain client finished:{:?}", r);
+        r
     }
 }
 
 pub async fn run(
     threshold: u16,
-    pair: sp_core::sr25519::Pair,
+    para_signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
     others: Vec<AccountId>,
+    para_profile: ChainProfile,
+    relay_profile: ChainProfile,
+    para_id: u32,
     para_ws_server: &str,
     relay_ws_server: &str,
     pool_addr: &str,
     multi_addr: &str,
     currency_id: CurrencyId,
+    origin: DispatchOrigin,
     first: bool,
+    relay_transfer: Option<RelayTransfer>,
+    reserve: BalanceReserve,
 ) -> Result<(), Error> {
-    // initialize heiko related api
-    // todo register all unknown type
-    let para_subxt_client = ClientBuilder::<HeikoRuntime>::new()
-        .set_url(para_ws_server)
-        .register_type_size::<CurrencyId>("CurrencyIdOf<T>")
-        .register_type_size::<CurrencyId>("Currency<T>")
-        .register_type_size::<CurrencyId>("Currency")
-        .register_type_size::<Balance>("BalanceOf<T>")
-        .register_type_size::<<HeikoRuntime as System>::AccountId>("T::AccountId")
-        .register_type_size::<ValidatorSet<HeikoRuntime>>("ValidatorSet<T>")
-        .register_type_size::<CurrencyId>("T::CurrencyId")
-        .register_type_size::<Balance>("T::Balance")
-        .register_type_size::<CurrencyId>("T::OracleKey")
-        .register_type_size::<PriceWithDecimal>("T::OracleValue")
-        .register_type_size::<CurrencyId>("CurrencyId")
-        .register_type_size::<PalletId>("ParaId")
-        .register_type_size::<MultiLocation>("MultiLocation")
-        .register_type_size::<Outcome>("xcm::v0::Outcome")
-        .register_type_size::<Outcome>("Outcome")
-        .register_type_size::<([u8; 4], u64)>("MessageId")
-        .skip_type_sizes_check()
-        .build()
-        .await
-        .unwrap();
-
-    // todo register all unknown type
-    let relay_subxt_client = ClientBuilder::<RelayRuntime>::new()
-        .set_url(relay_ws_server)
-        .register_type_size::<<RelayRuntime as System>::AccountId>("T::AccountId")
-        .register_type_size::<<RelayRuntime as Staking>::CandidateReceipt>("CandidateReceipt<Hash>")
-        .register_type_size::<u32>("CoreIndex")
-        .register_type_size::<u32>("GroupIndex")
-        .register_type_size::<[u8; 4]>("ParaId")
-        .register_type_size::<MultiLocation>("MultiLocation")
-        .register_type_size::<Outcome>("xcm::v0::Outcome")
-        .register_type_size::<Outcome>("Outcome")
-        .register_type_size::<[u8; 32]>("MessageId")
-        .skip_type_sizes_check()
-        // .register_type_size::<([u8; 20])>("EthereumAddress")
-        .build()
-        .await
-        .unwrap();
-
-    let multi_account_id = AccountId::from_string(multi_addr).unwrap();
-    let pool_account_id = AccountId::from_string(pool_addr).unwrap();
-    let para_signer = PairSigner::<HeikoRuntime, sp_core::sr25519::Pair>::new(pair.clone());
-
-    // initial channel
-    let (system_rpc_tx, system_rpc_rx) = mpsc::channel::<(TasksType, oneshot::Sender<u64>)>(10);
+    let multi_account_id = AccountId::from_string(multi_addr)
+        .map_err(|_| KeystoreError::InvalidAddress(multi_addr.to_string()))?;
+    let pool_account_id = AccountId::from_string(pool_addr)
+        .map_err(|_| Error::Other(format!("invalid pool address: {}", pool_addr)))?;
 
     // todo put this to database, because this will be lost when the client restart
     let withdraw_unbonded_amount = Arc::new(Mutex::new(0));
+    let unstake_list = Arc::new(Mutex::new(vec![]));
+    let unbonded_list = Arc::new(Mutex::new(vec![]));
+    let xcm_tracker = Arc::new(Mutex::new(XcmTracker::default()));
+    // held by the dispatcher while a task is running
+    let dispatch_lock = Arc::new(Mutex::new(()));
+    // the tasks queued and the one received are kept when the clients are rebuilt
+    let (system_rpc_tx, mut system_rpc_rx) = mpsc::channel::<TaskMessage<TasksType>>(10);
+    let mut pending = None;
 
-    // initial multi threads to listen on-chain status
-    let l = listener::listener(
-        system_rpc_tx,
-        &para_subxt_client,
-        &relay_subxt_client,
-        pool_account_id.clone(),
-        currency_id.clone(),
-        withdraw_unbonded_amount.clone(),
-    );
-
-    // initial task to receive order and dive
-    let t = tasks::dispatch(
-        system_rpc_rx,
-        &para_subxt_client,
-        &relay_subxt_client,
-        &para_signer,
-        multi_account_id,
-        pool_account_id,
-        threshold,
-        others,
-        first,
-        withdraw_unbonded_amount.clone(),
-    );
-    join!(l, t);
-    Ok(())
+    loop {
+        // initialize heiko related api
+        let para_subxt_client = build_client::<HeikoRuntime>(para_profile, para_ws_server).await?;
+        ensure_compatible(&para_subxt_client, "para chain", &para_requirements(origin))?;
+        ensure_para_id(&para_subxt_client, para_id).await?;
+
+        let relay_subxt_client =
+            build_client::<RelayRuntime>(relay_profile, relay_ws_server).await?;
+        ensure_compatible(&relay_subxt_client, "relay chain", RELAY_REQUIREMENTS)?;
+        if relay_transfer.is_some() {
+            ensure_compatible(&para_subxt_client, "para chain", XTOKENS_REQUIREMENTS)?;
+            ensure_compatible(
+                &relay_subxt_client,
+                "relay chain",
+                RELAY_DEPOSIT_REQUIREMENTS,
+            )?;
+        }
+
+        // initial multi threads to listen on-chain status
+        let l = listener::listener(
+            system_rpc_tx.clone(),
+            &para_subxt_client,
+            &relay_subxt_client,
+            pool_account_id.clone(),
+            multi_account_id.clone(),
+            currency_id.clone(),
+            withdraw_unbonded_amount.clone(),
+            reserve,
+            xcm_tracker.clone(),
+        );
+
+        // initial task to receive order and dive
+        let t = tasks::dispatch(
+            &mut system_rpc_rx,
+            &mut pending,
+            &para_subxt_client,
+            &relay_subxt_client,
+            para_signer,
+            origin,
+            multi_account_id.clone(),
+            pool_account_id.clone(),
+            threshold,
+            others.clone(),
+            first,
+            relay_transfer.clone(),
+            xcm_tracker.clone(),
+            unstake_list.clone(),
+            unbonded_list.clone(),
+            dispatch_lock.clone(),
+        );
+
+        // pause the dispatcher once the runtime is upgraded, the clients will be rebuilt
+        let u = async {
+            wait_runtime_upgrade(&relay_subxt_client, &para_subxt_client).await?;
+            let _paused = dispatch_lock.lock().await;
+            info!("Dispatcher paused, rebuild clients with the new metadata");
+            Ok::<(), Error>(())
+        };
+
+        let j = join(l, t);
+        pin_mut!(j, u);
+        match select(j, u).await {
+            Either::Left(_) => return Ok(()),
+            Either::Right((r, _)) => r?,
+        }
+    }
 }
+
 #[tokio::test]
 async fn test_decode_event() -> Result<(), Error> {
     let relay_ws_server = "ws://localhost:9944";
 
     let storage_change = hex::decode("1400000000000000b814fb0a0000000002000000010000003204d007000001000000470000000000010000002c01d0070000221164d61bfc7eb4705b310ecef08194c63a06c7102d4c08bb4e1aac6e2798f6746c1ea40ff59943a337fb7204bcfd0b9acc5d150ff78ce06c670a159191ba3238b5eec10bb4d7645a3b8286bb6eddc04e0a2ffcf32c6e061528acfe775914424eb1d74c4245bcf10db6759e4a1fd646ec868bfc41b1f227e14e9191cefcd51f00ad4c4ce5ce3b524790412b63b3fd3b4d2355227257ad2c962b40ae18468df18c9f4977a24ec47c848bc12e7c404694e969d898fd49c257be74dea04fb45640232def20f8242faacceded6e1e012f5552f7fd1cdfab71864477bde31551648ce12d8c2fa64bea26b327282d0833fb4028be61a99b005d8461504bdd18f260b74b1010ccb364518bff212956c32599860b91016c3044a9a27b9d4ea9cc3a1c620a110cdabf7f6cd59e8010c9cae4b24c3b26f7775a8679e1ff454d8cdb9209f8dd0268f6b8ec5ab3aee90494b740ad82e07c836c2bd7f9ce626908e7fdc56d1f0e6020db70e42ed6f4f6e21d253e2f509c6825a81c918d024a2f8f3fea585ac7eb5bc6d2b83418099a7a8da309a6d1df7f6716aeee1cfd26234115e4bbef09d5e0bf68080661757261202bcd1508000000000561757261010106db3098bcc504e5e5172465c0d99ee1a5cb95bc66be5b4737139d6b8ebf5d6aeb92cfe9be8d8aab7aae3247d0e44ef6367164ca69edc367b2cf711302fef48300000000000000000000010000003202c17c0f4e580dbc0ae5b37fbb1d42486cc4b65cfe00cd5870384ecbaed6dfb1bd0180c3c9010000000010000001000000000080b2e60e00000000020000").unwrap();
 
-    let relay_subxt_client = ClientBuilder::<RelayRuntime>::new()
-        .set_url(relay_ws_server)
-        .register_type_size::<<RelayRuntime as System>::AccountId>("T::AccountId")
-        .register_type_size::<<RelayRuntime as Staking>::CandidateReceipt>("CandidateReceipt<Hash>")
-        .register_type_size::<u32>("CoreIndex")
-        .register_type_size::<u32>("GroupIndex")
-        .register_type_size::<[u8; 4]>("ParaId")
-        .register_type_size::<MultiLocation>("MultiLocation")
-        .register_type_size::<Outcome>("xcm::v0::Outcome")
-        .register_type_size::<Outcome>("Outcome")
-        .register_type_size::<[u8; 32]>("MessageId")
-        .skip_type_sizes_check()
-        // .register_type_size::<([u8; 20])>("EthereumAddress")
-        .build()
-        .await
-        .unwrap();
+    let relay_subxt_client =
+        build_client::<RelayRuntime>(ChainProfile::Rococo, relay_ws_server).await?;
 
     let decoder = relay_subxt_client.events_decoder();
     // decoder.decode_events(&mut storage_change.clone().as_bytes())?;
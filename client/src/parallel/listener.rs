@@ -1,4 +1,11 @@
+use crate::common::anchor::{finalized_anchor, BlockEvents, LISTEN_ANCHOR_INTERVAL};
+use crate::common::balance::BalanceReserve;
+use crate::common::logger::TaskMessage;
 use crate::common::primitives::{AccountId, TasksType, MAX_WITHDRAW_BALANCE, MIN_WITHDRAW_BALANCE};
+use crate::common::profile::ChainKind;
+use crate::common::xcm_tracker::{
+    check_transfers, listen_para_deposit, listen_relay_deposit, TransferStatus, XcmTracker,
+};
 pub use parallel_primitives::CurrencyId;
 
 use async_std::{
@@ -7,24 +14,28 @@ use async_std::{
 };
 use futures::join;
 use runtime::heiko::{self, runtime::HeikoRuntime};
-use runtime::kusama::runtime::KusamaRuntime as RelayRuntime;
 use runtime::pallets::liquid_staking::UnstakedEvent;
 use runtime::pallets::staking::{UnbondedEvent, WithdrawnEvent};
+use runtime::RelayRuntime;
 use sp_core::Decode;
 use std::time;
 use substrate_subxt::{Client, EventSubscription, RawEvent};
 use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info};
 
 const LISTEN_INTERVAL: u64 = 5; // 5 sec
 pub const LISTEN_WAIT_INTERVAL: u64 = 30; // 30 sec
 
 pub async fn listener(
-    system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     para_subxt_client: &Client<HeikoRuntime>,
     relay_subxt_client: &Client<RelayRuntime>,
     pool_account_id: AccountId,
+    relay_stash: AccountId,
     currency_id: CurrencyId,
     withdraw_unbonded_amount: Arc<Mutex<u128>>,
+    reserve: BalanceReserve,
+    xcm_tracker: Arc<Mutex<XcmTracker>>,
 ) {
     let l1 = listen_pool_balance(
         system_rpc_tx.clone(),
@@ -32,48 +43,72 @@ pub async fn listener(
         pool_account_id.clone(),
         currency_id.clone(),
         withdraw_unbonded_amount.clone(),
+        reserve,
     );
     let l2 = listen_unstaked_event(system_rpc_tx.clone(), para_subxt_client);
     let l3 = listen_unbonded_event(system_rpc_tx.clone(), relay_subxt_client);
     let l4 = listen_withdraw_unbonded_event(
         system_rpc_tx.clone(),
         relay_subxt_client,
+        pool_account_id.clone(),
+        relay_stash,
         withdraw_unbonded_amount.clone(),
+        xcm_tracker.clone(),
     );
-    join!(l1, l2, l3, l4);
+    // reconcile the transfers between the chains with their deposits
+    let l5 = listen_para_deposit(para_subxt_client, currency_id, xcm_tracker.clone());
+    let l6 = listen_relay_deposit(relay_subxt_client, xcm_tracker.clone());
+    let l7 = check_transfers(xcm_tracker.clone());
+    join!(l1, l2, l3, l4, l5, l6, l7);
 }
 
 /// listen to the balance change of pool
 pub(crate) async fn listen_pool_balance(
-    system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     para_subxt_client: &Client<HeikoRuntime>,
     pool_account_id: AccountId,
     currency_id: CurrencyId,
     withdraw_unbonded_amount: Arc<Mutex<u128>>,
+    reserve: BalanceReserve,
 ) {
     let store = heiko::api::AccountsStore::<HeikoRuntime> {
         account: pool_account_id,
         currency_id,
     };
     loop {
-        match para_subxt_client.fetch(&store, None).await {
+        // decide at the finalized anchor block, the amount is derived from the balance there
+        let at = match finalized_anchor(para_subxt_client, LISTEN_ANCHOR_INTERVAL).await {
+            Ok(at) => at,
+            Err(e) => {
+                error!("listen_pool_balance error: {:?}", e);
+                task::sleep(time::Duration::from_secs(LISTEN_INTERVAL)).await;
+                continue;
+            }
+        };
+        match para_subxt_client.fetch(&store, Some(at)).await {
             Ok(r) => {
                 if let Some(account_info) = r {
-                    let balance = account_info.free - account_info.frozen;
-                    if balance >= MIN_WITHDRAW_BALANCE + *withdraw_unbonded_amount.lock().await {
-                        println!("[+] Pool's amount is {:?}， need to withdraw", balance);
+                    let balance = reserve.spendable(account_info.free, account_info.frozen, 0);
+                    // the amount is derived from storage alone, no stake is decided while the
+                    // withdrawn amount is waiting to be transferred
+                    let wa = *withdraw_unbonded_amount.lock().await;
+                    if wa > 0 {
+                        info!("withdrawn {} waiting to be transferred, stake deferred", wa);
+                    } else if balance >= MIN_WITHDRAW_BALANCE {
+                        info!("Pool's amount is {:?}， need to withdraw", balance);
                         let (resp_tx, resp_rx) = oneshot::channel();
-                        let wa = *withdraw_unbonded_amount.lock().await;
-                        if balance - wa < MAX_WITHDRAW_BALANCE {
+                        if balance < MAX_WITHDRAW_BALANCE {
                             system_rpc_tx
                                 .clone()
-                                .try_send((TasksType::ParaStake(balance - wa), resp_tx))
+                                .try_send(TasksType::ParaStake(balance, at).message(resp_tx))
                                 .ok();
                             let _res = resp_rx.await.ok();
                         } else {
                             system_rpc_tx
                                 .clone()
-                                .try_send((TasksType::ParaStake(MAX_WITHDRAW_BALANCE), resp_tx))
+                                .try_send(
+                                    TasksType::ParaStake(MAX_WITHDRAW_BALANCE, at).message(resp_tx),
+                                )
                                 .ok();
                             let _res = resp_rx.await.ok();
                         }
@@ -82,7 +117,7 @@ pub(crate) async fn listen_pool_balance(
                 }
             }
             Err(e) => {
-                println!("listen_pool_balance error: {:?}", e);
+                error!("listen_pool_balance error: {:?}", e);
             }
         }
         task::sleep(time::Duration::from_secs(LISTEN_INTERVAL)).await;
@@ -91,7 +126,7 @@ pub(crate) async fn listen_pool_balance(
 
 /// listen to the unstaked event
 async fn listen_unstaked_event(
-    mut system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    mut system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     para_subxt_client: &Client<HeikoRuntime>,
 ) {
     let sub = para_subxt_client
@@ -106,17 +141,17 @@ async fn listen_unstaked_event(
             .next()
             .await
             .and_then(|result_raw| -> Option<RawEvent> {
-                println!("RawEvent:{:?}", result_raw);
+                debug!("RawEvent:{:?}", result_raw);
                 result_raw.ok()
             })
             .and_then(|raw| -> Option<UnstakedEvent<HeikoRuntime>> {
                 UnstakedEvent::<HeikoRuntime>::decode(&mut &raw.data[..]).ok()
             }) {
             Some(event) => {
-                println!("[+] Received Unstaked event: {:?}", &event);
+                info!("Received Unstaked event: {:?}", &event);
                 let (resp_tx, resp_rx) = oneshot::channel();
                 system_rpc_tx
-                    .try_send((TasksType::ParaUnstake(event.account, event.amount), resp_tx))
+                    .try_send(TasksType::ParaUnstake(event.account, event.amount).message(resp_tx))
                     .ok();
                 let _res = resp_rx.await.ok();
             }
@@ -127,7 +162,7 @@ async fn listen_unstaked_event(
 
 /// listen to the unbonded event
 async fn listen_unbonded_event(
-    mut system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    mut system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     relay_subxt_client: &Client<RelayRuntime>,
 ) {
     let sub = relay_subxt_client
@@ -135,27 +170,28 @@ async fn listen_unbonded_event(
         .await
         .unwrap();
     let decoder = relay_subxt_client.events_decoder();
-    let mut sub = EventSubscription::<RelayRuntime>::new(sub, &decoder);
-    sub.filter_event::<UnbondedEvent<RelayRuntime>>();
+    let mut sub = BlockEvents::new::<UnbondedEvent<RelayRuntime>>(sub, &decoder);
     loop {
+        // handled at the block containing the event
         match sub
             .next()
             .await
-            .and_then(|result_raw| -> Option<RawEvent> {
-                println!("RawEvent:{:?}", result_raw);
+            .and_then(|result_raw| {
+                debug!("RawEvent:{:?}", result_raw);
                 result_raw.ok()
             })
-            .and_then(|raw| -> Option<UnbondedEvent<RelayRuntime>> {
-                UnbondedEvent::<RelayRuntime>::decode(&mut &raw.data[..]).ok()
+            .and_then(|(at, raw)| {
+                UnbondedEvent::<RelayRuntime>::decode(&mut &raw.data[..])
+                    .ok()
+                    .map(|event| (at, event))
             }) {
-            Some(event) => {
-                println!("[+] Received Unbonded event: {:?}", &event);
+            Some((at, event)) => {
+                info!("Received Unbonded event: {:?}", &event);
                 let (resp_tx, resp_rx) = oneshot::channel();
                 system_rpc_tx
-                    .try_send((
-                        TasksType::RelayUnbonded(event.account, event.amount),
-                        resp_tx,
-                    ))
+                    .try_send(
+                        TasksType::RelayUnbonded(event.account, event.amount, at).message(resp_tx),
+                    )
                     .ok();
                 let _res = resp_rx.await.ok();
             }
@@ -164,11 +200,15 @@ async fn listen_unbonded_event(
     }
 }
 
-/// listen to the withdraw unbonded event
+/// listen to the withdraw unbonded event of the relay stash, the task is dispatched once the
+/// amount is deposited to the pool and dropped if the transfer failed
 async fn listen_withdraw_unbonded_event(
-    mut system_rpc_tx: mpsc::Sender<(TasksType, oneshot::Sender<u64>)>,
+    system_rpc_tx: mpsc::Sender<TaskMessage<TasksType>>,
     relay_subxt_client: &Client<RelayRuntime>,
+    pool_account_id: AccountId,
+    relay_stash: AccountId,
     withdraw_unbonded_amount: Arc<Mutex<u128>>,
+    xcm_tracker: Arc<Mutex<XcmTracker>>,
 ) {
     let sub = relay_subxt_client
         .subscribe_finalized_events()
@@ -182,29 +222,59 @@ async fn listen_withdraw_unbonded_event(
             .next()
             .await
             .and_then(|result_raw| -> Option<RawEvent> {
-                println!("RawEvent:{:?}", result_raw);
+                debug!("RawEvent:{:?}", result_raw);
                 result_raw.ok()
             })
             .and_then(|raw| -> Option<WithdrawnEvent<RelayRuntime>> {
                 WithdrawnEvent::<RelayRuntime>::decode(&mut &raw.data[..]).ok()
             }) {
-            Some(event) => {
-                println!("[+] Received Withdrawn event: {:?}", &event);
-                let (resp_tx, resp_rx) = oneshot::channel();
+            // the withdrawals of other stakers are never transferred to the pool
+            Some(event) if event.account == relay_stash => {
+                info!("Received Withdrawn event: {:?}", &event);
                 *withdraw_unbonded_amount.lock().await += event.amount;
 
-                //todo need to wait until asset has been transfered to pool address.
-                // task::sleep(time::Duration::from_secs(LISTEN_WAIT_INTERVAL)).await;
-
-                system_rpc_tx
-                    .try_send((
-                        TasksType::RelayWithdrawUnbonded(event.account, event.amount),
-                        resp_tx,
-                    ))
-                    .ok();
-                let _res = resp_rx.await.ok();
+                // the relay client transfers the withdrawn amount to pool, wait for its deposit
+                // without blocking the following events
+                let delivered = {
+                    let mut xcm_tracker = xcm_tracker.lock().await;
+                    let id = xcm_tracker.record(
+                        ChainKind::Para,
+                        pool_account_id.clone(),
+                        event.amount,
+                        None,
+                    );
+                    xcm_tracker.wait(id)
+                };
+                let mut system_rpc_tx = system_rpc_tx.clone();
+                let withdraw_unbonded_amount = withdraw_unbonded_amount.clone();
+                task::spawn(async move {
+                    // the amount is in the pool balance or never will be, staking goes on
+                    let status = delivered.await;
+                    {
+                        let mut withdrawn = withdraw_unbonded_amount.lock().await;
+                        *withdrawn = withdrawn.saturating_sub(event.amount);
+                    }
+                    match status {
+                        Ok(TransferStatus::Delivered) => {
+                            let (resp_tx, resp_rx) = oneshot::channel();
+                            system_rpc_tx
+                                .try_send(
+                                    TasksType::RelayWithdrawUnbonded(event.account, event.amount)
+                                        .message(resp_tx),
+                                )
+                                .ok();
+                            let _res = resp_rx.await.ok();
+                        }
+                        status => {
+                            error!(
+                                "withdrawn {} is not deposited to pool: {:?}, task dropped",
+                                event.amount, status
+                            );
+                        }
+                    }
+                });
             }
-            None => {}
+            Some(_) | None => {}
         }
     }
 }
@@ -1,154 +1,235 @@
 use super::transactions::{
-    do_first_finish_processed_unstake, do_first_process_pending_unstake, do_first_withdraw,
-    do_last_finish_processed_unstake, do_last_process_pending_unstake, do_last_withdraw,
+    do_first_finish_processed_unstake, do_first_process_pending_unstake,
+    do_first_transfer_to_relay_chain, do_first_withdraw, do_last_finish_processed_unstake,
+    do_last_process_pending_unstake, do_last_transfer_to_relay_chain, do_last_withdraw,
     wait_transfer_finished,
 };
-use crate::common::primitives::{AccountId, Amount, TasksType};
+use crate::common::logger::TaskMessage;
+use crate::common::origin::DispatchOrigin;
+use crate::common::primitives::{AccountId, Amount, TasksType, XCM_RELAY_DEST_WEIGHT};
+use crate::common::profile::ChainKind;
+use crate::common::xcm_tracker::{TransferStatus, XcmTracker};
 
-use async_std::sync::{Arc, Mutex};
+use async_std::{
+    sync::{Arc, Mutex},
+    task,
+};
 use core::marker::PhantomData;
 
+use parallel_primitives::CurrencyId;
 use runtime::error::Error;
-use runtime::heiko::runtime::HeikoRuntime;
-use runtime::kusama::{self, runtime::KusamaRuntime as RelayRuntime};
+use runtime::heiko::{self, api::XcmVersion, runtime::HeikoRuntime};
+use runtime::{kusama, RelayRuntime};
+use sp_core::H256;
 
-use substrate_subxt::Client;
+use substrate_subxt::{Client, Encoded};
 use substrate_subxt::{Error as SubError, Signer};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn, Instrument};
+
+/// Where the withdrawn amount of para chain is transferred to on relay chain.
+#[derive(Clone, Debug)]
+pub struct RelayTransfer {
+    /// The stash of relay chain to bond the amount.
+    pub stash: AccountId,
+    pub currency_id: CurrencyId,
+    /// The XCM version of para chain.
+    pub version: XcmVersion,
+}
+
+impl RelayTransfer {
+    /// The `orml_xtokens` transfer of amount to the stash, encoded for the multisig.
+    pub fn call(
+        &self,
+        subxt_client: &Client<HeikoRuntime>,
+        amount: Amount,
+    ) -> Result<Encoded, Error> {
+        heiko::api::xtokens_transfer_to_relay_chain_call::<HeikoRuntime>(
+            subxt_client,
+            self.version,
+            self.currency_id,
+            amount,
+            self.stash.clone().into(),
+            XCM_RELAY_DEST_WEIGHT as u64,
+        )
+    }
+}
 
 pub async fn dispatch(
-    mut system_rpc_rx: mpsc::Receiver<(TasksType, oneshot::Sender<u64>)>,
+    system_rpc_rx: &mut mpsc::Receiver<TaskMessage<TasksType>>,
+    pending: &mut Option<TaskMessage<TasksType>>,
     para_subxt_client: &Client<HeikoRuntime>,
     relay_subxt_client: &Client<RelayRuntime>,
     para_signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     multi_account_id: AccountId,
     pool_account_id: AccountId,
     threshold: u16,
     others: Vec<AccountId>,
     first: bool,
-    withdraw_unbonded_amount: Arc<Mutex<u128>>,
+    relay_transfer: Option<RelayTransfer>,
+    xcm_tracker: Arc<Mutex<XcmTracker>>,
+    unstake_list: Arc<Mutex<Vec<(AccountId, Amount)>>>,
+    unbonded_list: Arc<Mutex<Vec<(AccountId, Amount)>>>,
+    dispatch_lock: Arc<Mutex<()>>,
 ) {
-    // todo put this to database, because this will be lost when the client restart
-    let mut unstake_list: Vec<(AccountId, Amount)> = vec![];
-    let mut unbonded_list: Vec<(AccountId, Amount)> = vec![];
     loop {
-        match system_rpc_rx.recv().await {
-            Some((task_type, response)) => match task_type {
-                TasksType::ParaStake(amount) => {
-                    println!("[+] Start withdraw task");
-                    let _ = start_withdraw_task_para(
-                        &para_subxt_client,
-                        para_signer,
-                        multi_account_id.clone(),
-                        threshold.clone(),
-                        others.clone(),
-                        amount.clone(),
-                        first.clone(),
-                    )
-                    .await
-                    .map_err(|e| println!("error start_withdraw_task_para: {:?}", e));
-                    response.send(0).unwrap();
-                }
-                TasksType::ParaUnstake(owner, amount) => {
-                    println!("[+] Start ParaUnstake task");
-                    unstake_list.push((owner, amount));
-                    response.send(0).unwrap();
-                }
-                TasksType::RelayUnbonded(agent, amount) => {
-                    println!("[+] Start process pending unstake task");
-                    let mut index = 0;
-                    let mut found = false;
-                    for (owner, a) in unstake_list.clone().into_iter() {
-                        if amount == a {
-                            found = true;
+        // the task is kept pending until it runs, so it survives the dispatcher being dropped
+        // when the clients are rebuilt, the listener waiting for its response may be gone then
+        if pending.is_none() {
+            *pending = system_rpc_rx.recv().await;
+        }
+        // hold the lock until the task finished, the dispatcher is paused while it is taken
+        let _running = dispatch_lock.lock().await;
+        let task = pending.take();
+        let mut unstake_list = unstake_list.lock().await;
+        let mut unbonded_list = unbonded_list.lock().await;
+        match task {
+            // the task and the calls it submits are logged in its span
+            Some((task_type, span, response)) => {
+                async {
+                    match task_type {
+                        TasksType::ParaStake(amount, at) => {
+                            info!("Start withdraw task, amount decided at {:?}", at);
+                            let withdrawn = start_withdraw_task_para(
+                                &para_subxt_client,
+                                para_signer,
+                                origin,
+                                multi_account_id.clone(),
+                                threshold.clone(),
+                                others.clone(),
+                                amount.clone(),
+                                first.clone(),
+                            )
+                            .await
+                            .map_err(|e| warn!("error start_withdraw_task_para: {:?}", e));
+                            // nothing to transfer if the withdraw failed
+                            if let (Ok(_), Some(relay_transfer)) = (withdrawn, &relay_transfer) {
+                                info!("Start transfer to relay chain task");
+                                let _ = start_transfer_to_relay_task_para(
+                                    &para_subxt_client,
+                                    para_signer,
+                                    relay_transfer,
+                                    &xcm_tracker,
+                                    multi_account_id.clone(),
+                                    threshold.clone(),
+                                    others.clone(),
+                                    amount.clone(),
+                                    first.clone(),
+                                )
+                                .await
+                                .map_err(|e| {
+                                    warn!("error start_transfer_to_relay_task_para: {:?}", e)
+                                });
+                            }
+                            response.send(0).ok();
+                        }
+                        TasksType::ParaUnstake(owner, amount) => {
+                            info!("Start ParaUnstake task");
+                            unstake_list.push((owner, amount));
+                            response.send(0).ok();
+                        }
+                        TasksType::RelayUnbonded(agent, amount, at) => {
+                            info!("Start process pending unstake task");
+                            let mut index = 0;
+                            let mut found = false;
+                            for (owner, a) in unstake_list.clone().into_iter() {
+                                if amount == a {
+                                    found = true;
 
-                            // get era_index
-                            match get_era_index(relay_subxt_client).await {
-                                Ok(era) => {
-                                    let _ = start_process_pending_unstake_task_para(
-                                        &para_subxt_client,
-                                        para_signer,
-                                        multi_account_id.clone(),
-                                        threshold.clone(),
-                                        others.clone(),
-                                        agent.clone(),
-                                        owner.clone(),
-                                        era.clone(),
-                                        amount.clone(),
-                                        first.clone(),
-                                    )
-                                    .await
-                                    .map_err(|e| {
-                                        println!("process pending unstake task error: {:?}", e)
-                                    });
-                                    unbonded_list.push((owner, amount));
-                                    break;
-                                }
-                                Err(e) => {
-                                    println!("fetch CurrentEraStore error : {:?}", e);
-                                    break;
+                                    // get era_index
+                                    match get_era_index(relay_subxt_client, at).await {
+                                        Ok(era) => {
+                                            let _ = start_process_pending_unstake_task_para(
+                                                &para_subxt_client,
+                                                para_signer,
+                                                origin,
+                                                multi_account_id.clone(),
+                                                threshold.clone(),
+                                                others.clone(),
+                                                agent.clone(),
+                                                owner.clone(),
+                                                era.clone(),
+                                                amount.clone(),
+                                                first.clone(),
+                                            )
+                                            .await
+                                            .map_err(|e| {
+                                                warn!("process pending unstake task error: {:?}", e)
+                                            });
+                                            unbonded_list.push((owner, amount));
+                                            break;
+                                        }
+                                        Err(e) => {
+                                            warn!("fetch CurrentEraStore error : {:?}", e);
+                                            break;
+                                        }
+                                    }
                                 }
+                                index = index + 1
                             }
+                            if found {
+                                unstake_list.remove(index);
+                            }
+                            response.send(0).ok();
                         }
-                        index = index + 1
-                    }
-                    if found {
-                        unstake_list.remove(index);
-                    }
-                    response.send(0).unwrap();
-                }
-                TasksType::RelayWithdrawUnbonded(agent, mut amount) => {
-                    println!("[+] Start finish processed unstake task");
-                    let mut count = 0;
-                    for (owner, a) in unbonded_list.clone().into_iter() {
-                        if amount < a {
-                            break;
-                        }
+                        TasksType::RelayWithdrawUnbonded(agent, mut amount) => {
+                            info!("Start finish processed unstake task");
+                            let mut count = 0;
+                            for (owner, a) in unbonded_list.clone().into_iter() {
+                                if amount < a {
+                                    break;
+                                }
 
-                        match start_finish_processed_unstake_task_para(
-                            &para_subxt_client,
-                            para_signer,
-                            multi_account_id.clone(),
-                            pool_account_id.clone(),
-                            threshold.clone(),
-                            others.clone(),
-                            agent.clone(),
-                            owner.clone(),
-                            amount.clone(),
-                            first.clone(),
-                        )
-                        .await
-                        {
-                            Ok(_result) => {
-                                println!(" finish processed unstake succeed");
-                                amount -= a;
-                                count = count + 1;
-                                *withdraw_unbonded_amount.lock().await -= a;
+                                match start_finish_processed_unstake_task_para(
+                                    &para_subxt_client,
+                                    para_signer,
+                                    origin,
+                                    multi_account_id.clone(),
+                                    pool_account_id.clone(),
+                                    threshold.clone(),
+                                    others.clone(),
+                                    agent.clone(),
+                                    owner.clone(),
+                                    amount.clone(),
+                                    first.clone(),
+                                )
+                                .await
+                                {
+                                    Ok(_result) => {
+                                        info!("finish processed unstake succeed");
+                                        amount -= a;
+                                        count = count + 1;
+                                    }
+                                    Err(e) => {
+                                        warn!("finish processed unstake task error: {:?}", e);
+                                        break;
+                                    }
+                                };
                             }
-                            Err(e) => {
-                                println!("finish processed unstake task error: {:?}", e);
-                                break;
+                            if count != 0 {
+                                for i in (count - 1)..0 {
+                                    unbonded_list.remove(i);
+                                }
                             }
-                        };
-                    }
-                    if count != 0 {
-                        for i in (count - 1)..0 {
-                            unbonded_list.remove(i);
+                            response.send(0).ok();
                         }
                     }
-                    response.send(0).unwrap();
                 }
-            },
-            None => println!("dispatch pending..."),
+                .instrument(span)
+                .await
+            }
+            None => info!("dispatch pending..."),
         }
     }
 }
 
-async fn get_era_index(relay_subxt_client: &Client<RelayRuntime>) -> Result<u32, Error> {
+/// The current era of relay chain at block `at`.
+async fn get_era_index(relay_subxt_client: &Client<RelayRuntime>, at: H256) -> Result<u32, Error> {
     let store = kusama::api::CurrentEraStore::<RelayRuntime> {
         _runtime: PhantomData,
     };
-    match relay_subxt_client.fetch(&store, None).await {
+    match relay_subxt_client.fetch(&store, Some(at)).await {
         Ok(era) => {
             if let Some(era_index) = era {
                 Ok(era_index)
@@ -168,6 +249,7 @@ async fn get_era_index(relay_subxt_client: &Client<RelayRuntime>) -> Result<u32,
 pub(crate) async fn start_withdraw_task_para(
     para_subxt_client: &Client<HeikoRuntime>,
     para_signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     multi_account_id: AccountId,
     threshold: u16,
     others: Vec<AccountId>,
@@ -179,31 +261,89 @@ pub(crate) async fn start_withdraw_task_para(
             others.clone(),
             &para_subxt_client,
             para_signer,
+            origin,
             multi_account_id.clone(),
             amount.clone(),
             threshold.clone(),
         )
         .await?;
-        println!("[+] Create withdraw transaction finished");
+        info!("Create withdraw transaction finished");
     } else {
         let _call_hash = do_last_withdraw(
             others.clone(),
             multi_account_id.clone(),
             &para_subxt_client,
             para_signer,
+            origin,
             amount.clone(),
             threshold.clone(),
         )
         .await?;
-        println!("[+] Create withdraw transaction finished");
+        info!("Create withdraw transaction finished");
     }
     Ok(())
 }
 
+/// start transfer to relay chain task, the deposit to the stash is waited for by the tracker
+pub(crate) async fn start_transfer_to_relay_task_para(
+    para_subxt_client: &Client<HeikoRuntime>,
+    para_signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    relay_transfer: &RelayTransfer,
+    xcm_tracker: &Arc<Mutex<XcmTracker>>,
+    multi_account_id: AccountId,
+    threshold: u16,
+    others: Vec<AccountId>,
+    amount: Amount,
+    first: bool,
+) -> Result<(), Error> {
+    let call_hash = if first {
+        do_first_transfer_to_relay_chain(
+            others.clone(),
+            &para_subxt_client,
+            para_signer,
+            relay_transfer,
+            amount.clone(),
+            threshold.clone(),
+        )
+        .await?
+    } else {
+        do_last_transfer_to_relay_chain(
+            others.clone(),
+            multi_account_id.clone(),
+            &para_subxt_client,
+            para_signer,
+            relay_transfer,
+            amount.clone(),
+            threshold.clone(),
+        )
+        .await?
+    };
+    let _ = wait_transfer_finished(&para_subxt_client, multi_account_id.clone(), call_hash).await?;
+    info!("Create transfer to relay chain transaction finished");
+
+    let deposited = {
+        let mut xcm_tracker = xcm_tracker.lock().await;
+        let id = xcm_tracker.record(ChainKind::Relay, relay_transfer.stash.clone(), amount, None);
+        xcm_tracker.wait(id)
+    };
+    // the dispatcher goes on, the deposit is reported once the tracker settles the transfer
+    task::spawn(
+        async move {
+            match deposited.await {
+                Ok(TransferStatus::Delivered) => info!("transfer to relay chain deposited"),
+                status => error!("transfer to relay chain not deposited: {:?}", status),
+            }
+        }
+        .in_current_span(),
+    );
+    Ok(())
+}
+
 /// start process_pending_unstake task
 pub(crate) async fn start_process_pending_unstake_task_para(
     para_subxt_client: &Client<HeikoRuntime>,
     para_signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     multi_account_id: AccountId,
     threshold: u16,
     others: Vec<AccountId>,
@@ -218,6 +358,7 @@ pub(crate) async fn start_process_pending_unstake_task_para(
             others.clone(),
             &para_subxt_client,
             para_signer,
+            origin,
             agent,
             owner,
             era_index.clone(),
@@ -227,13 +368,14 @@ pub(crate) async fn start_process_pending_unstake_task_para(
         .await?;
         let _ =
             wait_transfer_finished(&para_subxt_client, multi_account_id.clone(), call_hash).await?;
-        println!("[+] Create process pending unstake transaction finished");
+        info!("Create process pending unstake transaction finished");
     } else {
         let call_hash = do_last_process_pending_unstake(
             others.clone(),
             multi_account_id.clone(),
             &para_subxt_client,
             para_signer,
+            origin,
             agent,
             owner,
             era_index.clone(),
@@ -243,7 +385,7 @@ pub(crate) async fn start_process_pending_unstake_task_para(
         .await?;
         let _ =
             wait_transfer_finished(&para_subxt_client, multi_account_id.clone(), call_hash).await?;
-        println!("[+] Create process pending unstake transaction finished");
+        info!("Create process pending unstake transaction finished");
     }
     Ok(())
 }
@@ -252,6 +394,7 @@ pub(crate) async fn start_process_pending_unstake_task_para(
 pub(crate) async fn start_finish_processed_unstake_task_para(
     para_subxt_client: &Client<HeikoRuntime>,
     para_signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     multi_account_id: AccountId,
     _pool_account_id: AccountId,
     threshold: u16,
@@ -266,6 +409,7 @@ pub(crate) async fn start_finish_processed_unstake_task_para(
             others.clone(),
             &para_subxt_client,
             para_signer,
+            origin,
             agent,
             owner,
             amount.clone(),
@@ -274,13 +418,14 @@ pub(crate) async fn start_finish_processed_unstake_task_para(
         .await?;
         let _ =
             wait_transfer_finished(&para_subxt_client, multi_account_id.clone(), call_hash).await?;
-        println!("[+] Create finish processed unstake transaction finished");
+        info!("Create finish processed unstake transaction finished");
     } else {
         let call_hash = do_last_finish_processed_unstake(
             others.clone(),
             multi_account_id.clone(),
             &para_subxt_client,
             para_signer,
+            origin,
             agent,
             owner,
             amount.clone(),
@@ -289,7 +434,7 @@ pub(crate) async fn start_finish_processed_unstake_task_para(
         .await?;
         let _ =
             wait_transfer_finished(&para_subxt_client, multi_account_id.clone(), call_hash).await?;
-        println!("[+] Create finish processed unstake transaction finished");
+        info!("Create finish processed unstake transaction finished");
     }
     Ok(())
 }
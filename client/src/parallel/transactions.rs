@@ -1,5 +1,7 @@
+use super::tasks::RelayTransfer;
+use crate::common::logger::record_call_hash;
+use crate::common::origin::DispatchOrigin;
 use crate::common::primitives::{AccountId, Amount};
-use core::marker::PhantomData;
 
 use runtime::error::Error;
 use runtime::heiko::{self, runtime::HeikoRuntime};
@@ -7,51 +9,38 @@ use runtime::kusama::{self};
 use runtime::pallets::multisig::Multisig;
 
 use std::{thread, time};
-use substrate_subxt::{sudo, Client, Signer};
-use substrate_subxt::{Error as SubError, Runtime};
+use substrate_subxt::{Client, Runtime, Signer};
+use tracing::{info, warn};
 
 /// The first wallet to call withdraw. No need use 'TimePoint' and call 'approve_as_multi'.
 pub(crate) async fn do_first_withdraw(
     others: Vec<AccountId>,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     multi_account_id: AccountId,
     amount: Amount,
     threshold: u16,
 ) -> Result<[u8; 32], Error> {
-    println!("[+] Create first withdraw transaction");
+    info!("Create first withdraw transaction");
 
-    println!("---------- start create multi-signature transaction ----------");
     // 1.1 construct balance transfer call
     // let dest = AccountKeyring::Eve.to_account_id().into();
     let inner_call =
         heiko::api::liquid_staking_withdraw_call::<HeikoRuntime>(multi_account_id, amount);
-    let inner_call_encoded = subxt_client
-        .encode(inner_call)
-        .map_err(|e| Error::SubxtError(e))?;
-    let sudo_call = sudo::SudoCall::<HeikoRuntime> {
-        _runtime: PhantomData,
-        call: &inner_call_encoded,
-    };
-
-    let mc =
-        heiko::api::multisig_approve_as_multi_call::<HeikoRuntime, sudo::SudoCall<HeikoRuntime>>(
-            subxt_client,
-            threshold,
-            others,
-            None,
-            sudo_call.clone(),
-            0u64,
-        )?;
+    let call = origin.wrap(subxt_client, inner_call)?;
+
+    let mc = heiko::api::multisig_approve_as_multi_encoded_call::<HeikoRuntime>(
+        threshold, others, None, &call, 0u64,
+    );
 
     // 1.2 initial the multisg call
     let result = subxt_client.watch(mc, signer).await?;
-    println!("multisig_approve_as_multi_call result {:?}", result);
+    info!("multisig_approve_as_multi_call result {:?}", result);
 
     // get account_id of multi address
-    let call_hash = kusama::api::multisig_call_hash(subxt_client, sudo_call)?;
-    println!("call hash {:?}", format!("0x{}", hex::encode(call_hash)));
-    println!("---------- end create multi-signature transaction ----------");
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
     Ok(call_hash)
 }
 
@@ -67,50 +56,40 @@ pub(crate) async fn do_last_withdraw(
     multi_account_id: AccountId,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     amount: Amount,
     threshold: u16,
 ) -> Result<[u8; 32], Error> {
-    println!("[+] Crate last withdraw transaction");
-    println!("---------- start create multi-signature transaction ----------");
+    info!("Create last withdraw transaction");
     // 1.1 construct balance transfer call
     // let dest = AccountKeyring::Eve.to_account_id().into();
     let inner_call =
         heiko::api::liquid_staking_withdraw_call::<HeikoRuntime>(multi_account_id.clone(), amount);
 
-    let inner_call_encoded = subxt_client
-        .encode(inner_call)
-        .map_err(|e| Error::SubxtError(e))?;
-    let sudo_call = sudo::SudoCall::<HeikoRuntime> {
-        _runtime: PhantomData,
-        call: &inner_call_encoded,
-    };
+    let call = origin.wrap(subxt_client, inner_call)?;
 
     // check if timepoint already exist.
-    let call_hash =
-        heiko::api::multisig_call_hash(subxt_client, sudo_call.clone()).map_err(|_e| {
-            Error::SubxtError(SubError::Other("failed to load get call hash".to_string()))
-        })?;
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
 
     // let when = get_time_point::<HeikoRuntime>(subxt_client, account_id.clone(), call_hash).await;
     let when =
         get_last_time_point::<HeikoRuntime>(subxt_client, multi_account_id.clone(), call_hash)
             .await;
-    println!("multisig timepoint{:?}", when);
+    info!("multisig timepoint{:?}", when);
 
-    let mc = kusama::api::multisig_as_multi_call::<HeikoRuntime, sudo::SudoCall<HeikoRuntime>>(
-        subxt_client,
+    let mc = heiko::api::multisig_as_multi_encoded_call::<HeikoRuntime>(
         threshold,
         others,
         when,
-        sudo_call,
+        call,
         false,
         1_000_000_000_000,
-    )?;
+    );
 
     // 1.2 initial the multisg call
     let result = subxt_client.watch(mc, signer).await?;
-    println!("multisig_as_multi_call result {:?}", result);
-    println!("---------- end create multi-signature transaction ----------");
+    info!("multisig_as_multi_call result {:?}", result);
     Ok(call_hash)
 }
 
@@ -119,45 +98,32 @@ pub(crate) async fn do_first_process_pending_unstake(
     others: Vec<AccountId>,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     agent: AccountId,
     owner: AccountId,
     era_index: u32,
     amount: Amount,
     threshold: u16,
 ) -> Result<[u8; 32], Error> {
-    println!("[+] Create first process_pending_unstake transaction");
+    info!("Create first process_pending_unstake transaction");
 
-    println!("---------- start create multi-signature transaction ----------");
     // 1.1 construct balance transfer call
     let inner_call = heiko::api::liquid_staking_process_pending_unstake_call::<HeikoRuntime>(
         agent, owner, era_index, amount,
     );
-    let inner_call_encoded = subxt_client
-        .encode(inner_call)
-        .map_err(|e| Error::SubxtError(e))?;
-    let sudo_call = sudo::SudoCall::<HeikoRuntime> {
-        _runtime: PhantomData,
-        call: &inner_call_encoded,
-    };
-
-    let mc =
-        heiko::api::multisig_approve_as_multi_call::<HeikoRuntime, sudo::SudoCall<HeikoRuntime>>(
-            subxt_client,
-            threshold,
-            others,
-            None,
-            sudo_call.clone(),
-            0u64,
-        )?;
+    let call = origin.wrap(subxt_client, inner_call)?;
+
+    let mc = heiko::api::multisig_approve_as_multi_encoded_call::<HeikoRuntime>(
+        threshold, others, None, &call, 0u64,
+    );
 
     // 1.2 initial the multisg call
     let result = subxt_client.watch(mc, signer).await?;
-    println!("multisig_approve_as_multi_call result {:?}", result);
+    info!("multisig_approve_as_multi_call result {:?}", result);
 
     // get account_id of multi address
-    let call_hash = kusama::api::multisig_call_hash(subxt_client, sudo_call)?;
-    println!("call hash {:?}", format!("0x{}", hex::encode(call_hash)));
-    println!("---------- end create multi-signature transaction ----------");
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
     Ok(call_hash)
 }
 
@@ -167,53 +133,43 @@ pub(crate) async fn do_last_process_pending_unstake(
     multi_account_id: AccountId,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     agent: AccountId,
     owner: AccountId,
     era_index: u32,
     amount: Amount,
     threshold: u16,
 ) -> Result<[u8; 32], Error> {
-    println!("[+] Crate last process_pending_unstake transaction");
-    println!("---------- start create multi-signature transaction ----------");
+    info!("Create last process_pending_unstake transaction");
     // construct process pending unstake call
     let inner_call = heiko::api::liquid_staking_process_pending_unstake_call::<HeikoRuntime>(
         agent, owner, era_index, amount,
     );
 
-    let inner_call_encoded = subxt_client
-        .encode(inner_call)
-        .map_err(|e| Error::SubxtError(e))?;
-    let sudo_call = sudo::SudoCall::<HeikoRuntime> {
-        _runtime: PhantomData,
-        call: &inner_call_encoded,
-    };
+    let call = origin.wrap(subxt_client, inner_call)?;
 
     // check if timepoint already exist.
-    let call_hash =
-        heiko::api::multisig_call_hash(subxt_client, sudo_call.clone()).map_err(|_e| {
-            Error::SubxtError(SubError::Other("failed to load get call hash".to_string()))
-        })?;
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
 
     // let when = get_time_point::<HeikoRuntime>(subxt_client, account_id.clone(), call_hash).await;
     let when =
         get_last_time_point::<HeikoRuntime>(subxt_client, multi_account_id.clone(), call_hash)
             .await;
-    println!("multisig timepoint{:?}", when);
+    info!("multisig timepoint{:?}", when);
 
-    let mc = kusama::api::multisig_as_multi_call::<HeikoRuntime, sudo::SudoCall<HeikoRuntime>>(
-        subxt_client,
+    let mc = heiko::api::multisig_as_multi_encoded_call::<HeikoRuntime>(
         threshold,
         others,
         when,
-        sudo_call,
+        call,
         false,
         1_000_000_000_000,
-    )?;
+    );
 
     // 1.2 initial the multisg call
     let result = subxt_client.watch(mc, signer).await?;
-    println!("multisig_as_multi_call result {:?}", result);
-    println!("---------- end create multi-signature transaction ----------");
+    info!("multisig_as_multi_call result {:?}", result);
     Ok(call_hash)
 }
 
@@ -222,50 +178,34 @@ pub(crate) async fn do_first_finish_processed_unstake(
     others: Vec<AccountId>,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     agent: AccountId,
     owner: AccountId,
     amount: Amount,
     threshold: u16,
 ) -> Result<[u8; 32], Error> {
-    println!("[+] Create first finish_processed_unstake transaction");
+    info!("Create first finish_processed_unstake transaction");
 
-    println!("---------- start create multi-signature transaction ----------");
     // 1.1 construct balance transfer call
     let inner_call = heiko::api::liquid_staking_finish_processed_unstake_call::<HeikoRuntime>(
         agent, owner, amount,
     );
-    let inner_call_encoded = subxt_client
-        .encode(inner_call)
-        .map_err(|e| Error::SubxtError(e))?;
-    let sudo_call = sudo::SudoCall::<HeikoRuntime> {
-        _runtime: PhantomData,
-        call: &inner_call_encoded,
-    };
-
-    let mc =
-        heiko::api::multisig_approve_as_multi_call::<HeikoRuntime, sudo::SudoCall<HeikoRuntime>>(
-            subxt_client,
-            threshold,
-            others,
-            None,
-            sudo_call.clone(),
-            0u64,
-        )?;
+    let call = origin.wrap(subxt_client, inner_call)?;
+
+    let mc = heiko::api::multisig_approve_as_multi_encoded_call::<HeikoRuntime>(
+        threshold, others, None, &call, 0u64,
+    );
 
     // 1.2 initial the multisg call
     let result = subxt_client.watch(mc, signer).await?;
-    println!(
+    info!(
         "[finish_processed_unstake] multisig_approve_as_multi_call result {:?}",
         result
     );
 
     // get account_id of multi address
-    let call_hash = kusama::api::multisig_call_hash(subxt_client, sudo_call)?;
-    println!(
-        "[finish_processed_unstake] call hash {:?}",
-        format!("0x{}", hex::encode(call_hash))
-    );
-    println!("---------- end create multi-signature transaction ----------");
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
     Ok(call_hash)
 }
 
@@ -275,64 +215,118 @@ pub(crate) async fn do_last_finish_processed_unstake(
     multi_account_id: AccountId,
     subxt_client: &Client<HeikoRuntime>,
     signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    origin: DispatchOrigin,
     agent: AccountId,
     owner: AccountId,
     amount: Amount,
     threshold: u16,
 ) -> Result<[u8; 32], Error> {
-    println!("[+] Crate last finish_processed_unstake transaction");
-    println!("---------- start create multi-signature transaction ----------");
+    info!("Create last finish_processed_unstake transaction");
     // construct process pending unstake call
     let inner_call = heiko::api::liquid_staking_finish_processed_unstake_call::<HeikoRuntime>(
         agent, owner, amount,
     );
 
-    let inner_call_encoded = subxt_client
-        .encode(inner_call)
-        .map_err(|e| Error::SubxtError(e))?;
-    let sudo_call = sudo::SudoCall::<HeikoRuntime> {
-        _runtime: PhantomData,
-        call: &inner_call_encoded,
-    };
+    let call = origin.wrap(subxt_client, inner_call)?;
 
     // check if timepoint already exist.
-    let call_hash =
-        heiko::api::multisig_call_hash(subxt_client, sudo_call.clone()).map_err(|_e| {
-            Error::SubxtError(SubError::Other("failed to load get call hash".to_string()))
-        })?;
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
 
     // let when = get_time_point::<HeikoRuntime>(subxt_client, account_id.clone(), call_hash).await;
     let when =
         get_last_time_point::<HeikoRuntime>(subxt_client, multi_account_id.clone(), call_hash)
             .await;
-    println!("multisig timepoint{:?}", when);
+    info!("multisig timepoint{:?}", when);
 
-    let mc = kusama::api::multisig_as_multi_call::<HeikoRuntime, sudo::SudoCall<HeikoRuntime>>(
-        subxt_client,
+    let mc = heiko::api::multisig_as_multi_encoded_call::<HeikoRuntime>(
         threshold,
         others,
         when,
-        sudo_call,
+        call,
         false,
         1_000_000_000_000,
-    )?;
+    );
 
     // 1.2 initial the multisg call
     let result = subxt_client.watch(mc, signer).await?;
-    println!(
+    info!(
         "[finish_processed_unstake] multisig_as_multi_call result {:?}",
         result
     );
-    println!("---------- end create multi-signature transaction ----------");
     Ok(call_hash)
 }
 
+/// The first wallet to transfer the withdrawn amount to the relay stash through XCM. No need use
+/// 'TimePoint' and call 'approve_as_multi'.
+pub(crate) async fn do_first_transfer_to_relay_chain(
+    others: Vec<AccountId>,
+    subxt_client: &Client<HeikoRuntime>,
+    signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    relay_transfer: &RelayTransfer,
+    amount: Amount,
+    threshold: u16,
+) -> Result<[u8; 32], Error> {
+    info!("Create first transfer to relay chain transaction");
+    // the multisig account transfers its own tokens, no dispatch origin is needed
+    let call = relay_transfer.call(subxt_client, amount)?;
+    let mc = heiko::api::multisig_approve_as_multi_encoded_call::<HeikoRuntime>(
+        threshold, others, None, &call, 0u64,
+    );
+    let result = subxt_client.watch(mc, signer).await?;
+    info!(
+        "[transfer_to_relay_chain] multisig_approve_as_multi_call result {:?}",
+        result
+    );
+
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
+    Ok(call_hash)
+}
+
+/// If the wallet is the last one need to get 'TimePoint' and call 'as_multi'.
+pub(crate) async fn do_last_transfer_to_relay_chain(
+    others: Vec<AccountId>,
+    multi_account_id: AccountId,
+    subxt_client: &Client<HeikoRuntime>,
+    signer: &(dyn Signer<HeikoRuntime> + Send + Sync),
+    relay_transfer: &RelayTransfer,
+    amount: Amount,
+    threshold: u16,
+) -> Result<[u8; 32], Error> {
+    info!("Create last transfer to relay chain transaction");
+    let call = relay_transfer.call(subxt_client, amount)?;
+    let call_hash = heiko::api::multisig_encoded_call_hash(&call);
+    record_call_hash(&call_hash);
+    let when =
+        get_last_time_point::<HeikoRuntime>(subxt_client, multi_account_id.clone(), call_hash)
+            .await;
+    info!("multisig timepoint{:?}", when);
+
+    let mc = heiko::api::multisig_as_multi_encoded_call::<HeikoRuntime>(
+        threshold,
+        others,
+        when,
+        call,
+        false,
+        1_000_000_000_000,
+    );
+    let result = subxt_client.watch(mc, signer).await?;
+    info!(
+        "[transfer_to_relay_chain] multisig_as_multi_call result {:?}",
+        result
+    );
+    Ok(call_hash)
+}
+
+/// The multisig is opened after the block the task was decided at, so it is looked up at the
+/// latest block.
 pub(crate) async fn get_last_time_point<T: Runtime + Multisig>(
     subxt_client: &Client<T>,
     multisig_account: T::AccountId,
     call_hash: [u8; 32],
 ) -> Option<kusama::api::Timepoint<T::BlockNumber>> {
-    println!("get time point, waiting...");
+    info!("get time point, waiting...");
     loop {
         let store = kusama::api::MultisigsStore::<T> {
             multisig_account: multisig_account.clone(),
@@ -341,7 +335,7 @@ pub(crate) async fn get_last_time_point<T: Runtime + Multisig>(
         if let Some(Some(kusama::api::MultisigData { when, .. })) = subxt_client
             .fetch(&store, None)
             .await
-            .map_err(|e| println!("error get_time_point: {:?}", e))
+            .map_err(|e| warn!("error get_time_point: {:?}", e))
             .ok()
         {
             return Some(when);
@@ -356,7 +350,7 @@ pub(crate) async fn wait_transfer_finished(
     call_hash: [u8; 32],
 ) -> Result<(), Error> {
     // todo check if the transaction is in block
-    println!("transferring, waiting...");
+    info!("transferring, waiting...");
     loop {
         let store = kusama::api::MultisigsStore::<HeikoRuntime> {
             multisig_account: account_id.clone(),
@@ -5,4 +5,6 @@ use thiserror::Error as ThisError;
 pub enum Error {
     #[error("Substrate Subxt Error: `{0:?}`")]
     SubxtError(#[from] SubxtError),
+    #[error("Serde Json Error: `{0:?}`")]
+    SerdeJsonError(#[from] serde_json::Error),
 }
@@ -1,5 +1,6 @@
 pub use super::balances::balances_transfer_call;
-pub use super::currencies::currencies_transfer_call;
+pub use super::currencies::{currencies_transfer_call, DepositedEvent};
+pub use super::general_council::{general_council_propose_call, ProposeCall};
 pub use super::liquid_staking::{
     liquid_staking_finish_processed_unstake_call, liquid_staking_process_pending_unstake_call,
     liquid_staking_record_rewards_call, liquid_staking_record_slash_call,
@@ -7,10 +8,13 @@ pub use super::liquid_staking::{
     TotalStakingAssetStore, TotalVoucherStore,
 };
 pub use super::multisig::{
-    multisig_approve_as_multi_call, multisig_as_multi_call, multisig_call_hash, MultisigData,
+    multisig_approve_as_multi_call, multisig_approve_as_multi_encoded_call, multisig_as_multi_call,
+    multisig_as_multi_encoded_call, multisig_call_hash, multisig_encoded_call_hash, MultisigData,
     MultisigsStore,
 };
 pub use super::nominee_election::{NomineeElection, ValidatorInfo, ValidatorSet};
 pub use super::orml_tokens::AccountsStore;
+pub use super::parachain_info::ParachainIdStore;
 pub use super::system::AccountStore;
-pub use super::xcm_pallet::reserve_transfer_assets_call;
+pub use super::xcm_pallet::{reserve_transfer_assets_call, XcmVersion};
+pub use super::xtokens::{xtokens_relay_chain_dest, xtokens_transfer_to_relay_chain_call};
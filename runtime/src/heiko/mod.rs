@@ -2,9 +2,12 @@ pub mod api;
 pub mod runtime;
 use crate::pallets::balances;
 use crate::pallets::currencies;
+use crate::pallets::general_council;
 use crate::pallets::liquid_staking;
 use crate::pallets::multisig;
 use crate::pallets::nominee_election;
 use crate::pallets::orml_tokens;
+use crate::pallets::parachain_info;
 use crate::pallets::system;
 use crate::pallets::xcm_pallet;
+use crate::pallets::xtokens;
@@ -1,10 +1,13 @@
 use super::currencies::Currencies;
+use super::general_council::GeneralCouncil;
 use super::liquid_staking::LiquidStaking;
 use super::multisig::Multisig;
 use super::nominee_election::NomineeElection;
 use super::orml_tokens::Tokens;
+use super::parachain_info::ParachainInfo;
 use super::system::System;
 use crate::pallets::xcm_pallet::XcmPallet;
+use crate::pallets::xtokens::XTokens;
 pub use parallel_primitives::CurrencyId;
 pub use substrate_subxt::NodeTemplateRuntime as HeikoRuntime;
 
@@ -25,3 +28,9 @@ impl System for HeikoRuntime {}
 impl Currencies for HeikoRuntime {}
 
 impl XcmPallet for HeikoRuntime {}
+
+impl GeneralCouncil for HeikoRuntime {}
+
+impl XTokens for HeikoRuntime {}
+
+impl ParachainInfo for HeikoRuntime {}
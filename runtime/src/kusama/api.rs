@@ -1,12 +1,23 @@
-pub use super::balances::balances_transfer_call;
+pub use super::balances::{balances_transfer_call, DepositEvent};
 pub use super::currencies::currencies_transfer_call;
 pub use super::multisig::{
-    multisig_approve_as_multi_call, multisig_as_multi_call, multisig_call_hash, MultisigData,
+    multisig_approve_as_multi_call, multisig_approve_as_multi_encoded_call, multisig_as_multi_call,
+    multisig_as_multi_encoded_call, multisig_call_hash, multisig_encoded_call_hash, MultisigData,
     MultisigsStore, Timepoint,
 };
+pub use super::proxy::{
+    proxy_add_proxy_call, proxy_call, proxy_remove_proxy_call, AddProxyCall, ProxiesStore,
+    ProxyCall, ProxyDefinition, ProxyType, RemoveProxyCall,
+};
 pub use super::staking::{
-    staking_bond_call, staking_bond_extra_call, staking_unbond_call,
-    staking_withdraw_unbonded_call, BondExtraCall, BondedStore, CurrentEraStore, UnbondCall,
+    staking_bond_call, staking_bond_extra_call, staking_nominate_call, staking_unbond_call,
+    staking_withdraw_unbonded_call, BondExtraCall, BondedStore, CurrentEraStore,
+    MinNominatorBondStore, SlashingSpans, SlashingSpansStore, UnbondCall,
+};
+pub use super::system::{system_remark_call, AccountStore, RemarkCall};
+pub use super::utility::{
+    utility_batch_all_call, utility_batch_call, utility_encode_call, BatchAllCall, BatchCall,
+};
+pub use super::xcm_pallet::{
+    limited_reserve_transfer_assets_call, reserve_transfer_assets_call, XcmTransferCall, XcmVersion,
 };
-pub use super::system::AccountStore;
-pub use super::xcm_pallet::reserve_transfer_assets_call;
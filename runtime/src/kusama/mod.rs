@@ -3,6 +3,8 @@ pub mod runtime;
 use crate::pallets::balances;
 use crate::pallets::currencies;
 use crate::pallets::multisig;
+use crate::pallets::proxy;
 use crate::pallets::staking;
 use crate::pallets::system;
+use crate::pallets::utility;
 use crate::pallets::xcm_pallet;
@@ -1,7 +1,9 @@
 use super::currencies::Currencies;
 use super::multisig::Multisig;
+use super::proxy::Proxy;
 use super::staking::Staking;
 use super::system::System;
+use super::utility::Utility;
 use crate::pallets::xcm_pallet::XcmPallet;
 pub use substrate_subxt::DefaultNodeRuntime as KusamaRuntime;
 
@@ -10,3 +12,5 @@ impl System for KusamaRuntime {}
 impl Staking for KusamaRuntime {}
 impl Currencies for KusamaRuntime {}
 impl XcmPallet for KusamaRuntime {}
+impl Proxy for KusamaRuntime {}
+impl Utility for KusamaRuntime {}
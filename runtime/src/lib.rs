@@ -5,3 +5,12 @@ pub mod error;
 pub mod heiko;
 pub mod kusama;
 pub mod pallets;
+pub mod rpc;
+
+/// The runtime of every relay chain profile. Polkadot has the same pallets, call indices and
+/// types as Kusama, so it is not a runtime of its own.
+pub type RelayRuntime = kusama::runtime::KusamaRuntime;
+
+/// The runtime of every para chain profile. Parallel and the dev chain run the same pallets as
+/// Heiko, so they share its types.
+pub type ParaRuntime = heiko::runtime::HeikoRuntime;
@@ -1,5 +1,7 @@
+use codec::Decode;
 use substrate_subxt::balances::{Balances, TransferCall};
 use substrate_subxt::system::System;
+use substrate_subxt::Event;
 
 pub fn balances_transfer_call<'a, T: Balances + System>(
     to: &'a <T as System>::Address,
@@ -7,3 +9,17 @@ pub fn balances_transfer_call<'a, T: Balances + System>(
 ) -> TransferCall<T> {
     TransferCall::<T> { to, amount }
 }
+
+/// Deposit event, some amount was deposited into the account, e.g. by an XCM transfer.
+#[derive(Clone, Debug, Eq, PartialEq, Decode)]
+pub struct DepositEvent<T: Balances> {
+    /// Account the amount was deposited into.
+    pub who: T::AccountId,
+    /// Amount of balance that was deposited.
+    pub amount: T::Balance,
+}
+
+impl<T: Balances> Event<T> for DepositEvent<T> {
+    const MODULE: &'static str = "Balances";
+    const EVENT: &'static str = "Deposit";
+}
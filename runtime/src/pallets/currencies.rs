@@ -1,4 +1,4 @@
-use codec::Encode;
+use codec::{Decode, Encode};
 use parallel_primitives::CurrencyId;
 use substrate_subxt::balances::Balances;
 use substrate_subxt::system::System;
@@ -27,3 +27,13 @@ pub fn currencies_transfer_call<'a, T: Currencies + System>(
         amount,
     }
 }
+
+/// Deposited event, e.g. the tokens transferred from relay chain through XCM.
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct DepositedEvent<T: Currencies> {
+    pub currency_id: CurrencyId,
+    /// Account the amount was deposited into.
+    pub who: T::AccountId,
+    /// Amount of balance that was deposited.
+    pub amount: T::Balance,
+}
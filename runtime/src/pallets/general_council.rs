@@ -0,0 +1,30 @@
+use codec::Encode;
+use core::marker::PhantomData;
+use substrate_subxt::{system::System, Call, Encoded};
+
+#[module]
+pub trait GeneralCouncil: System {}
+
+/// Propose the call to the council, it is executed at once if the threshold is below 2.
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct ProposeCall<T: GeneralCouncil> {
+    #[codec(compact)]
+    pub threshold: u32,
+    pub proposal: Encoded,
+    #[codec(compact)]
+    pub length_bound: u32,
+    pub _runtime: PhantomData<T>,
+}
+
+pub fn general_council_propose_call<T: GeneralCouncil>(
+    threshold: u32,
+    proposal: Encoded,
+) -> ProposeCall<T> {
+    let length_bound = proposal.0.len() as u32;
+    ProposeCall::<T> {
+        threshold,
+        proposal,
+        length_bound,
+        _runtime: PhantomData,
+    }
+}
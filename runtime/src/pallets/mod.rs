@@ -1,10 +1,15 @@
 pub mod balances;
 pub mod currencies;
+pub mod general_council;
 pub mod liquid_staking;
 pub mod multisig;
 pub mod nominee_election;
 pub mod orml_tokens;
+pub mod parachain_info;
+pub mod proxy;
 pub mod staking;
 pub mod system;
+pub mod utility;
 pub mod xcm_pallet;
+pub mod xtokens;
 use crate::error;
@@ -2,7 +2,7 @@ use super::error::Error;
 use codec::{Decode, Encode};
 use frame_support::weights::Weight;
 use sp_core::hashing::blake2_256;
-use substrate_subxt::{balances::Balances, Call, Client, Runtime};
+use substrate_subxt::{balances::Balances, Call, Client, Encoded, Runtime};
 
 #[derive(Copy, Clone, Eq, PartialEq, Encode, Decode, Default, Debug)]
 pub struct Timepoint<BlockNumber> {
@@ -114,3 +114,42 @@ pub fn multisig_call_hash<T: Multisig + Runtime, C: Call<T> + Send + Sync>(
     let call_hash = blake2_256(&call_encoded.encode());
     Ok(call_hash)
 }
+
+/// The hash of the call which is already encoded, e.g. wrapped for a dispatch origin.
+pub fn multisig_encoded_call_hash(call: &Encoded) -> [u8; 32] {
+    blake2_256(&call.0)
+}
+
+pub fn multisig_approve_as_multi_encoded_call<T: Multisig>(
+    threshold: u16,
+    other_signatories: Vec<T::AccountId>,
+    maybe_timepoint: Option<Timepoint<T::BlockNumber>>,
+    call: &Encoded,
+    max_weight: Weight,
+) -> ApproveAsMultiCall<T> {
+    ApproveAsMultiCall::<T> {
+        threshold,
+        other_signatories,
+        maybe_timepoint,
+        call_hash: multisig_encoded_call_hash(call),
+        max_weight,
+    }
+}
+
+pub fn multisig_as_multi_encoded_call<T: Multisig>(
+    threshold: u16,
+    other_signatories: Vec<T::AccountId>,
+    maybe_timepoint: Option<Timepoint<T::BlockNumber>>,
+    call: Encoded,
+    store_call: bool,
+    max_weight: Weight,
+) -> AsMultiCall<T> {
+    AsMultiCall::<T> {
+        threshold,
+        other_signatories,
+        maybe_timepoint,
+        call: call.0,
+        store_call,
+        max_weight,
+    }
+}
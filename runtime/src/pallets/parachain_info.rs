@@ -0,0 +1,14 @@
+use codec::{Decode, Encode};
+use core::marker::PhantomData;
+use substrate_subxt::system::System;
+
+#[module]
+pub trait ParachainInfo: System {}
+
+/// The parachain id of para chain on relay chain.
+#[derive(Encode, Decode, Copy, Clone, Debug, Default, Store)]
+pub struct ParachainIdStore<T: ParachainInfo> {
+    #[store(returns = u32)]
+    /// Marker for the runtime
+    pub _runtime: PhantomData<T>,
+}
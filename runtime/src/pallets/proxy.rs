@@ -0,0 +1,133 @@
+use super::error::Error;
+use codec::{Decode, Encode};
+use substrate_subxt::{balances::Balances, Call, Client, Encoded, Runtime};
+
+/// The proxy types of Kusama and Polkadot, see `runtime_common::ProxyType`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub enum ProxyType {
+    #[codec(index = 0)]
+    Any,
+    #[codec(index = 1)]
+    NonTransfer,
+    #[codec(index = 2)]
+    Governance,
+    #[codec(index = 3)]
+    Staking,
+    #[codec(index = 5)]
+    IdentityJudgement,
+    #[codec(index = 6)]
+    CancelProxy,
+    #[codec(index = 7)]
+    Auction,
+}
+
+impl ProxyType {
+    /// Whether the proxy of this type can dispatch the staking calls.
+    pub fn allows_staking(&self) -> bool {
+        matches!(self, ProxyType::Any | ProxyType::Staking)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub struct ProxyDefinition<AccountId, BlockNumber> {
+    /// The account which may act on behalf of another.
+    pub delegate: AccountId,
+    /// The calls which the delegate may dispatch.
+    pub proxy_type: ProxyType,
+    /// The number of blocks an announcement must be in place for before the call is dispatched.
+    pub delay: BlockNumber,
+}
+
+#[derive(Encode, Clone, Debug, Eq, PartialEq, Store)]
+pub struct ProxiesStore<T: Proxy> {
+    #[store(returns = (Vec<ProxyDefinition<T::AccountId, T::BlockNumber>>, T::Balance))]
+    pub real: T::AccountId,
+}
+
+#[module]
+pub trait Proxy: Balances {}
+
+/// Dispatch the call on behalf of `real`, the call is encoded without the length prefix.
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct ProxyCall<T: Proxy> {
+    pub real: T::AccountId,
+    pub force_proxy_type: Option<ProxyType>,
+    pub call: Encoded,
+}
+
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct AddProxyCall<T: Proxy> {
+    pub delegate: T::AccountId,
+    pub proxy_type: ProxyType,
+    pub delay: T::BlockNumber,
+}
+
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct RemoveProxyCall<T: Proxy> {
+    pub delegate: T::AccountId,
+    pub proxy_type: ProxyType,
+    pub delay: T::BlockNumber,
+}
+
+pub fn proxy_call<T: Proxy + Runtime, C: Call<T> + Send + Sync>(
+    subxt_client: &Client<T>,
+    real: T::AccountId,
+    force_proxy_type: Option<ProxyType>,
+    call: C,
+) -> Result<ProxyCall<T>, Error> {
+    let call = subxt_client
+        .encode(call)
+        .map_err(|e| Error::SubxtError(e))?;
+    Ok(ProxyCall::<T> {
+        real,
+        force_proxy_type,
+        call,
+    })
+}
+
+pub fn proxy_add_proxy_call<T: Proxy>(
+    delegate: T::AccountId,
+    proxy_type: ProxyType,
+    delay: T::BlockNumber,
+) -> AddProxyCall<T> {
+    AddProxyCall::<T> {
+        delegate,
+        proxy_type,
+        delay,
+    }
+}
+
+pub fn proxy_remove_proxy_call<T: Proxy>(
+    delegate: T::AccountId,
+    proxy_type: ProxyType,
+    delay: T::BlockNumber,
+) -> RemoveProxyCall<T> {
+    RemoveProxyCall::<T> {
+        delegate,
+        proxy_type,
+        delay,
+    }
+}
+
+#[test]
+fn test_proxy_type_encode() {
+    assert_eq!(ProxyType::Staking.encode(), vec![3]);
+    assert_eq!(ProxyType::Auction.encode(), vec![7]);
+    assert!(ProxyType::decode(&mut &[4u8][..]).is_err());
+    // the proxies of an account decode even if one of them is of a type the client doesn't use
+    let proxies = vec![
+        ProxyDefinition {
+            delegate: 1u64,
+            proxy_type: ProxyType::Auction,
+            delay: 0u32,
+        },
+        ProxyDefinition {
+            delegate: 2u64,
+            proxy_type: ProxyType::Staking,
+            delay: 0u32,
+        },
+    ];
+    let decoded = Vec::<ProxyDefinition<u64, u32>>::decode(&mut &proxies.encode()[..]).unwrap();
+    assert_eq!(decoded, proxies);
+    assert!(!decoded[0].proxy_type.allows_staking());
+}
@@ -13,6 +13,40 @@ pub struct CurrentEraStore<T: Staking> {
     pub _runtime: PhantomData<T>,
 }
 
+/// The least amount a nominator must bond.
+#[derive(Encode, Decode, Copy, Clone, Debug, Default, Store)]
+pub struct MinNominatorBondStore<T: Staking> {
+    #[store(returns = u128)]
+    /// Marker for the runtime
+    pub _runtime: PhantomData<T>,
+}
+
+/// The slashing spans of a stash, see `pallet_staking::slashing::SlashingSpans`.
+#[derive(Encode, Decode, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SlashingSpans {
+    /// The index of the current slashing span of the stash.
+    pub span_index: u32,
+    /// The start era of the most recent (ongoing) slashing span.
+    pub last_start: u32,
+    /// The last era at which a non-zero slash occurred.
+    pub last_nonzero_slash: u32,
+    /// The start eras of all previous slashing spans, most recent first.
+    pub prior: Vec<u32>,
+}
+
+impl SlashingSpans {
+    /// The number of slashing spans, which `withdraw_unbonded` expects as `num_slashing_spans`.
+    pub fn num_slashing_spans(&self) -> u32 {
+        self.prior.len() as u32 + 1
+    }
+}
+
+#[derive(Encode, Copy, Clone, Debug, Hash, PartialEq, Eq, Ord, PartialOrd, Store)]
+pub struct SlashingSpansStore<T: Staking> {
+    #[store(returns = Option<SlashingSpans>)]
+    pub stash: T::AccountId,
+}
+
 /// Reward event.
 #[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
 pub struct RewardEvent<T: Staking> {
@@ -1,4 +1,5 @@
 use codec::Encode;
+use core::marker::PhantomData;
 use frame_system::AccountInfo;
 use substrate_subxt::balances::Balances;
 use substrate_subxt::system::System as SubxtSystem;
@@ -11,3 +12,17 @@ pub struct AccountStore<T: System> {
 
 #[module]
 pub trait System: Balances + SubxtSystem {}
+
+/// Make an on-chain remark, e.g. to tag a batch with the block it was derived from.
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct RemarkCall<T: System> {
+    pub remark: Vec<u8>,
+    pub _runtime: PhantomData<T>,
+}
+
+pub fn system_remark_call<T: System>(remark: Vec<u8>) -> RemarkCall<T> {
+    RemarkCall::<T> {
+        remark,
+        _runtime: PhantomData,
+    }
+}
@@ -0,0 +1,43 @@
+use super::error::Error;
+use codec::Encode;
+use core::marker::PhantomData;
+use substrate_subxt::{system::System, Call, Client, Encoded, Runtime};
+
+#[module]
+pub trait Utility: System {}
+
+/// Dispatch the calls in order, stops at the first failed call without reverting the others.
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct BatchCall<T: Utility> {
+    pub calls: Vec<Encoded>,
+    pub _runtime: PhantomData<T>,
+}
+
+/// Dispatch the calls in order, all of them are reverted if any fails.
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct BatchAllCall<T: Utility> {
+    pub calls: Vec<Encoded>,
+    pub _runtime: PhantomData<T>,
+}
+
+/// Encode the call with the module and call index of runtime, to be put into a batch.
+pub fn utility_encode_call<T: Utility + Runtime, C: Call<T> + Send + Sync>(
+    subxt_client: &Client<T>,
+    call: C,
+) -> Result<Encoded, Error> {
+    subxt_client.encode(call).map_err(|e| Error::SubxtError(e))
+}
+
+pub fn utility_batch_call<T: Utility>(calls: Vec<Encoded>) -> BatchCall<T> {
+    BatchCall::<T> {
+        calls,
+        _runtime: PhantomData,
+    }
+}
+
+pub fn utility_batch_all_call<T: Utility>(calls: Vec<Encoded>) -> BatchAllCall<T> {
+    BatchAllCall::<T> {
+        calls,
+        _runtime: PhantomData,
+    }
+}
@@ -1,13 +1,151 @@
+use super::error::Error;
 use codec::Encode;
+use core::fmt;
 use core::marker::PhantomData;
+use core::str::FromStr;
 use frame_support::weights::Weight;
 use substrate_subxt::balances::Balances;
 use substrate_subxt::system::System as SubxtSystem;
-use xcm::v0::{MultiAsset, MultiLocation};
+use substrate_subxt::{Client, Encoded, ExtrinsicSuccess, Runtime, Signer};
+use xcm::v0::{Junction, MultiAsset, MultiLocation, NetworkId};
 
 #[module]
 pub trait XcmPallet: Balances + SubxtSystem {}
 
+/// The XCM version of relay chain, which decides the call and payload of reserve transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XcmVersion {
+    /// Unversioned v0 payload of `reserve_transfer_assets`, before polkadot v0.9.9.
+    V0,
+    /// Versioned v1 payload of `reserve_transfer_assets` with the fee asset item.
+    V1,
+    /// Versioned v1 payload of `limited_reserve_transfer_assets` with the weight limit.
+    V2,
+}
+
+impl XcmVersion {
+    pub fn name(&self) -> &'static str {
+        match self {
+            XcmVersion::V0 => "v0",
+            XcmVersion::V1 => "v1",
+            XcmVersion::V2 => "v2",
+        }
+    }
+}
+
+impl fmt::Display for XcmVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for XcmVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v0" | "0" => Ok(XcmVersion::V0),
+            "v1" | "1" => Ok(XcmVersion::V1),
+            "v2" | "2" => Ok(XcmVersion::V2),
+            _ => Err(format!("unknown xcm version: {}", s)),
+        }
+    }
+}
+
+/// The subset of `xcm::v1` types the client sends, encoded the same as polkadot.
+pub mod v1 {
+    use super::{Encode, NetworkId, PhantomData, Weight, XcmPallet, MODULE};
+
+    #[derive(Clone, Debug, Eq, PartialEq, Encode)]
+    pub enum Junction {
+        #[codec(index = 0)]
+        Parachain(#[codec(compact)] u32),
+        #[codec(index = 1)]
+        AccountId32 { network: NetworkId, id: [u8; 32] },
+        #[codec(index = 3)]
+        AccountKey20 { network: NetworkId, key: [u8; 20] },
+        #[codec(index = 4)]
+        PalletInstance(u8),
+        #[codec(index = 5)]
+        GeneralIndex(#[codec(compact)] u128),
+        #[codec(index = 6)]
+        GeneralKey(Vec<u8>),
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Encode)]
+    pub enum Junctions {
+        #[codec(index = 0)]
+        Here,
+        #[codec(index = 1)]
+        X1(Junction),
+        #[codec(index = 2)]
+        X2(Junction, Junction),
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Encode)]
+    pub struct MultiLocation {
+        pub parents: u8,
+        pub interior: Junctions,
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Encode)]
+    pub enum AssetId {
+        #[codec(index = 0)]
+        Concrete(MultiLocation),
+        #[codec(index = 1)]
+        Abstract(Vec<u8>),
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Encode)]
+    pub enum Fungibility {
+        #[codec(index = 0)]
+        Fungible(#[codec(compact)] u128),
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Encode)]
+    pub struct MultiAsset {
+        pub id: AssetId,
+        pub fun: Fungibility,
+    }
+
+    /// The assets sorted and deduplicated, see `xcm::v1::MultiAssets`.
+    pub type MultiAssets = Vec<MultiAsset>;
+
+    #[derive(Call, Encode, Debug, Clone)]
+    pub struct ReserveTransferAssetsCall<T: XcmPallet> {
+        pub dest: super::VersionedMultiLocation,
+        pub beneficiary: super::VersionedMultiLocation,
+        pub assets: super::VersionedMultiAssets,
+        pub fee_asset_item: u32,
+        pub dest_weight: Weight,
+        pub _runtime: PhantomData<T>,
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Encode)]
+pub enum VersionedMultiLocation {
+    #[codec(index = 0)]
+    V0(MultiLocation),
+    #[codec(index = 1)]
+    V1(v1::MultiLocation),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Encode)]
+pub enum VersionedMultiAssets {
+    #[codec(index = 0)]
+    V0(Vec<MultiAsset>),
+    #[codec(index = 1)]
+    V1(v1::MultiAssets),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Encode)]
+pub enum WeightLimit {
+    #[codec(index = 0)]
+    Unlimited,
+    #[codec(index = 1)]
+    Limited(#[codec(compact)] Weight),
+}
+
 #[derive(Call, Encode, Debug, Clone)]
 pub struct ReserveTransferAssetsCall<T: XcmPallet> {
     pub dest: MultiLocation,
@@ -17,6 +155,16 @@ pub struct ReserveTransferAssetsCall<T: XcmPallet> {
     pub _runtime: PhantomData<T>,
 }
 
+#[derive(Call, Encode, Debug, Clone)]
+pub struct LimitedReserveTransferAssetsCall<T: XcmPallet> {
+    pub dest: VersionedMultiLocation,
+    pub beneficiary: VersionedMultiLocation,
+    pub assets: VersionedMultiAssets,
+    pub fee_asset_item: u32,
+    pub weight_limit: WeightLimit,
+    pub _runtime: PhantomData<T>,
+}
+
 pub fn reserve_transfer_assets_call<T: XcmPallet>(
     dest: MultiLocation,
     beneficiary: MultiLocation,
@@ -31,3 +179,127 @@ pub fn reserve_transfer_assets_call<T: XcmPallet>(
         _runtime: PhantomData,
     }
 }
+
+pub fn limited_reserve_transfer_assets_call<T: XcmPallet>(
+    dest: VersionedMultiLocation,
+    beneficiary: VersionedMultiLocation,
+    assets: VersionedMultiAssets,
+    fee_asset_item: u32,
+    weight_limit: WeightLimit,
+) -> LimitedReserveTransferAssetsCall<T> {
+    LimitedReserveTransferAssetsCall::<T> {
+        dest,
+        beneficiary,
+        assets,
+        fee_asset_item,
+        weight_limit,
+        _runtime: PhantomData,
+    }
+}
+
+/// The reserve transfer of the native asset of relay chain to an account of para chain, in the
+/// call and payload of the XCM version.
+#[derive(Debug, Clone)]
+pub enum XcmTransferCall<T: XcmPallet> {
+    V0(ReserveTransferAssetsCall<T>),
+    V1(v1::ReserveTransferAssetsCall<T>),
+    V2(LimitedReserveTransferAssetsCall<T>),
+}
+
+impl<T: XcmPallet + Runtime> XcmTransferCall<T> {
+    /// Transfer the amount to the beneficiary of para chain, the fees are paid by the asset of
+    /// `fee_asset_item` and the execution on para chain is limited to `dest_weight`.
+    pub fn to_para_chain(
+        version: XcmVersion,
+        para_id: u32,
+        beneficiary: [u8; 32],
+        amount: u128,
+        fee_asset_item: u32,
+        dest_weight: Weight,
+    ) -> Self {
+        if version == XcmVersion::V0 {
+            return XcmTransferCall::V0(reserve_transfer_assets_call::<T>(
+                MultiLocation::X1(Junction::Parachain(para_id)),
+                MultiLocation::X1(Junction::AccountId32 {
+                    network: NetworkId::Any,
+                    id: beneficiary,
+                }),
+                vec![MultiAsset::ConcreteFungible {
+                    id: MultiLocation::Null,
+                    amount,
+                }],
+                dest_weight,
+            ));
+        }
+
+        let location = |junction| v1::MultiLocation {
+            parents: 0,
+            interior: v1::Junctions::X1(junction),
+        };
+        let dest = VersionedMultiLocation::V1(location(v1::Junction::Parachain(para_id)));
+        let beneficiary = VersionedMultiLocation::V1(location(v1::Junction::AccountId32 {
+            network: NetworkId::Any,
+            id: beneficiary,
+        }));
+        let assets = VersionedMultiAssets::V1(vec![v1::MultiAsset {
+            id: v1::AssetId::Concrete(v1::MultiLocation {
+                parents: 0,
+                interior: v1::Junctions::Here,
+            }),
+            fun: v1::Fungibility::Fungible(amount),
+        }]);
+        match version {
+            XcmVersion::V1 => XcmTransferCall::V1(v1::ReserveTransferAssetsCall::<T> {
+                dest,
+                beneficiary,
+                assets,
+                fee_asset_item,
+                dest_weight,
+                _runtime: PhantomData,
+            }),
+            _ => XcmTransferCall::V2(limited_reserve_transfer_assets_call::<T>(
+                dest,
+                beneficiary,
+                assets,
+                fee_asset_item,
+                WeightLimit::Limited(dest_weight),
+            )),
+        }
+    }
+
+    /// Encode the call with the module and call index of runtime, e.g. to be put into a batch.
+    pub fn encode(self, subxt_client: &Client<T>) -> Result<Encoded, Error> {
+        match self {
+            XcmTransferCall::V0(call) => subxt_client.encode(call),
+            XcmTransferCall::V1(call) => subxt_client.encode(call),
+            XcmTransferCall::V2(call) => subxt_client.encode(call),
+        }
+        .map_err(|e| Error::SubxtError(e))
+    }
+
+    /// Submit the call and wait for its success.
+    pub async fn watch(
+        self,
+        subxt_client: &Client<T>,
+        signer: &(dyn Signer<T> + Send + Sync),
+    ) -> Result<ExtrinsicSuccess<T>, Error> {
+        match self {
+            XcmTransferCall::V0(call) => subxt_client.watch(call, signer).await,
+            XcmTransferCall::V1(call) => subxt_client.watch(call, signer).await,
+            XcmTransferCall::V2(call) => subxt_client.watch(call, signer).await,
+        }
+        .map_err(|e| Error::SubxtError(e))
+    }
+}
+
+#[test]
+fn test_versioned_encode() {
+    let dest = VersionedMultiLocation::V1(v1::MultiLocation {
+        parents: 0,
+        interior: v1::Junctions::X1(v1::Junction::Parachain(2000)),
+    });
+    // V1, parents, X1, Parachain and the compact parachain id
+    assert_eq!(dest.encode(), vec![1, 0, 1, 0, 0x41, 0x1f]);
+    assert_eq!(WeightLimit::Limited(1).encode(), vec![1, 4]);
+    assert_eq!("v2".parse::<XcmVersion>(), Ok(XcmVersion::V2));
+}
@@ -0,0 +1,81 @@
+use super::error::Error;
+use super::xcm_pallet::{v1, VersionedMultiLocation, XcmVersion};
+use codec::Encode;
+use frame_support::weights::Weight;
+use parallel_primitives::CurrencyId;
+use substrate_subxt::balances::Balances;
+use substrate_subxt::{Client, Encoded, Runtime};
+use xcm::v0::{Junction, MultiLocation, NetworkId};
+
+#[module]
+pub trait XTokens: Balances {}
+
+/// Transfer the tokens of para chain to another chain, `orml_xtokens` after polkadot v0.9.9.
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct TransferCall<T: XTokens> {
+    pub currency_id: CurrencyId,
+    pub amount: T::Balance,
+    pub dest: VersionedMultiLocation,
+    pub dest_weight: Weight,
+}
+
+/// The call of `orml_xtokens` with the unversioned destination, before polkadot v0.9.9.
+pub mod v0 {
+    use super::{CurrencyId, Encode, MultiLocation, Weight, XTokens, MODULE};
+
+    #[derive(Clone, Debug, PartialEq, Call, Encode)]
+    pub struct TransferCall<T: XTokens> {
+        pub currency_id: CurrencyId,
+        pub amount: T::Balance,
+        pub dest: MultiLocation,
+        pub dest_weight: Weight,
+    }
+}
+
+/// The account of relay chain as the destination of para chain in the XCM version.
+pub fn xtokens_relay_chain_dest(version: XcmVersion, account: [u8; 32]) -> VersionedMultiLocation {
+    let network = NetworkId::Any;
+    match version {
+        XcmVersion::V0 => VersionedMultiLocation::V0(MultiLocation::X2(
+            Junction::Parent,
+            Junction::AccountId32 {
+                network,
+                id: account,
+            },
+        )),
+        XcmVersion::V1 | XcmVersion::V2 => VersionedMultiLocation::V1(v1::MultiLocation {
+            parents: 1,
+            interior: v1::Junctions::X1(v1::Junction::AccountId32 {
+                network,
+                id: account,
+            }),
+        }),
+    }
+}
+
+/// Encode the call to transfer the amount of currency to the account of relay chain, which is
+/// dispatched by the multisig account.
+pub fn xtokens_transfer_to_relay_chain_call<T: XTokens + Runtime>(
+    subxt_client: &Client<T>,
+    version: XcmVersion,
+    currency_id: CurrencyId,
+    amount: T::Balance,
+    account: [u8; 32],
+    dest_weight: Weight,
+) -> Result<Encoded, Error> {
+    let call = match xtokens_relay_chain_dest(version, account) {
+        VersionedMultiLocation::V0(dest) => subxt_client.encode(v0::TransferCall::<T> {
+            currency_id,
+            amount,
+            dest,
+            dest_weight,
+        }),
+        dest => subxt_client.encode(TransferCall::<T> {
+            currency_id,
+            amount,
+            dest,
+            dest_weight,
+        }),
+    };
+    call.map_err(|e| Error::SubxtError(e))
+}
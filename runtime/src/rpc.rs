@@ -0,0 +1,87 @@
+use super::error::Error;
+use serde::{de, Deserialize, Deserializer};
+use sp_core::Bytes;
+use substrate_subxt::{Client, Runtime};
+
+/// Runtime version of chain, the part of `sp_version::RuntimeVersion` the client cares about.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeVersion {
+    /// Identifies the different runtimes, e.g. "kusama" or "heiko".
+    pub spec_name: String,
+    /// Version of the runtime specification, bumped on every runtime upgrade.
+    pub spec_version: u32,
+    /// Version of the extrinsic interface.
+    pub transaction_version: u32,
+}
+
+/// Fetch the runtime version at block `at`, the latest block if `None`.
+pub async fn runtime_version<T: Runtime>(
+    subxt_client: &Client<T>,
+    at: Option<T::Hash>,
+) -> Result<RuntimeVersion, Error> {
+    let params = &[serde_json::to_value(at)?];
+    let version = subxt_client
+        .rpc_client()
+        .request("state_getRuntimeVersion", params.into())
+        .await?;
+    Ok(version)
+}
+
+/// Dispatch info of an extrinsic, the part of `pallet_transaction_payment::RuntimeDispatchInfo`
+/// the client cares about.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeDispatchInfo {
+    /// Weight of the extrinsic.
+    pub weight: u64,
+    /// The inclusion fee of the extrinsic, without the tip.
+    #[serde(deserialize_with = "deserialize_balance")]
+    pub partial_fee: u128,
+}
+
+/// The balances are serialized as strings since they overflow the numbers of javascript,
+/// older nodes still serialize them as numbers.
+fn deserialize_balance<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Balance {
+        Number(u128),
+        String(String),
+    }
+
+    match Balance::deserialize(deserializer)? {
+        Balance::Number(n) => Ok(n),
+        Balance::String(s) => s.parse().map_err(de::Error::custom),
+    }
+}
+
+/// Query the dispatch info and fee of the encoded extrinsic at block `at`, the latest block if
+/// `None`.
+pub async fn query_info<T: Runtime>(
+    subxt_client: &Client<T>,
+    extrinsic: Vec<u8>,
+    at: Option<T::Hash>,
+) -> Result<RuntimeDispatchInfo, Error> {
+    let params = &[
+        serde_json::to_value(Bytes(extrinsic))?,
+        serde_json::to_value(at)?,
+    ];
+    let info = subxt_client
+        .rpc_client()
+        .request("payment_queryInfo", params.into())
+        .await?;
+    Ok(info)
+}
+
+#[test]
+fn test_dispatch_info() {
+    let info: RuntimeDispatchInfo = serde_json::from_str(
+        r#"{"weight":195000000,"class":"normal","partialFee":"15600000000000000000000"}"#,
+    )
+    .unwrap();
+    assert_eq!(info.partial_fee, 15_600_000_000_000_000_000_000);
+    let info: RuntimeDispatchInfo =
+        serde_json::from_str(r#"{"weight":195000000,"class":"normal","partialFee":1560}"#).unwrap();
+    assert_eq!(info.partial_fee, 1560);
+}